@@ -0,0 +1,28 @@
+# An example of a translator written in Python. Copy it to a `translators` directory
+# next to the config file to load it at startup, or load it with
+# `translator_load examples/translator.py`.
+# Requires Surfer to be built with the `python` feature.
+
+
+class Translator:
+    def name(self):
+        return "Example: valid and payload"
+
+    def translates(self, name, width):
+        # The most significant bit is the valid bit
+        return "yes" if width >= 2 else "no"
+
+    def signal_info(self, name, width):
+        return {"valid": "bool", "payload": "bits"}
+
+    def translate(self, name, width, value):
+        valid, payload = value[0], value[1:]
+        if valid not in "01":
+            return {"string": "undefined", "kind": "undef"}
+        return {
+            "struct": {
+                "valid": {"bits": valid},
+                "payload": {"bits": payload},
+            },
+            "kind": "normal" if valid == "1" else "dontcare",
+        }
@@ -0,0 +1,390 @@
+//! Analyses which scan signals for likely problems: glitches, i.e. pulses shorter than a
+//! threshold, and intervals where values are unknown, such as X propagating from an
+//! uninitialised register after reset. The findings are listed in a panel and marked on
+//! the canvas.
+use eframe::egui;
+use eframe::epaint::{Rect, Rounding};
+use itertools::Itertools;
+use num::{BigInt, BigUint};
+
+use crate::decoders::ValueTrace;
+use crate::displayed_item::DisplayedItem;
+use crate::message::Message;
+use crate::time::time_string;
+use crate::translation::ValueKind;
+use crate::view::{DrawingContext, ItemDrawingInfo};
+use crate::wave_container::{ScopeName, VarName};
+use crate::{State, WaveData};
+
+#[derive(Debug, Clone)]
+pub enum AnalysisTarget {
+    /// All displayed signals
+    Displayed,
+    /// All signals in the scope
+    Scope(ScopeName),
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalysisSettings {
+    pub target: AnalysisTarget,
+    /// Pulses shorter than this many timestamps are glitches
+    pub min_pulse: BigUint,
+    /// Unknown values before this time, typically the end of reset, are not reported
+    pub after: BigUint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindingKind {
+    Glitch,
+    Unknown(ValueKind),
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub signal: VarName,
+    pub start: BigUint,
+    pub end: BigUint,
+    pub kind: FindingKind,
+}
+
+/// The kind of unknown values in `bits`, or `None` if they are all known
+fn unknown_kind(bits: &str) -> Option<ValueKind> {
+    if bits.contains(['x', 'u']) {
+        Some(ValueKind::Undef)
+    } else if bits.contains('z') {
+        Some(ValueKind::HighImp)
+    } else if bits.contains('w') {
+        Some(ValueKind::Weak)
+    } else {
+        None
+    }
+}
+
+/// Values which last for less than `min_pulse` before the signal returns to its previous
+/// value. Rewrites of the same value are not changes, and values of signals which change
+/// on every tick, such as counters, are not pulses since they do not return
+fn glitches(trace: &ValueTrace, min_pulse: &BigUint) -> Vec<(BigUint, BigUint)> {
+    trace
+        .iter()
+        .dedup_by(|(_, a), (_, b)| a == b)
+        .tuple_windows()
+        .filter(|((_, before), (start, _), (end, after))| {
+            before == after && &(end - start) < min_pulse
+        })
+        .map(|(_, (start, _), (end, _))| (start.clone(), end.clone()))
+        .collect()
+}
+
+/// The intervals after `after` in which `trace` has unknown values. The last value
+/// lasts until `end`
+fn unknown_intervals(
+    trace: &ValueTrace,
+    after: &BigUint,
+    end: &BigUint,
+) -> Vec<(BigUint, BigUint, ValueKind)> {
+    let mut intervals: Vec<(BigUint, BigUint, ValueKind)> = vec![];
+    for (i, (time, value)) in trace.iter().enumerate() {
+        let Some(kind) = unknown_kind(value) else {
+            continue;
+        };
+        let start = time.max(after).clone();
+        let stop = trace.get(i + 1).map_or(end, |(next, _)| next).clone();
+        if stop <= start {
+            continue;
+        }
+        match intervals.last_mut() {
+            Some(prev) if prev.1 == start && prev.2 == kind => prev.1 = stop,
+            _ => intervals.push((start, stop, kind)),
+        }
+    }
+    intervals
+}
+
+/// Parse `[min_pulse=<ticks>] [after=<time>]`
+pub fn parse_analysis_args(target: AnalysisTarget, args: &str) -> Option<AnalysisSettings> {
+    let mut settings = AnalysisSettings {
+        target,
+        min_pulse: BigUint::from(2u32),
+        after: BigUint::from(0u32),
+    };
+    for word in args.split_whitespace() {
+        match word.split_once('=')? {
+            ("min_pulse", value) => settings.min_pulse = value.parse().ok()?,
+            ("after", value) => settings.after = value.parse().ok()?,
+            _ => return None,
+        }
+    }
+    Some(settings)
+}
+
+/// Scan the `traces` of signals, returning the findings sorted by time. The last values
+/// last until `end`
+pub fn analyze(
+    traces: Vec<(VarName, ValueTrace)>,
+    settings: &AnalysisSettings,
+    end: &BigUint,
+) -> Vec<Finding> {
+    traces
+        .into_iter()
+        .flat_map(|(signal, trace)| {
+            let finding = |start, end, kind| Finding {
+                signal: signal.clone(),
+                start,
+                end,
+                kind,
+            };
+            glitches(&trace, &settings.min_pulse)
+                .into_iter()
+                .map(|(start, end)| finding(start, end, FindingKind::Glitch))
+                .chain(
+                    unknown_intervals(&trace, &settings.after, end)
+                        .into_iter()
+                        .map(|(start, end, kind)| finding(start, end, FindingKind::Unknown(kind))),
+                )
+                .collect::<Vec<_>>()
+        })
+        .sorted_by(|a, b| a.start.cmp(&b.start))
+        .collect()
+}
+
+impl WaveData {
+    /// The value traces of the signals to analyse. Reading them needs the waveform, so
+    /// this is done before the scan is moved to the background
+    pub fn analysis_traces(&self, target: &AnalysisTarget) -> Vec<(VarName, ValueTrace)> {
+        let signals = match target {
+            AnalysisTarget::Displayed => self
+                .displayed_items
+                .iter()
+                .filter_map(|item| match item {
+                    DisplayedItem::Signal(signal) => Some(signal.signal_ref.clone()),
+                    _ => None,
+                })
+                .unique()
+                .collect(),
+            AnalysisTarget::Scope(scope) => self.inner.signals_in_module(scope),
+        };
+        signals
+            .into_iter()
+            .map(|signal| {
+                let trace = self.value_trace(&signal);
+                (signal, trace)
+            })
+            .collect()
+    }
+}
+
+impl FindingKind {
+    fn description(&self) -> &'static str {
+        match self {
+            FindingKind::Glitch => "Glitch",
+            FindingKind::Unknown(ValueKind::HighImp) => "High impedance",
+            FindingKind::Unknown(ValueKind::Weak) => "Weak unknown value",
+            FindingKind::Unknown(_) => "Unknown value",
+        }
+    }
+}
+
+impl State {
+    /// Draw a panel listing the findings of the last analysis. Clicking a finding moves
+    /// the cursor to its start.
+    pub fn draw_analysis_panel(
+        &self,
+        ctx: &egui::Context,
+        msgs: &mut Vec<Message>,
+        findings: &[Finding],
+    ) {
+        let Some(waves) = &self.waves else { return };
+        let time = |t: &BigUint| {
+            time_string(
+                &t.clone().into(),
+                &waves.inner.metadata(),
+                &self.wanted_timescale,
+            )
+        };
+
+        egui::TopBottomPanel::bottom("analysis")
+            .resizable(true)
+            .default_height(150.)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(format!("{} findings", findings.len()));
+                    if ui.button("Close").clicked() {
+                        msgs.push(Message::ClearAnalysis);
+                    }
+                });
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("analysis_grid")
+                        .num_columns(4)
+                        .striped(true)
+                        .spacing([10., 5.])
+                        .show(ui, |ui| {
+                            ui.strong("Start");
+                            ui.strong("End");
+                            ui.strong("Signal");
+                            ui.strong("Finding");
+                            ui.end_row();
+
+                            for finding in findings {
+                                if ui.link(time(&finding.start)).clicked() {
+                                    let start = BigInt::from(finding.start.clone());
+                                    msgs.push(Message::CursorSet(start.clone()));
+                                    msgs.push(Message::GoToTime(start));
+                                }
+                                ui.label(time(&finding.end));
+                                ui.label(finding.signal.full_path_string());
+                                ui.label(finding.kind.description());
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    /// Mark the findings on the canvas, in the row of their signal if it is displayed
+    /// and across the whole canvas otherwise
+    pub fn draw_finding_markers(
+        &self,
+        waves: &WaveData,
+        findings: &[Finding],
+        item_offsets: &[ItemDrawingInfo],
+        y_zero: f32,
+        frame_width: f32,
+        ctx: &mut DrawingContext,
+    ) {
+        for finding in findings {
+            let to_x = |time: &BigUint| {
+                waves
+                    .viewport
+                    .from_time(&BigInt::from(time.clone()), frame_width as f64)
+                    as f32
+            };
+            let (start, end) = (to_x(&finding.start), to_x(&finding.end));
+            if end < 0. || start > frame_width {
+                continue;
+            }
+            // Glitches can be far narrower than a pixel
+            let end = end.max(start + 2.);
+
+            let color = match finding.kind {
+                FindingKind::Glitch => self.config.theme.accent_warn.background,
+                FindingKind::Unknown(kind) => {
+                    kind.color(self.config.theme.signal_default, &self.config.theme)
+                }
+            }
+            .gamma_multiply(0.4);
+
+            let row = item_offsets.iter().find_map(|info| match info {
+                ItemDrawingInfo::Signal(info)
+                    if info.field_ref.root == finding.signal && info.field_ref.field.is_empty() =>
+                {
+                    Some(info.offset - y_zero)
+                }
+                _ => None,
+            });
+            let (top, bottom) = match row {
+                Some(offset) => (offset, offset + ctx.cfg.line_height),
+                None => (0., ctx.cfg.canvas_height),
+            };
+            ctx.painter.rect_filled(
+                Rect {
+                    min: (ctx.to_screen)(start, top),
+                    max: (ctx.to_screen)(end, bottom),
+                },
+                Rounding::ZERO,
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trace(values: &[(u32, &str)]) -> ValueTrace {
+        values
+            .iter()
+            .map(|(time, value)| (BigUint::from(*time), value.to_string()))
+            .collect()
+    }
+
+    fn times(intervals: Vec<(BigUint, BigUint)>) -> Vec<(u32, u32)> {
+        intervals
+            .into_iter()
+            .map(|(start, end)| (start.try_into().unwrap(), end.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn short_pulses_are_glitches() {
+        let trace = trace(&[(0, "0"), (1, "1"), (10, "0"), (11, "1"), (12, "0")]);
+        assert_eq!(
+            times(glitches(&trace, &BigUint::from(2u32))),
+            vec![(10, 11), (11, 12)]
+        );
+        assert_eq!(times(glitches(&trace, &BigUint::from(1u32))), vec![]);
+    }
+
+    #[test]
+    fn only_pulses_which_return_are_glitches() {
+        let counter = trace(&[(0, "00"), (1, "01"), (2, "10"), (3, "11")]);
+        assert_eq!(times(glitches(&counter, &BigUint::from(2u32))), vec![]);
+
+        let rewritten = trace(&[(0, "0"), (5, "1"), (6, "1"), (7, "0")]);
+        assert_eq!(
+            times(glitches(&rewritten, &BigUint::from(3u32))),
+            vec![(5, 7)]
+        );
+    }
+
+    #[test]
+    fn first_pulse_of_late_trace_is_a_glitch() {
+        let trace = trace(&[(5, "0"), (6, "1"), (7, "0")]);
+        assert_eq!(times(glitches(&trace, &BigUint::from(2u32))), vec![(6, 7)]);
+    }
+
+    #[test]
+    fn unknown_kinds_are_classified() {
+        assert_eq!(unknown_kind("01x"), Some(ValueKind::Undef));
+        assert_eq!(unknown_kind("u1"), Some(ValueKind::Undef));
+        assert_eq!(unknown_kind("z1"), Some(ValueKind::HighImp));
+        assert_eq!(unknown_kind("w1"), Some(ValueKind::Weak));
+        assert_eq!(unknown_kind("0110"), None);
+    }
+
+    #[test]
+    fn unknown_values_after_reset_are_found() {
+        let trace = trace(&[
+            (0, "xx"),
+            (10, "01"),
+            (20, "0x"),
+            (25, "1x"),
+            (30, "zz"),
+            (40, "11"),
+            (50, "x1"),
+        ]);
+        let intervals = unknown_intervals(&trace, &BigUint::from(5u32), &BigUint::from(60u32))
+            .into_iter()
+            .map(|(start, end, kind)| (start.try_into().unwrap(), end.try_into().unwrap(), kind))
+            .collect::<Vec<(u32, u32, _)>>();
+        assert_eq!(
+            intervals,
+            vec![
+                (5, 10, ValueKind::Undef),
+                (20, 30, ValueKind::Undef),
+                (30, 40, ValueKind::HighImp),
+                (50, 60, ValueKind::Undef),
+            ]
+        );
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        let settings =
+            parse_analysis_args(AnalysisTarget::Displayed, "min_pulse=5 after=100").unwrap();
+        assert_eq!(settings.min_pulse, BigUint::from(5u32));
+        assert_eq!(settings.after, BigUint::from(100u32));
+        assert!(parse_analysis_args(AnalysisTarget::Displayed, "after").is_none());
+        assert!(parse_analysis_args(AnalysisTarget::Displayed, "before=3").is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use itertools::Itertools;
+use log::warn;
+use num::BigUint;
+
+use crate::displayed_item::DisplayedItem;
+use crate::translation::{bit_fields, BasicTranslator, BinaryTranslator, TranslatorList};
+use crate::wave_container::{FieldRef, VarName};
+use crate::{State, WaveData};
+
+/// What to copy to the clipboard. Items are specified by their index in the list of
+/// displayed items, or the focused item if the index is `None`.
+#[derive(Debug, Clone)]
+pub enum ClipboardContent {
+    /// The translated value at the cursor of the (sub-)field of the item
+    Value(Option<usize>, Vec<String>),
+    /// The raw bits of the item at the cursor
+    RawValue(Option<usize>),
+    /// The full hierarchical name of the (sub-)field of the item
+    Name(Option<usize>, Vec<String>),
+    /// A tab separated table of the names and values of all displayed signals
+    /// at the cursor
+    ValueTable,
+}
+
+impl WaveData {
+    fn cursor_time(&self) -> Option<BigUint> {
+        self.cursor.as_ref().and_then(|c| c.to_biguint())
+    }
+
+    fn signal_of_item(&self, vidx: Option<usize>) -> Option<&VarName> {
+        match self.displayed_items.get(vidx.or(self.focused_item)?)? {
+            DisplayedItem::Signal(signal) => Some(&signal.signal_ref),
+            _ => None,
+        }
+    }
+
+    /// The translated value of the specified field of `signal` at `time`
+    pub fn translated_value_at(
+        &self,
+        field: &FieldRef,
+        time: &BigUint,
+        translators: &TranslatorList,
+    ) -> Option<String> {
+        let meta = self.inner.signal_meta(&field.root).ok()?;
+        let (_, value) = self.inner.query_signal(&field.root, time).ok().flatten()?;
+        let translator =
+            self.signal_translator(&FieldRef::without_fields(field.root.clone()), translators);
+        let translated = translator
+            .translate(&meta, &value)
+            .map_err(|e| warn!("{e:#?}"))
+            .ok()?;
+
+        // The fields of expanded bits are not known to the translator
+        let bits = bit_fields(meta.num_bits.unwrap_or(0) as u64, &value);
+        translated
+            .flatten(
+                FieldRef::without_fields(field.root.clone()),
+                &self.signal_format,
+                translators,
+            )
+            .as_fields()
+            .into_iter()
+            .chain(bits)
+            .find(|(path, _)| path == &field.field)
+            .and_then(|(_, value)| value)
+            .map(|(value, _)| value)
+    }
+
+    pub fn raw_value_at(&self, signal: &VarName, time: &BigUint) -> Option<String> {
+        let meta = self.inner.signal_meta(signal).ok()?;
+        let (_, value) = self.inner.query_signal(signal, time).ok().flatten()?;
+        Some(
+            BinaryTranslator {}
+                .basic_translate(meta.num_bits.unwrap_or(0) as u64, &value)
+                .0,
+        )
+    }
+
+    pub fn clipboard_text(
+        &self,
+        content: &ClipboardContent,
+        translators: &TranslatorList,
+    ) -> Option<String> {
+        match content {
+            ClipboardContent::Value(vidx, field) => {
+                let field_ref = FieldRef {
+                    root: self.signal_of_item(*vidx)?.clone(),
+                    field: field.clone(),
+                };
+                self.translated_value_at(&field_ref, &self.cursor_time()?, translators)
+            }
+            ClipboardContent::RawValue(vidx) => {
+                self.raw_value_at(self.signal_of_item(*vidx)?, &self.cursor_time()?)
+            }
+            ClipboardContent::Name(vidx, field) => {
+                let root = self.signal_of_item(*vidx)?.full_path_string();
+                Some([root].into_iter().chain(field.iter().cloned()).join("."))
+            }
+            ClipboardContent::ValueTable => {
+                let time = self.cursor_time()?;
+                Some(
+                    self.displayed_items
+                        .iter()
+                        .filter_map(|item| match item {
+                            DisplayedItem::Signal(signal) => Some(signal),
+                            _ => None,
+                        })
+                        .map(|signal| {
+                            let value = self
+                                .translated_value_at(
+                                    &FieldRef::without_fields(signal.signal_ref.clone()),
+                                    &time,
+                                    translators,
+                                )
+                                .unwrap_or_else(|| "-".to_string());
+                            format!("{}\t{value}", signal.signal_ref.full_path_string())
+                        })
+                        .join("\n"),
+                )
+            }
+        }
+    }
+}
+
+impl State {
+    pub fn copy_to_clipboard(&self, content: ClipboardContent) {
+        let Some(waves) = &self.waves else {
+            return;
+        };
+        let Some(text) = waves.clipboard_text(&content, &self.translators) else {
+            warn!("Nothing to copy for {content:?}");
+            return;
+        };
+        if let Some(ctx) = &self.context {
+            ctx.output_mut(|o| o.copied_text = text);
+        } else {
+            warn!("Can not copy to the clipboard without a UI context");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigInt;
+
+    use super::*;
+    use crate::message::Message;
+    use crate::tests::load_example;
+
+    fn counter_state() -> State {
+        let mut state = load_example("examples/counter.vcd");
+        let counter = VarName::from_hierarchy_string("tb.dut.counter");
+        state.update(Message::AddSignal(counter.clone()));
+        state.update(Message::AddSignal(VarName::from_hierarchy_string(
+            "tb.reset",
+        )));
+        state.update(Message::SignalFormatChange(
+            FieldRef::without_fields(counter),
+            "Unsigned".to_string(),
+        ));
+        state.update(Message::CursorSet(BigInt::from(150)));
+        state
+    }
+
+    #[test]
+    fn values_at_time_are_found() {
+        let state = counter_state();
+        let waves = state.waves.as_ref().unwrap();
+        let counter = VarName::from_hierarchy_string("tb.dut.counter");
+
+        assert_eq!(
+            waves.raw_value_at(&counter, &BigUint::from(149u32)),
+            Some("0010".to_string())
+        );
+        assert_eq!(
+            waves.raw_value_at(&counter, &BigUint::from(150u32)),
+            Some("0011".to_string())
+        );
+        assert_eq!(
+            waves.translated_value_at(
+                &FieldRef::without_fields(counter.clone()),
+                &BigUint::from(150u32),
+                &state.translators
+            ),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            waves.translated_value_at(
+                &FieldRef {
+                    root: counter,
+                    field: vec!["[2]".to_string()]
+                },
+                &BigUint::from(150u32),
+                &state.translators
+            ),
+            Some("0".to_string())
+        );
+    }
+
+    #[test]
+    fn clipboard_text_is_generated() {
+        let mut state = counter_state();
+        state.update(Message::FocusItem(0));
+        let waves = state.waves.as_ref().unwrap();
+        let text = |content| waves.clipboard_text(&content, &state.translators);
+
+        assert_eq!(
+            text(ClipboardContent::Value(None, vec![])),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            text(ClipboardContent::Value(Some(0), vec!["[1]".to_string()])),
+            Some("1".to_string())
+        );
+        assert_eq!(
+            text(ClipboardContent::RawValue(Some(1))),
+            Some("0".to_string())
+        );
+        assert_eq!(
+            text(ClipboardContent::Name(Some(0), vec!["[1]".to_string()])),
+            Some("tb.dut.counter.[1]".to_string())
+        );
+        assert_eq!(text(ClipboardContent::Name(Some(2), vec![])), None);
+        assert_eq!(
+            text(ClipboardContent::ValueTable),
+            Some("tb.dut.counter\t3\ntb.reset\t0".to_string())
+        );
+    }
+}
@@ -1,22 +1,120 @@
 use std::iter::zip;
+use std::path::PathBuf;
 
+use directories::ProjectDirs;
 use eframe::egui::{self};
 use eframe::emath::Align2;
 use eframe::epaint::Vec2;
 use eframe::epaint::{FontFamily, FontId};
 use egui::text::{LayoutJob, TextFormat};
-use fzcmd::parse_command;
+use fzcmd::{expand_command, parse_command, FuzzyOutput};
+use log::warn;
 
 use crate::{
     commands::{get_parser, run_fuzzy_parser},
     message::Message,
-    State,
+    MoveDir, State,
 };
 
+/// The number of executed commands which are remembered
+const MAX_HISTORY: usize = 1000;
+
 pub struct CommandPrompt {
     pub visible: bool,
     pub expanded: String,
     pub suggestions: Vec<(String, Vec<bool>)>,
+    /// Executed commands, oldest first
+    pub history: Vec<String>,
+    /// The history entry being shown, if any
+    pub history_index: Option<usize>,
+    /// Whether the input is a query for a reverse search of the history
+    pub searching: bool,
+}
+
+fn history_path() -> Option<PathBuf> {
+    ProjectDirs::from("org", "surfer-project", "surfer")
+        .map(|proj_dirs| proj_dirs.config_dir().join("command_history"))
+}
+
+/// Load the commands executed in earlier sessions
+pub fn load_history() -> Vec<String> {
+    history_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+impl CommandPrompt {
+    /// Step to an older or newer history entry and return it. Stepping past the newest
+    /// entry returns an empty command
+    pub fn step_history(&mut self, dir: &MoveDir) -> String {
+        let len = self.history.len();
+        self.searching = false;
+        self.history_index = match (dir, self.history_index) {
+            (MoveDir::Up, None) => len.checked_sub(1),
+            (MoveDir::Up, Some(idx)) => Some(idx.saturating_sub(1)),
+            (MoveDir::Down, Some(idx)) if idx + 1 < len => Some(idx + 1),
+            (MoveDir::Down, _) => None,
+        };
+        self.history_index
+            .map(|idx| self.history[idx].clone())
+            .unwrap_or_default()
+    }
+
+    /// Find the newest history entry containing `query`, or if `older` is set, the
+    /// newest one older than the current match
+    pub fn search_history(&mut self, query: &str, older: bool) -> Option<String> {
+        let end = match self.history_index {
+            Some(idx) if older => idx,
+            _ => self.history.len(),
+        };
+        let found = self.history[..end]
+            .iter()
+            .rposition(|command| command.contains(query));
+        // Keep showing the oldest match when there are no more
+        if found.is_some() || !older {
+            self.history_index = found;
+        }
+        self.history_index.map(|idx| self.history[idx].clone())
+    }
+
+    /// Remember an executed command, also for later sessions
+    pub fn add_to_history(&mut self, command: String) {
+        if command.is_empty() || self.history.last() == Some(&command) {
+            return;
+        }
+        self.history.push(command);
+        if self.history.len() > MAX_HISTORY {
+            self.history.drain(..self.history.len() - MAX_HISTORY);
+        }
+
+        let Some(path) = history_path() else { return };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, self.history.join("\n") + "\n"));
+        if let Err(e) = result {
+            warn!("Failed to save command history to {}: {e}", path.display())
+        }
+    }
+
+    /// Leave the history and the search
+    pub fn reset_history(&mut self) {
+        self.history_index = None;
+        self.searching = false;
+    }
+}
+
+impl State {
+    /// Update the expansion and the suggestions for `input`
+    pub fn expand_command_prompt(&mut self, input: &str) {
+        let FuzzyOutput {
+            expanded,
+            suggestions,
+        } = expand_command(input, get_parser(self));
+        self.command_prompt.expanded = expanded;
+        self.command_prompt.suggestions = suggestions.unwrap_or(vec![]);
+    }
 }
 
 pub fn show_command_prompt(
@@ -35,6 +133,12 @@ pub fn show_command_prompt(
             egui::Frame::none().show(ui, |ui| {
                 ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
                     ui.colored_label(state.config.theme.primary_ui_color.foreground, "🏄");
+                    if state.command_prompt.searching {
+                        ui.colored_label(
+                            state.config.theme.primary_ui_color.foreground,
+                            "history search:",
+                        );
+                    }
 
                     let input = &mut *state.command_prompt_text.borrow_mut();
                     let response = ui.add(
@@ -44,7 +148,11 @@ pub fn show_command_prompt(
                     );
 
                     if response.changed() {
-                        run_fuzzy_parser(input, state, msgs);
+                        if state.command_prompt.searching {
+                            msgs.push(Message::CommandPromptSearch(false));
+                        } else {
+                            run_fuzzy_parser(input, state, msgs);
+                        }
                     }
 
                     if response.lost_focus()
@@ -54,6 +162,9 @@ pub fn show_command_prompt(
                             parse_command(&state.command_prompt.expanded, get_parser(state)).ok();
 
                         if let Some(command_parsed) = command_parsed {
+                            msgs.push(Message::CommandPromptHistoryAdd(
+                                state.command_prompt.expanded.clone(),
+                            ));
                             msgs.push(Message::ShowCommandPrompt(false));
                             msgs.push(Message::CommandPromptClear);
                             msgs.push(command_parsed);
@@ -126,3 +237,50 @@ pub fn show_command_prompt(
             }
         });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn prompt(history: &[&str]) -> CommandPrompt {
+        CommandPrompt {
+            visible: true,
+            expanded: String::new(),
+            suggestions: vec![],
+            history: history.iter().map(|s| s.to_string()).collect(),
+            history_index: None,
+            searching: false,
+        }
+    }
+
+    #[test]
+    fn history_is_stepped_through() {
+        let mut prompt = prompt(&["zoom_fit", "signal_add tb.clk", "goto_end"]);
+        assert_eq!(prompt.step_history(&MoveDir::Up), "goto_end");
+        assert_eq!(prompt.step_history(&MoveDir::Up), "signal_add tb.clk");
+        assert_eq!(prompt.step_history(&MoveDir::Up), "zoom_fit");
+        assert_eq!(prompt.step_history(&MoveDir::Up), "zoom_fit");
+        assert_eq!(prompt.step_history(&MoveDir::Down), "signal_add tb.clk");
+        assert_eq!(prompt.step_history(&MoveDir::Down), "goto_end");
+        assert_eq!(prompt.step_history(&MoveDir::Down), "");
+        assert_eq!(prompt.history_index, None);
+    }
+
+    #[test]
+    fn history_is_searched_backwards() {
+        let mut prompt = prompt(&["signal_add tb.a", "zoom_fit", "signal_add tb.b"]);
+        assert_eq!(
+            prompt.search_history("signal", false).as_deref(),
+            Some("signal_add tb.b")
+        );
+        assert_eq!(
+            prompt.search_history("signal", true).as_deref(),
+            Some("signal_add tb.a")
+        );
+        assert_eq!(
+            prompt.search_history("signal", true).as_deref(),
+            Some("signal_add tb.a")
+        );
+        assert_eq!(prompt.search_history("module", false), None);
+    }
+}
@@ -1,18 +1,32 @@
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::{fs, str::FromStr};
 
 use crate::{
+    analysis::{parse_analysis_args, AnalysisTarget},
+    clipboard::ClipboardContent,
     clock_highlighting::ClockHighlightType,
+    decoders::{
+        parse_bus_args, parse_handshake_args, parse_i2c_args, parse_spi_args, parse_uart_args,
+    },
     displayed_item::DisplayedItem,
+    image_export::parse_export_image_args,
+    macros::macro_command,
     message::Message,
+    qualifier::Qualifier,
     signal_name_type::SignalNameType,
+    translation::numeric_translators::FixedPointTranslator,
     util::{alpha_idx_to_uint_idx, uint_idx_to_alpha_idx},
-    wave_container::{ScopeName, VarName},
+    value_table::parse_export_values_args,
+    vcd_export::parse_export_vcd_args,
+    wave_container::{FieldRef, ScopeName, VarName},
     State,
 };
 
-use fzcmd::{expand_command, Command, FuzzyOutput, ParamGreed};
+use camino::Utf8PathBuf;
+use fzcmd::{expand_command, parse_command, Command, FuzzyOutput, ParamGreed};
 use itertools::Itertools;
+use log::error;
 
 pub fn get_parser(state: &State) -> Command<Message> {
     fn single_word(
@@ -85,6 +99,13 @@ pub fn get_parser(state: &State) -> Command<Message> {
 
     let color_names = state.config.theme.colors.keys().cloned().collect_vec();
 
+    let focused_signal = state.waves.as_ref().and_then(|waves| {
+        match waves.displayed_items.get(waves.focused_item?)? {
+            DisplayedItem::Signal(signal) => Some(signal.signal_ref.clone()),
+            _ => None,
+        }
+    });
+
     let active_module = state.waves.as_ref().and_then(|w| w.active_module.clone());
 
     fn vcd_files() -> Vec<String> {
@@ -115,11 +136,15 @@ pub fn get_parser(state: &State) -> Command<Message> {
         BTreeMap::new()
     };
 
+    let macros = state.config.macros.clone();
+
     Command::NonTerminal(
         ParamGreed::Word,
         vec![
             "load_vcd",
             "load_url",
+            "translator_load",
+            "translator_load_url",
             "config_reload",
             "scroll_to_start",
             "scroll_to_end",
@@ -141,16 +166,43 @@ pub fn get_parser(state: &State) -> Command<Message> {
             "signal_focus",
             "signal_unfocus",
             "signal_unset_color",
+            "signal_set_qualifier",
+            "signal_unset_qualifier",
+            "signal_expand_bits",
+            "signal_collapse_bits",
+            "bus_combine",
+            "analyze_signals",
+            "analyze_scope",
+            "analysis_clear",
+            "signal_set_fixed_point",
+            "signal_copy_value",
+            "signal_copy_raw_value",
+            "signal_copy_name",
+            "copy_value_table",
             "preference_set_clock_highlight",
             "divider_add",
+            "decoder_add_uart",
+            "decoder_add_spi",
+            "decoder_add_i2c",
+            "decoder_add_bus",
             "goto_cursor",
+            "export_image",
+            "export_values",
+            "export_vcd",
+            "view_save",
+            "view_load",
+            "run_command_file",
         ]
         .into_iter()
         .map(|s| s.into())
+        // Macros with the name of a built-in command are shadowed by it
+        .chain(macros.keys().cloned())
+        .unique()
         .collect(),
         Box::new(move |query, _| {
             let signals_in_active_scope = signals_in_active_scope.clone();
             let cursors = cursors.clone();
+            let focused_signal = focused_signal.clone();
             let modules = modules.clone();
             let active_module = active_module.clone();
             match query {
@@ -158,6 +210,21 @@ pub fn get_parser(state: &State) -> Command<Message> {
                     Box::new(vcd_files),
                     Box::new(|word| Some(Command::Terminal(Message::LoadVcd(word.into())))),
                 ),
+                "translator_load" => single_word(
+                    vec![],
+                    Box::new(|word| {
+                        Some(Command::Terminal(Message::LoadUserTranslator(word.into())))
+                    }),
+                ),
+                "translator_load_url" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        Some(Command::Terminal(Message::LoadTranslatorFromUrl(
+                            query.to_string(),
+                        )))
+                    }),
+                )),
                 "load_url" => Some(Command::NonTerminal(
                     ParamGreed::Rest,
                     vec![],
@@ -234,6 +301,70 @@ pub fn get_parser(state: &State) -> Command<Message> {
                 "signal_unset_color" => {
                     Some(Command::Terminal(Message::ItemColorChange(None, None)))
                 }
+                "signal_set_qualifier" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        let qualifier = Qualifier::parse(query).ok()?;
+                        Some(Command::Terminal(Message::SetSignalQualifier(
+                            None,
+                            Some(qualifier),
+                        )))
+                    }),
+                )),
+                "signal_unset_qualifier" => {
+                    Some(Command::Terminal(Message::SetSignalQualifier(None, None)))
+                }
+                "signal_expand_bits" => {
+                    Some(Command::Terminal(Message::SetBitsExpanded(None, true)))
+                }
+                "signal_collapse_bits" => {
+                    Some(Command::Terminal(Message::SetBitsExpanded(None, false)))
+                }
+                "bus_combine" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_bus_args(query)
+                            .map(|d| Command::Terminal(Message::AddDecoder(Box::new(d))))
+                    }),
+                )),
+                "analyze_signals" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_analysis_args(AnalysisTarget::Displayed, query)
+                            .map(|settings| Command::Terminal(Message::Analyze(settings)))
+                    }),
+                )),
+                "analyze_scope" => Some(Command::NonTerminal(
+                    ParamGreed::Word,
+                    modules.clone(),
+                    Box::new(|scope, _| {
+                        let scope = ScopeName::from_hierarchy_string(scope);
+                        Some(Command::NonTerminal(
+                            ParamGreed::Rest,
+                            vec![],
+                            Box::new(move |query, _| {
+                                parse_analysis_args(AnalysisTarget::Scope(scope.clone()), query)
+                                    .map(|settings| Command::Terminal(Message::Analyze(settings)))
+                            }),
+                        ))
+                    }),
+                )),
+                "analysis_clear" => Some(Command::Terminal(Message::ClearAnalysis)),
+                "signal_copy_value" => Some(Command::Terminal(Message::CopyToClipboard(
+                    ClipboardContent::Value(None, vec![]),
+                ))),
+                "signal_copy_raw_value" => Some(Command::Terminal(Message::CopyToClipboard(
+                    ClipboardContent::RawValue(None),
+                ))),
+                "signal_copy_name" => Some(Command::Terminal(Message::CopyToClipboard(
+                    ClipboardContent::Name(None, vec![]),
+                ))),
+                "copy_value_table" => Some(Command::Terminal(Message::CopyToClipboard(
+                    ClipboardContent::ValueTable,
+                ))),
                 "signal_set_name_type" => single_word(
                     vec![
                         "Local".to_string(),
@@ -280,10 +411,55 @@ pub fn get_parser(state: &State) -> Command<Message> {
                     }),
                 ),
                 "signal_unfocus" => Some(Command::Terminal(Message::UnfocusItem)),
+                "signal_set_fixed_point" => single_word(
+                    ["Q.8", "Q.15", "Q.16", "Q.31", "UQ.8", "UQ.16"]
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect_vec(),
+                    Box::new(move |word| {
+                        FixedPointTranslator::from_name(word)?;
+                        Some(Command::Terminal(Message::SignalFormatChange(
+                            FieldRef::without_fields(focused_signal.clone()?),
+                            word.to_string(),
+                        )))
+                    }),
+                ),
                 "divider_add" => single_word(
                     vec![],
                     Box::new(|word| Some(Command::Terminal(Message::AddDivider(word.into())))),
                 ),
+                "decoder_add_uart" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_uart_args(query)
+                            .map(|d| Command::Terminal(Message::AddDecoder(Box::new(d))))
+                    }),
+                )),
+                "decoder_add_spi" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_spi_args(query)
+                            .map(|d| Command::Terminal(Message::AddDecoder(Box::new(d))))
+                    }),
+                )),
+                "decoder_add_i2c" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_i2c_args(query)
+                            .map(|d| Command::Terminal(Message::AddDecoder(Box::new(d))))
+                    }),
+                )),
+                "decoder_add_bus" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_handshake_args(query)
+                            .map(|d| Command::Terminal(Message::AddDecoder(Box::new(d))))
+                    }),
+                )),
                 "goto_cursor" => single_word(
                     cursors.keys().cloned().collect(),
                     Box::new(move |name| {
@@ -292,7 +468,45 @@ pub fn get_parser(state: &State) -> Command<Message> {
                             .map(|idx| Command::Terminal(Message::GoToCursorPosition(*idx)))
                     }),
                 ),
-                _ => None,
+                "export_image" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_export_image_args(query)
+                            .map(|settings| Command::Terminal(Message::ExportImage(settings)))
+                    }),
+                )),
+                "export_values" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_export_values_args(query)
+                            .map(|settings| Command::Terminal(Message::ExportValueTable(settings)))
+                    }),
+                )),
+                "export_vcd" => Some(Command::NonTerminal(
+                    ParamGreed::Rest,
+                    vec![],
+                    Box::new(|query, _| {
+                        parse_export_vcd_args(query)
+                            .map(|settings| Command::Terminal(Message::ExportVcd(settings)))
+                    }),
+                )),
+                "view_save" => single_word(
+                    vec![],
+                    Box::new(|word| Some(Command::Terminal(Message::SaveView(word.into())))),
+                ),
+                "view_load" => single_word(
+                    vec![],
+                    Box::new(|word| Some(Command::Terminal(Message::LoadView(word.into())))),
+                ),
+                "run_command_file" => single_word(
+                    vec![],
+                    Box::new(|word| Some(Command::Terminal(Message::RunCommandFile(word.into())))),
+                ),
+                _ => macros
+                    .get(query)
+                    .map(|commands| macro_command(query, commands)),
             }
         }),
     )
@@ -309,3 +523,182 @@ pub fn run_fuzzy_parser(input: &str, state: &State, msgs: &mut Vec<Message>) {
         suggestions: suggestions.unwrap_or(vec![]),
     })
 }
+
+/// How deeply command files and macros can run each other, which stops a file which runs
+/// itself before it overflows the stack
+pub const MAX_COMMAND_DEPTH: usize = 16;
+
+thread_local! {
+    /// The source and line of the command which is running, see [command_location]
+    static COMMAND_LOCATION: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// The source and line of the command from a command file or macro which is running on
+/// this thread, if any. It is added to the messages logged while the command runs
+pub fn command_location() -> Option<String> {
+    COMMAND_LOCATION.with(|location| location.borrow().clone())
+}
+
+impl State {
+    /// Run the commands in `path`, one per line, as if they were entered in the command
+    /// prompt. Empty lines and lines starting with `#` are skipped, and lines which fail
+    /// to parse are reported with their line number.
+    ///
+    /// Waveforms are loaded in the background, so the lines after `load_vcd` or
+    /// `load_url` are run once the waveform has been loaded
+    pub fn run_command_file(&mut self, path: &Utf8PathBuf) {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                error!("Failed to read command file {path}: {e:#?}");
+                return;
+            }
+        };
+        self.run_commands(path.as_str(), contents.lines());
+    }
+
+    /// Run `commands` as if they were entered in the command prompt. Errors are reported
+    /// with the line number in `source`
+    pub fn run_commands<'a>(&mut self, source: &str, commands: impl IntoIterator<Item = &'a str>) {
+        let lines = commands
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.to_string()))
+            .collect();
+        self.run_numbered_commands(source, lines)
+    }
+
+    /// Parse line `line_number` of `source`, returning `None` for blank lines and comments
+    fn parse_command_line(
+        &self,
+        source: &str,
+        line_number: usize,
+        line: &str,
+    ) -> Option<Result<Message, String>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        Some(
+            parse_command(line, get_parser(self))
+                .map_err(|e| format!("{source}:{line_number}: Failed to run `{line}`: {e:?}")),
+        )
+    }
+
+    /// Run `lines`, which are numbered by their line in `source`
+    pub(crate) fn run_numbered_commands(&mut self, source: &str, lines: Vec<(usize, String)>) {
+        if self.command_depth >= MAX_COMMAND_DEPTH {
+            error!("{source}: Commands are nested more than {MAX_COMMAND_DEPTH} levels deep");
+            return;
+        }
+        self.command_depth += 1;
+
+        let mut lines = lines.into_iter();
+        while let Some((line_number, line)) = lines.next() {
+            // Each command is parsed after the previous one has been run, so it can
+            // refer to signals and items added by earlier lines
+            let message = match self.parse_command_line(source, line_number, &line) {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => {
+                    error!("{e}");
+                    continue;
+                }
+                None => continue,
+            };
+            let loads_waves = matches!(message, Message::LoadVcd(_) | Message::LoadVcdFromUrl(_));
+
+            let outer = COMMAND_LOCATION
+                .with(|location| location.replace(Some(format!("{source}:{line_number}"))));
+            self.update(message);
+            COMMAND_LOCATION.with(|location| location.replace(outer));
+
+            if loads_waves {
+                self.deferred_commands = Some((source.to_string(), lines.collect()));
+                break;
+            }
+        }
+
+        self.command_depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::load_example;
+
+    fn dividers(state: &State) -> Vec<String> {
+        state
+            .waves
+            .as_ref()
+            .unwrap()
+            .displayed_items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayedItem::Divider(divider) => Some(divider.name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn command_file(name: &str, contents: &str) -> Utf8PathBuf {
+        let path = std::env::temp_dir().join(format!("surfer_test_{name}.sucl"));
+        fs::write(&path, contents).unwrap();
+        path.try_into().unwrap()
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let mut state = load_example("examples/counter.vcd");
+        let path = command_file(
+            "comments",
+            "# Dividers\n\ndivider_add first\n   \n  # divider_add skipped\ndivider_add second\n",
+        );
+        state.run_command_file(&path);
+        assert_eq!(dividers(&state), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let state = load_example("examples/counter.vcd");
+        assert!(state.parse_command_line("cmds", 3, "  # comment").is_none());
+        assert!(state.parse_command_line("cmds", 4, "").is_none());
+        assert!(matches!(
+            state.parse_command_line("cmds", 5, "divider_add x"),
+            Some(Ok(Message::AddDivider(_)))
+        ));
+        let Some(Err(e)) = state.parse_command_line("cmds", 6, "no_such_command") else {
+            panic!("Expected an error")
+        };
+        assert!(e.starts_with("cmds:6: Failed to run `no_such_command`"));
+    }
+
+    #[test]
+    fn recursive_command_files_stop() {
+        let mut state = load_example("examples/counter.vcd");
+        let path = std::env::temp_dir().join("surfer_test_recursive.sucl");
+        let path: Utf8PathBuf = path.try_into().unwrap();
+        let contents = format!("divider_add again\nrun_command_file {path}\n");
+        fs::write(&path, contents).unwrap();
+        state.run_command_file(&path);
+        assert_eq!(dividers(&state).len(), MAX_COMMAND_DEPTH);
+        assert_eq!(state.command_depth, 0);
+    }
+
+    #[test]
+    fn commands_after_loading_wait_for_the_waveform() {
+        let mut state = load_example("examples/counter.vcd");
+        let vcd = project_root::get_project_root()
+            .unwrap()
+            .join("examples/counter.vcd");
+        let load = format!("load_vcd {}", vcd.display());
+        state.run_commands("test", [load.as_str(), "divider_add loaded"]);
+        assert!(state.deferred_commands.is_some());
+        assert!(dividers(&state).is_empty());
+
+        while state.deferred_commands.is_some() {
+            state.handle_async_messages();
+        }
+        assert_eq!(dividers(&state), vec!["loaded"]);
+    }
+}
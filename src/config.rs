@@ -7,11 +7,13 @@ use directories::ProjectDirs;
 use eframe::epaint::Color32;
 use serde::de;
 use serde::{Deserialize, Deserializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 #[cfg(not(target_arch = "wasm32"))]
-use std::path::Path;
+use std::path::PathBuf;
 
-use crate::{clock_highlighting::ClockHighlightType, signal_name_type::SignalNameType};
+use crate::{
+    clock_highlighting::ClockHighlightType, keys::KeyBindings, signal_name_type::SignalNameType,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct SurferConfig {
@@ -22,6 +24,13 @@ pub struct SurferConfig {
     // #[serde(deserialize_with = "deserialize_signal_name_type")]
     pub default_signal_name_type: SignalNameType,
     pub default_clock_highlight_type: ClockHighlightType,
+    /// Commands which run a sequence of commands, with `$1`, `$2`, ... replaced by
+    /// their arguments
+    #[serde(default)]
+    pub macros: BTreeMap<String, Vec<String>>,
+    /// The actions bound to key chords
+    #[serde(default)]
+    pub keys: KeyBindings,
 }
 
 #[derive(Debug, Deserialize)]
@@ -158,20 +167,50 @@ impl SurferConfig {
             config::FileFormat::Toml,
         ));
 
-        if let Some(proj_dirs) = ProjectDirs::from("org", "surfer-project", "surfer") {
-            let config_file = proj_dirs.config_dir().join("config.toml");
-            c = c.add_source(File::from(config_file).required(false));
+        let user_files = ProjectDirs::from("org", "surfer-project", "surfer")
+            .map(|proj_dirs| proj_dirs.config_dir().join("config.toml"))
+            .into_iter()
+            .chain([PathBuf::from("surfer.toml")])
+            .collect::<Vec<_>>();
+        for file in &user_files {
+            c = c.add_source(File::from(file.as_path()).required(false));
         }
 
-        c.add_source(File::from(Path::new("surfer.toml")).required(false))
+        let mut config: SurferConfig = c
             .add_source(Environment::with_prefix("surfer"))
             .build()?
             .try_deserialize()
-            .map_err(|e| anyhow!("Failed to parse config {e}"))
+            .map_err(|e| anyhow!("Failed to parse config {e}"))?;
+        config.keys = key_bindings(&default_config, &user_files)?;
+        Ok(config)
     }
 }
 
-fn hex_string_to_color32(mut str: String) -> Result<Color32> {
+/// The key bindings of the default config, overridden by those of the user config
+/// files. `Config` merges tables by the spelling of their keys, so the bindings are
+/// merged here instead, where `ctrl+j` in a user config replaces `Ctrl+J`
+#[cfg(not(target_arch = "wasm32"))]
+fn key_bindings(default_config: &str, user_files: &[PathBuf]) -> Result<KeyBindings> {
+    #[derive(Deserialize)]
+    struct KeySection {
+        #[serde(default)]
+        keys: HashMap<String, String>,
+    }
+
+    let user_configs = user_files.iter().filter(|file| file.exists()).map(|file| {
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))
+    });
+    let mut bindings = KeyBindings::default();
+    for contents in std::iter::once(Ok(default_config.to_string())).chain(user_configs) {
+        let section: KeySection = toml::from_str(&contents?)?;
+        bindings
+            .merge(section.keys)
+            .map_err(|e| color_eyre::eyre::anyhow!("Failed to parse key bindings: {e}"))?;
+    }
+    Ok(bindings)
+}
+
+pub(crate) fn hex_string_to_color32(mut str: String) -> Result<Color32> {
     let mut hex_str = String::new();
     if str.len() == 3 {
         for c in str.chars() {
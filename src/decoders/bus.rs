@@ -0,0 +1,181 @@
+use itertools::Itertools;
+use num::BigUint;
+
+use super::{value_at, BitTrace, DecodedFrame, ProtocolDecoder, ValueTrace};
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+
+/// A virtual bus combining one-bit signals into a vector. The value is shown in
+/// hexadecimal while all bits are 0 or 1, and as bits otherwise.
+pub struct BusDecoder {
+    /// The signals of the bits, most significant bit first
+    pub bits: Vec<VarName>,
+}
+
+impl BusDecoder {
+    /// Combine `signals` into a bus, with the most significant bit first unless
+    /// `lsb_first` is set
+    pub fn new(signals: Vec<VarName>, lsb_first: bool) -> Self {
+        let bits = if lsb_first {
+            signals.into_iter().rev().collect()
+        } else {
+            signals
+        };
+        Self { bits }
+    }
+}
+
+/// The text and kind of the bus value `bits`
+fn bus_value(bits: &str) -> (String, ValueKind) {
+    match BigUint::parse_bytes(bits.as_bytes(), 2) {
+        Some(value) => (
+            format!("{value:0width$x}", width = (bits.len() + 3) / 4),
+            ValueKind::Normal,
+        ),
+        None if bits.chars().all(|bit| bit == 'z') => (bits.to_string(), ValueKind::HighImp),
+        None => (bits.to_string(), ValueKind::Undef),
+    }
+}
+
+impl ProtocolDecoder for BusDecoder {
+    fn name(&self) -> String {
+        format!(
+            "{{{}}}",
+            self.bits
+                .iter()
+                .map(|signal| signal.name.as_str())
+                .join(", ")
+        )
+    }
+
+    fn inputs(&self) -> Vec<VarName> {
+        self.bits.clone()
+    }
+
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        _values: &[ValueTrace],
+        _seconds_per_tick: f64,
+        end: &BigUint,
+    ) -> Vec<DecodedFrame> {
+        let times = traces
+            .iter()
+            .flat_map(|trace| trace.iter().map(|(time, _)| time))
+            .filter(|time| *time < end)
+            .sorted()
+            .dedup()
+            .collect::<Vec<_>>();
+
+        let mut frames: Vec<DecodedFrame> = vec![];
+        for (i, time) in times.iter().enumerate() {
+            let bits = traces
+                .iter()
+                .map(|trace| value_at(trace, time))
+                .collect::<String>();
+            let (text, kind) = bus_value(&bits);
+            // Merge changes which do not change the value, such as a bit being set to
+            // its current value
+            if let Some(prev) = frames.last_mut().filter(|prev| prev.text == text) {
+                prev.end = times.get(i + 1).map_or(end, |next| *next).clone();
+                continue;
+            }
+            frames.push(DecodedFrame {
+                start: (*time).clone(),
+                end: times.get(i + 1).map_or(end, |next| *next).clone(),
+                text,
+                kind,
+            })
+        }
+        frames
+    }
+}
+
+/// Parse `[lsb_first|msb_first] <signal>...`, where the bit order is most significant
+/// bit first by default
+pub fn parse_bus_args(args: &str) -> Option<BusDecoder> {
+    let mut words = args.split_whitespace().peekable();
+    let lsb_first = words
+        .next_if(|word| matches!(*word, "lsb_first" | "msb_first"))
+        .map_or(false, |word| word == "lsb_first");
+    let signals = words
+        .map(VarName::from_hierarchy_string)
+        .collect::<Vec<_>>();
+    if signals.is_empty() {
+        return None;
+    }
+    Some(BusDecoder::new(signals, lsb_first))
+}
+
+#[cfg(test)]
+mod test {
+    use num::ToPrimitive;
+
+    use super::*;
+
+    fn trace(changes: &[(u32, char)]) -> BitTrace {
+        changes
+            .iter()
+            .map(|(time, value)| (BigUint::from(*time), *value))
+            .collect()
+    }
+
+    fn frames(traces: &[BitTrace]) -> Vec<(u32, u32, String)> {
+        parse_bus_args("a b c d e")
+            .unwrap()
+            .decode(traces, &[], 1., &BigUint::from(100u32))
+            .into_iter()
+            .map(|frame| {
+                (
+                    frame.start.to_u32().unwrap(),
+                    frame.end.to_u32().unwrap(),
+                    frame.text,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bits_are_combined_msb_first() {
+        let traces = [
+            trace(&[(0, '1')]),
+            trace(&[(0, '0'), (10, '1')]),
+            trace(&[(0, '0')]),
+            trace(&[(0, '0'), (20, '0')]),
+            trace(&[(0, '1'), (30, '0')]),
+        ];
+        assert_eq!(
+            frames(&traces),
+            vec![
+                (0, 10, "11".to_string()),
+                (10, 30, "19".to_string()),
+                (30, 100, "18".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_bits_are_shown_as_bits() {
+        let traces = [
+            trace(&[(0, 'x'), (5, '0')]),
+            trace(&[(0, '0')]),
+            trace(&[(0, '0')]),
+            trace(&[(0, '0')]),
+            trace(&[(0, '1')]),
+        ];
+        let decoded =
+            parse_bus_args("a b c d e")
+                .unwrap()
+                .decode(&traces, &[], 1., &BigUint::from(100u32));
+        assert_eq!(decoded[0].text, "x0001");
+        assert_eq!(decoded[0].kind, ValueKind::Undef);
+        assert_eq!(decoded[1].text, "01");
+    }
+
+    #[test]
+    fn bit_order_can_be_reversed() {
+        let bus = parse_bus_args("lsb_first tb.b0 tb.b1 tb.b2").unwrap();
+        assert_eq!(bus.name(), "{b2, b1, b0}");
+        assert!(parse_bus_args("msb_first").is_none());
+    }
+}
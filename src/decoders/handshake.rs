@@ -0,0 +1,242 @@
+use num::BigUint;
+
+use super::{BitTrace, DecodedFrame, ProtocolDecoder, ValueTrace};
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+
+/// Decodes the transactions of a bus with a handshake, such as valid/ready on an AXI
+/// channel or cyc/stb/ack on Wishbone. A transaction completes at each rising clock
+/// edge where all handshake signals are high. It is drawn from the time the first
+/// handshake signal, the request, was raised or the previous transaction completed,
+/// and labelled with the values of the fields at the completing edge.
+pub struct HandshakeDecoder {
+    pub clock: VarName,
+    pub handshake: Vec<VarName>,
+    /// Signals to show for each transaction, together with their labels
+    pub fields: Vec<(String, VarName)>,
+}
+
+/// The value of `trace` just before `time`. Signals sampled at a clock edge must be read
+/// this way, since values driven by the same edge change at the time of the edge.
+fn value_before<T: Clone>(trace: &[(BigUint, T)], time: &BigUint) -> Option<T> {
+    match trace.partition_point(|(t, _)| t < time) {
+        0 => None,
+        idx => Some(trace[idx - 1].1.clone()),
+    }
+}
+
+/// The time at which `trace`, which is high just before `time`, was raised
+fn raised_at(trace: &BitTrace, time: &BigUint) -> Option<BigUint> {
+    let idx = trace.partition_point(|(t, _)| t < time);
+    trace[..idx]
+        .iter()
+        .rev()
+        .take_while(|(_, value)| *value == '1')
+        .last()
+        .map(|(t, _)| t.clone())
+}
+
+/// `bits` in hexadecimal, or `None` if any of them is not 0 or 1
+fn hex_string(bits: &str) -> Option<String> {
+    BigUint::parse_bytes(bits.as_bytes(), 2).map(|value| format!("{value:x}"))
+}
+
+impl ProtocolDecoder for HandshakeDecoder {
+    fn name(&self) -> String {
+        let handshake = self
+            .handshake
+            .iter()
+            .map(|signal| signal.name.as_str())
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("Transactions {handshake}")
+    }
+
+    fn inputs(&self) -> Vec<VarName> {
+        std::iter::once(self.clock.clone())
+            .chain(self.handshake.iter().cloned())
+            .collect()
+    }
+
+    fn value_inputs(&self) -> Vec<VarName> {
+        self.fields
+            .iter()
+            .map(|(_, signal)| signal.clone())
+            .collect()
+    }
+
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        values: &[ValueTrace],
+        _seconds_per_tick: f64,
+        _end: &BigUint,
+    ) -> Vec<DecodedFrame> {
+        let [clock, handshake @ ..] = traces else {
+            return vec![];
+        };
+        let Some(request) = handshake.first() else {
+            return vec![];
+        };
+
+        let mut frames: Vec<DecodedFrame> = vec![];
+        for (i, (time, value)) in clock.iter().enumerate().skip(1) {
+            let is_rising_edge = clock[i - 1].1 == '0' && *value == '1';
+            if !is_rising_edge
+                || !handshake
+                    .iter()
+                    .all(|trace| value_before(trace, time) == Some('1'))
+            {
+                continue;
+            }
+
+            let requested = raised_at(request, time).unwrap_or_default();
+            let start = match frames.last() {
+                Some(prev) if prev.end > requested => prev.end.clone(),
+                _ => requested,
+            };
+
+            let fields = self
+                .fields
+                .iter()
+                .zip(values)
+                .map(|((label, _), trace)| {
+                    let value = value_before(trace, time)?;
+                    Some(format!("{label}={}", hex_string(&value)?))
+                })
+                .collect::<Option<Vec<_>>>();
+            let (text, kind) = match fields {
+                Some(fields) if fields.is_empty() => ("transfer".to_string(), ValueKind::Normal),
+                Some(fields) => (fields.join(" "), ValueKind::Normal),
+                None => ("UNDEF".to_string(), ValueKind::Undef),
+            };
+
+            frames.push(DecodedFrame {
+                start,
+                end: time.clone(),
+                text,
+                kind,
+            });
+        }
+        frames
+    }
+}
+
+/// Parse the arguments of the bus transaction command,
+/// `<clock> <handshake signals...> [<label>=<signal>...]`
+pub fn parse_handshake_args(args: &str) -> Option<HandshakeDecoder> {
+    let mut words = args.split_whitespace();
+    let clock = VarName::from_hierarchy_string(words.next()?);
+    let mut handshake = vec![];
+    let mut fields = vec![];
+    for word in words {
+        match word.split_once('=') {
+            Some((label, signal)) if !label.is_empty() && !signal.is_empty() => {
+                fields.push((label.to_string(), VarName::from_hierarchy_string(signal)))
+            }
+            Some(_) => return None,
+            // The handshake signals come before the fields
+            None if fields.is_empty() => handshake.push(VarName::from_hierarchy_string(word)),
+            None => return None,
+        }
+    }
+    if handshake.is_empty() {
+        return None;
+    }
+    Some(HandshakeDecoder {
+        clock,
+        handshake,
+        fields,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use num::ToPrimitive;
+
+    use super::*;
+
+    /// A trace with one value per tick
+    fn trace(values: &str) -> BitTrace {
+        values
+            .chars()
+            .enumerate()
+            .map(|(i, value)| (BigUint::from(i), value))
+            .collect()
+    }
+
+    /// A multi-bit trace with one value per tick
+    fn value_trace(values: &[&str]) -> ValueTrace {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (BigUint::from(i), value.to_string()))
+            .collect()
+    }
+
+    fn frames(
+        decoder: &HandshakeDecoder,
+        traces: &[BitTrace],
+        values: &[ValueTrace],
+    ) -> Vec<(u32, u32, String)> {
+        decoder
+            .decode(traces, values, 1., &BigUint::from(8u32))
+            .into_iter()
+            .map(|frame| {
+                (
+                    frame.start.to_u32().unwrap(),
+                    frame.end.to_u32().unwrap(),
+                    frame.text,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transactions_span_from_request_to_completion() {
+        let decoder = parse_handshake_args("clk valid ready addr=addr").unwrap();
+        let clk = trace("01010101");
+        let valid = trace("01111000");
+        let ready = trace("00011100");
+        let addr = value_trace(&["00", "00", "00", "10", "10", "11", "11", "11"]);
+        assert_eq!(
+            frames(&decoder, &[clk, valid, ready], &[addr]),
+            vec![(1, 5, "addr=2".to_string())]
+        );
+    }
+
+    #[test]
+    fn back_to_back_transactions_are_contiguous() {
+        let decoder = parse_handshake_args("clk cyc stb ack data=data").unwrap();
+        let clk = trace("0101010");
+        let cyc = trace("0111111");
+        let stb = trace("0111111");
+        let ack = trace("0111110");
+        let data = value_trace(&["00", "00", "01", "01", "10", "10", "x0"]);
+        assert_eq!(
+            frames(&decoder, &[clk, cyc, stb, ack], &[data]),
+            vec![(1, 3, "data=1".to_string()), (3, 5, "data=2".to_string())]
+        );
+    }
+
+    #[test]
+    fn undefined_fields_are_shown() {
+        let decoder = parse_handshake_args("clk valid ready data=data").unwrap();
+        let clk = trace("0101");
+        let valid = trace("1111");
+        let ready = trace("1111");
+        let data = value_trace(&["x1", "x1", "x1", "x1"]);
+        assert_eq!(
+            frames(&decoder, &[clk, valid, ready], &[data]),
+            vec![(0, 1, "UNDEF".to_string()), (1, 3, "UNDEF".to_string())]
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse_handshake_args("clk").is_none());
+        assert!(parse_handshake_args("clk addr=addr").is_none());
+        assert!(parse_handshake_args("clk valid addr=addr ready").is_none());
+        assert!(parse_handshake_args("clk valid =addr").is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use num::BigUint;
+
+use super::{
+    bits_value, next_change, value_at, BitTrace, DecodedFrame, ProtocolDecoder, ValueTrace,
+};
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+
+/// Decodes I2C transfers. Each transfer begins with a start condition followed by an
+/// address byte, which is shown together with the direction of the transfer, and
+/// continues with data bytes until a stop condition. A byte which is not acknowledged
+/// is shown as a warning, except for the last byte of a read where the controller
+/// signals the end of the read that way.
+pub struct I2cDecoder {
+    pub scl: VarName,
+    pub sda: VarName,
+}
+
+/// The state of a transfer between a start and a stop condition
+struct Transfer {
+    /// Whether the transfer began with a repeated start condition
+    repeated: bool,
+    /// Whether the address byte has been received, and the direction it gave
+    read: Option<bool>,
+    /// The start of the byte which is being received
+    byte_start: BigUint,
+    bits: Vec<char>,
+}
+
+impl Transfer {
+    fn byte_text(&self) -> (String, ValueKind) {
+        let Some(value) = bits_value(self.bits[..8].iter().cloned()) else {
+            return ("UNDEF".to_string(), ValueKind::Undef);
+        };
+        let (text, nack_kind) = match self.read {
+            None => {
+                let start = if self.repeated { "Sr" } else { "S" };
+                let direction = if value & 1 == 1 { "R" } else { "W" };
+                (
+                    format!("{start} {:02x} {direction}", value >> 1),
+                    ValueKind::Warn,
+                )
+            }
+            Some(true) => (format!("{value:02x}"), ValueKind::Normal),
+            Some(false) => (format!("{value:02x}"), ValueKind::Warn),
+        };
+        match self.bits[8] {
+            '0' => (text, ValueKind::Normal),
+            '1' => (format!("{text} NACK"), nack_kind),
+            _ => ("UNDEF".to_string(), ValueKind::Undef),
+        }
+    }
+}
+
+impl ProtocolDecoder for I2cDecoder {
+    fn name(&self) -> String {
+        format!("I2C {}", self.sda.name)
+    }
+
+    fn inputs(&self) -> Vec<VarName> {
+        vec![self.scl.clone(), self.sda.clone()]
+    }
+
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        _values: &[ValueTrace],
+        _seconds_per_tick: f64,
+        _end: &BigUint,
+    ) -> Vec<DecodedFrame> {
+        let [scl, sda] = traces else {
+            return vec![];
+        };
+        let mut times = scl
+            .iter()
+            .chain(sda.iter())
+            .map(|(t, _)| t.clone())
+            .collect::<Vec<_>>();
+        times.sort();
+        times.dedup();
+
+        let mut frames = vec![];
+        let mut transfer: Option<Transfer> = None;
+        let mut prev = ('x', 'x');
+        for time in times {
+            let (scl_value, sda_value) = (value_at(scl, &time), value_at(sda, &time));
+            let (prev_scl, prev_sda) = std::mem::replace(&mut prev, (scl_value, sda_value));
+
+            if prev_scl == '1' && scl_value == '1' && prev_sda != sda_value {
+                // A change of the data line while the clock is high is a start or stop
+                // condition. The clock pulse preceding it is not part of a byte, so only
+                // bytes with more bits than that are incomplete
+                let in_transfer = transfer.is_some();
+                if let Some(t) = transfer.take().filter(|t| t.bits.len() > 1) {
+                    frames.push(DecodedFrame {
+                        start: t.byte_start,
+                        end: time.clone(),
+                        text: "incomplete".to_string(),
+                        kind: ValueKind::Warn,
+                    });
+                }
+                if prev_sda == '1' && sda_value == '0' {
+                    transfer = Some(Transfer {
+                        repeated: in_transfer,
+                        read: None,
+                        byte_start: time.clone(),
+                        bits: vec![],
+                    });
+                }
+                continue;
+            }
+
+            let Some(t) = transfer.as_mut() else {
+                continue;
+            };
+            if prev_scl == '0' && scl_value == '1' {
+                t.bits.push(sda_value);
+            }
+            if t.bits.len() == 9 {
+                // The byte lasts until the clock edge following the acknowledge bit
+                let end = next_change(scl, &time).unwrap_or_else(|| time.clone());
+                let (text, kind) = t.byte_text();
+                frames.push(DecodedFrame {
+                    start: std::mem::replace(&mut t.byte_start, end.clone()),
+                    end,
+                    text,
+                    kind,
+                });
+                if t.read.is_none() {
+                    t.read = Some(t.bits[7] == '1');
+                }
+                t.bits.clear();
+            }
+        }
+        frames
+    }
+}
+
+/// Parse the arguments of the I2C decoder command, `<scl> <sda>`
+pub fn parse_i2c_args(args: &str) -> Option<I2cDecoder> {
+    match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [scl, sda] => Some(I2cDecoder {
+            scl: VarName::from_hierarchy_string(scl),
+            sda: VarName::from_hierarchy_string(sda),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The clock and data traces of a sequence of bus states, one per tick
+    fn bus(states: &[(char, char)]) -> [BitTrace; 2] {
+        let trace = |values: Vec<char>| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| (BigUint::from(i), value))
+                .collect()
+        };
+        [
+            trace(states.iter().map(|(scl, _)| *scl).collect()),
+            trace(states.iter().map(|(_, sda)| *sda).collect()),
+        ]
+    }
+
+    fn start() -> Vec<(char, char)> {
+        vec![('0', '1'), ('1', '1'), ('1', '0')]
+    }
+
+    fn stop() -> Vec<(char, char)> {
+        vec![('0', '0'), ('1', '0'), ('1', '1')]
+    }
+
+    /// The clock pulses of a byte followed by the acknowledge bit
+    fn byte(value: u8, ack: bool) -> Vec<(char, char)> {
+        (0..8)
+            .rev()
+            .map(|i| if value >> i & 1 == 1 { '1' } else { '0' })
+            .chain(std::iter::once(if ack { '0' } else { '1' }))
+            .flat_map(|bit| [('0', bit), ('1', bit)])
+            .collect()
+    }
+
+    fn decode(states: Vec<Vec<(char, char)>>) -> Vec<(String, ValueKind)> {
+        let decoder = parse_i2c_args("scl sda").unwrap();
+        decoder
+            .decode(&bus(&states.concat()), &[], 1., &BigUint::from(1000u32))
+            .into_iter()
+            .map(|frame| (frame.text, frame.kind))
+            .collect()
+    }
+
+    #[test]
+    fn write_transfers_are_decoded() {
+        let frames = decode(vec![start(), byte(0xa0, true), byte(0xa5, true), stop()]);
+        assert_eq!(
+            frames,
+            vec![
+                ("S 50 W".to_string(), ValueKind::Normal),
+                ("a5".to_string(), ValueKind::Normal)
+            ]
+        );
+    }
+
+    #[test]
+    fn unacknowledged_address_is_a_warning() {
+        let frames = decode(vec![start(), byte(0x51, false), stop()]);
+        assert_eq!(frames, vec![("S 28 R NACK".to_string(), ValueKind::Warn)]);
+    }
+
+    #[test]
+    fn repeated_start_begins_a_new_transfer() {
+        let frames = decode(vec![
+            start(),
+            byte(0xa0, true),
+            start(),
+            byte(0xa1, true),
+            byte(0x3c, false),
+            stop(),
+        ]);
+        assert_eq!(
+            frames,
+            vec![
+                ("S 50 W".to_string(), ValueKind::Normal),
+                ("Sr 50 R".to_string(), ValueKind::Normal),
+                ("3c NACK".to_string(), ValueKind::Normal)
+            ]
+        );
+    }
+
+    #[test]
+    fn bytes_cut_short_by_a_stop_are_incomplete() {
+        let mut partial = byte(0xa0, true);
+        partial.truncate(8);
+        let frames = decode(vec![start(), partial, stop()]);
+        assert_eq!(frames, vec![("incomplete".to_string(), ValueKind::Warn)]);
+    }
+}
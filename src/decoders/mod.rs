@@ -0,0 +1,130 @@
+//! Protocol decoders, which combine the values of several one-bit signals over time into
+//! a row of decoded frames, for example the bytes sent over a serial interface. Unlike
+//! translators, which translate the value of a single signal at a single time, decoders
+//! see the full traces of all their inputs. Virtual buses, which combine one-bit signals
+//! into a vector, are decoders as well.
+mod bus;
+mod handshake;
+mod i2c;
+mod spi;
+mod table;
+mod uart;
+
+use num::{BigUint, ToPrimitive, Zero};
+
+pub use bus::{parse_bus_args, BusDecoder};
+pub use handshake::{parse_handshake_args, HandshakeDecoder};
+pub use i2c::{parse_i2c_args, I2cDecoder};
+pub use spi::{parse_spi_args, SpiDecoder};
+pub use uart::{parse_uart_args, Parity, UartDecoder};
+
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+use crate::WaveData;
+
+/// The changes of a one-bit signal as a list of change times and new values, sorted by time
+pub type BitTrace = Vec<(BigUint, char)>;
+
+/// The changes of a multi-bit signal as a list of change times and new values as binary
+/// strings, sorted by time
+pub type ValueTrace = Vec<(BigUint, String)>;
+
+#[derive(Debug, Clone)]
+pub struct DecodedFrame {
+    pub start: BigUint,
+    pub end: BigUint,
+    pub text: String,
+    pub kind: ValueKind,
+}
+
+pub trait ProtocolDecoder {
+    fn name(&self) -> String;
+    /// The signals to decode. Their traces are passed to `decode` in the same order
+    fn inputs(&self) -> Vec<VarName>;
+    /// Multi-bit signals whose values are shown in the frames, such as the address and
+    /// data of a bus. Their traces are passed to `decode` in the same order
+    fn value_inputs(&self) -> Vec<VarName> {
+        vec![]
+    }
+    /// Decode the traces of the inputs. `seconds_per_tick` is the duration of one
+    /// timestamp, for protocols which depend on absolute times, and `end` is the last
+    /// timestamp of the waveform
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        values: &[ValueTrace],
+        seconds_per_tick: f64,
+        end: &BigUint,
+    ) -> Vec<DecodedFrame>;
+
+    /// All signals which must exist for the decoder to be used
+    fn all_inputs(&self) -> Vec<VarName> {
+        self.inputs()
+            .into_iter()
+            .chain(self.value_inputs())
+            .collect()
+    }
+}
+
+/// The value of `trace` at `time`, `x` before the first change
+pub fn value_at(trace: &BitTrace, time: &BigUint) -> char {
+    match trace.partition_point(|(t, _)| t <= time) {
+        0 => 'x',
+        idx => trace[idx - 1].1,
+    }
+}
+
+/// The first change of `trace` after `time`
+pub fn next_change(trace: &BitTrace, time: &BigUint) -> Option<BigUint> {
+    let idx = trace.partition_point(|(t, _)| t <= time);
+    trace.get(idx).map(|(t, _)| t.clone())
+}
+
+/// The numeric value of `bits`, most significant bit first, or `None` if any of the
+/// bits is not 0 or 1
+pub fn bits_value(bits: impl IntoIterator<Item = char>) -> Option<u64> {
+    bits.into_iter().try_fold(0, |acc, bit| match bit {
+        '0' => Some(acc << 1),
+        '1' => Some(acc << 1 | 1),
+        _ => None,
+    })
+}
+
+impl WaveData {
+    pub(crate) fn value_trace(&self, signal: &VarName) -> ValueTrace {
+        let end = self.num_timestamps.to_biguint().unwrap_or_default();
+        self.change_times(signal, &BigUint::zero(), &end)
+            .into_iter()
+            .filter_map(|time| {
+                let value = self.raw_value_at(signal, &time)?;
+                Some((time, value))
+            })
+            .collect()
+    }
+
+    /// The trace of the least significant bit of `signal`
+    fn bit_trace(&self, signal: &VarName) -> BitTrace {
+        self.value_trace(signal)
+            .into_iter()
+            .filter_map(|(time, value)| Some((time, value.chars().last()?)))
+            .collect()
+    }
+
+    pub fn decode(&self, decoder: &dyn ProtocolDecoder) -> Vec<DecodedFrame> {
+        let traces = decoder
+            .inputs()
+            .iter()
+            .map(|signal| self.bit_trace(signal))
+            .collect::<Vec<_>>();
+        let values = decoder
+            .value_inputs()
+            .iter()
+            .map(|signal| self.value_trace(signal))
+            .collect::<Vec<_>>();
+        let timescale = self.inner.metadata().timescale;
+        let seconds_per_tick =
+            timescale.factor as f64 * 10f64.powi(timescale.unit.to_exponent().unwrap_or(0) as i32);
+        let end = self.num_timestamps.to_biguint().unwrap_or_default();
+        decoder.decode(&traces, &values, seconds_per_tick, &end)
+    }
+}
@@ -0,0 +1,302 @@
+use num::BigUint;
+
+use super::{
+    bits_value, next_change, value_at, BitTrace, DecodedFrame, ProtocolDecoder, ValueTrace,
+};
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+
+/// Decodes SPI words from the data lines, sampled on the clock edge given by the SPI
+/// mode. If a chip select is given, it is active low and words are only decoded while
+/// it is asserted. A word which is cut short by the chip select is shown as a warning.
+pub struct SpiDecoder {
+    pub sclk: VarName,
+    pub mosi: VarName,
+    pub miso: Option<VarName>,
+    pub cs: Option<VarName>,
+    /// The SPI mode, i.e. `CPOL << 1 | CPHA`
+    pub mode: u8,
+    pub word_bits: u8,
+    pub lsb_first: bool,
+}
+
+/// A word which is being received
+struct Word {
+    start: BigUint,
+    /// The time of the last sample
+    last: BigUint,
+    /// The time at which the chip select was asserted before the word started
+    cs_start: Option<BigUint>,
+    mosi: Vec<char>,
+    miso: Vec<char>,
+}
+
+/// The time at which `cs`, which is asserted at `time`, was last asserted
+fn assertion_time(cs: &BitTrace, time: &BigUint) -> Option<BigUint> {
+    let idx = cs.partition_point(|(t, _)| t <= time);
+    cs[..idx]
+        .iter()
+        .rev()
+        .take_while(|(_, value)| *value == '0')
+        .last()
+        .map(|(t, _)| t.clone())
+}
+
+/// The time at which `cs` was first deasserted after `time`
+fn deassertion_time(cs: &BitTrace, time: &BigUint) -> Option<BigUint> {
+    cs.iter()
+        .find(|(t, value)| t > time && *value != '0')
+        .map(|(t, _)| t.clone())
+}
+
+impl SpiDecoder {
+    fn word_text(&self, bits: &[char]) -> Option<String> {
+        let value = if self.lsb_first {
+            bits_value(bits.iter().rev().cloned())
+        } else {
+            bits_value(bits.iter().cloned())
+        }?;
+        Some(format!(
+            "{value:0width$x}",
+            width = (self.word_bits as usize + 3) / 4
+        ))
+    }
+
+    fn frame(&self, word: Word, end: BigUint) -> DecodedFrame {
+        let (text, kind) = if word.mosi.len() < self.word_bits as usize {
+            ("incomplete".to_string(), ValueKind::Warn)
+        } else {
+            let mosi = self.word_text(&word.mosi);
+            let miso = if self.miso.is_some() {
+                self.word_text(&word.miso).map(Some)
+            } else {
+                Some(None)
+            };
+            match (mosi, miso) {
+                (Some(mosi), Some(Some(miso))) => (format!("{mosi}/{miso}"), ValueKind::Normal),
+                (Some(mosi), Some(None)) => (mosi, ValueKind::Normal),
+                _ => ("UNDEF".to_string(), ValueKind::Undef),
+            }
+        };
+        DecodedFrame {
+            start: word.start,
+            end,
+            text,
+            kind,
+        }
+    }
+}
+
+impl ProtocolDecoder for SpiDecoder {
+    fn name(&self) -> String {
+        let data = match &self.miso {
+            Some(miso) => format!("{}/{}", self.mosi.name, miso.name),
+            None => self.mosi.name.clone(),
+        };
+        format!("SPI {data} (mode {})", self.mode)
+    }
+
+    fn inputs(&self) -> Vec<VarName> {
+        [
+            Some(self.sclk.clone()),
+            Some(self.mosi.clone()),
+            self.miso.clone(),
+            self.cs.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        _values: &[ValueTrace],
+        _seconds_per_tick: f64,
+        end: &BigUint,
+    ) -> Vec<DecodedFrame> {
+        let mut traces = traces.iter();
+        let (Some(sclk), Some(mosi)) = (traces.next(), traces.next()) else {
+            return vec![];
+        };
+        let miso = self.miso.as_ref().and_then(|_| traces.next());
+        let cs = self.cs.as_ref().and_then(|_| traces.next());
+
+        // Data is sampled on the rising edge in modes 0 and 3 and on the falling edge
+        // in modes 1 and 2
+        let (from, to) = match self.mode {
+            0 | 3 => ('0', '1'),
+            _ => ('1', '0'),
+        };
+
+        let mut frames = vec![];
+        let mut word: Option<Word> = None;
+        for (i, (time, value)) in sclk.iter().enumerate().skip(1) {
+            if !(sclk[i - 1].1 == from && *value == to) {
+                continue;
+            }
+
+            let cs_start = match cs {
+                Some(cs) if value_at(cs, time) != '0' => continue,
+                Some(cs) => assertion_time(cs, time),
+                None => None,
+            };
+            // The chip select was deasserted since the word started
+            if word.as_ref().map_or(false, |w| w.cs_start != cs_start) {
+                let w = word.take().unwrap();
+                let end = cs
+                    .and_then(|cs| deassertion_time(cs, &w.start))
+                    .unwrap_or_else(|| time.clone());
+                frames.push(self.frame(w, end));
+            }
+
+            let w = word.get_or_insert_with(|| Word {
+                start: time.clone(),
+                last: time.clone(),
+                cs_start,
+                mosi: vec![],
+                miso: vec![],
+            });
+            w.last = time.clone();
+            w.mosi.push(value_at(mosi, time));
+            if let Some(miso) = miso {
+                w.miso.push(value_at(miso, time));
+            }
+
+            if w.mosi.len() == self.word_bits as usize {
+                // The word lasts until the clock edge following the last sample
+                let end = next_change(sclk, time).unwrap_or_else(|| time.clone());
+                frames.push(self.frame(word.take().unwrap(), end));
+            }
+        }
+        // A word which is unfinished at the end of the trace lasts until the clock edge
+        // following its last sample, or until the end of the trace
+        if let Some(w) = word {
+            let end = cs
+                .and_then(|cs| deassertion_time(cs, &w.start))
+                .or_else(|| next_change(sclk, &w.last))
+                .unwrap_or_else(|| end.clone());
+            frames.push(self.frame(w, end));
+        }
+        frames
+    }
+}
+
+/// Parse the arguments of the SPI decoder command,
+/// `<sclk> <mosi> [miso=<signal>] [cs=<signal>] [mode=<0-3>] [bits=<n>] [lsb]`
+pub fn parse_spi_args(args: &str) -> Option<SpiDecoder> {
+    let mut words = args.split_whitespace();
+    let mut decoder = SpiDecoder {
+        sclk: VarName::from_hierarchy_string(words.next()?),
+        mosi: VarName::from_hierarchy_string(words.next()?),
+        miso: None,
+        cs: None,
+        mode: 0,
+        word_bits: 8,
+        lsb_first: false,
+    };
+
+    for word in words {
+        match word.split_once('=') {
+            Some(("miso", signal)) => decoder.miso = Some(VarName::from_hierarchy_string(signal)),
+            Some(("cs", signal)) => decoder.cs = Some(VarName::from_hierarchy_string(signal)),
+            Some(("mode", mode)) => decoder.mode = mode.parse().ok().filter(|m| *m < 4)?,
+            Some(("bits", bits)) => {
+                decoder.word_bits = bits.parse().ok().filter(|b| (1..=64).contains(b))?
+            }
+            None if word == "lsb" => decoder.lsb_first = true,
+            _ => return None,
+        }
+    }
+    Some(decoder)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A trace with one value per tick
+    fn trace(values: &str) -> BitTrace {
+        values
+            .chars()
+            .enumerate()
+            .map(|(i, value)| (BigUint::from(i), value))
+            .collect()
+    }
+
+    fn texts(frames: Vec<DecodedFrame>) -> Vec<(String, ValueKind)> {
+        frames
+            .into_iter()
+            .map(|frame| (frame.text, frame.kind))
+            .collect()
+    }
+
+    #[test]
+    fn words_are_sampled_on_rising_edges_in_mode_0() {
+        let decoder = parse_spi_args("sclk mosi miso=miso bits=4").unwrap();
+        let sclk = trace("0101010101010101");
+        let mosi = trace("1100110000001111");
+        let miso = trace("0000000011110000");
+        let frames = decoder.decode(&[sclk, mosi, miso], &[], 1., &BigUint::from(100u32));
+        assert_eq!(
+            texts(frames),
+            vec![
+                ("a/0".to_string(), ValueKind::Normal),
+                ("3/c".to_string(), ValueKind::Normal)
+            ]
+        );
+    }
+
+    #[test]
+    fn lsb_first_reverses_bits() {
+        let decoder = parse_spi_args("sclk mosi bits=4 lsb mode=2").unwrap();
+        let sclk = trace("101010101");
+        let mosi = trace("111000000");
+        let frames = decoder.decode(&[sclk, mosi], &[], 1., &BigUint::from(100u32));
+        assert_eq!(texts(frames), vec![("1".to_string(), ValueKind::Normal)]);
+    }
+
+    #[test]
+    fn chip_select_deassertion_interrupts_words() {
+        let decoder = parse_spi_args("sclk mosi cs=cs bits=4").unwrap();
+        let sclk = trace("01010101010101");
+        let mosi = trace("11111111111111");
+        let cs = trace("00001110000000");
+        let frames = decoder.decode(&[sclk, mosi, cs], &[], 1., &BigUint::from(100u32));
+        assert_eq!(
+            texts(frames),
+            vec![
+                ("incomplete".to_string(), ValueKind::Warn),
+                ("f".to_string(), ValueKind::Normal)
+            ]
+        );
+    }
+
+    #[test]
+    fn unfinished_words_last_until_the_next_edge_or_the_end() {
+        let decoder = parse_spi_args("sclk mosi bits=4").unwrap();
+        let span = |sclk: &str| {
+            let mosi = trace(&"1".repeat(sclk.len()));
+            let frames = decoder.decode(&[trace(sclk), mosi], &[], 1., &BigUint::from(100u32));
+            frames
+                .into_iter()
+                .map(|frame| (frame.start, frame.end, frame.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            span("0101010"),
+            vec![(BigUint::from(1u32), BigUint::from(6u32), ValueKind::Warn)]
+        );
+        assert_eq!(
+            span("01"),
+            vec![(BigUint::from(1u32), BigUint::from(100u32), ValueKind::Warn)]
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse_spi_args("sclk").is_none());
+        assert!(parse_spi_args("sclk mosi mode=4").is_none());
+        assert!(parse_spi_args("sclk mosi foo").is_none());
+    }
+}
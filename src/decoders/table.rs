@@ -0,0 +1,85 @@
+use eframe::egui;
+use num::{BigInt, BigUint};
+
+use crate::displayed_item::DisplayedItem;
+use crate::message::Message;
+use crate::time::time_string;
+use crate::State;
+
+/// Minimum width of the columns, which keeps the header aligned with the rows
+const COLUMN_WIDTH: f32 = 100.;
+
+impl State {
+    /// Draw a window listing the frames of the decoder at `vidx`. Clicking a frame moves
+    /// the cursor to its start.
+    pub fn draw_frame_table_window(
+        &self,
+        ctx: &egui::Context,
+        msgs: &mut Vec<Message>,
+        vidx: usize,
+    ) {
+        let Some(waves) = &self.waves else { return };
+        let Some(DisplayedItem::Decoder(decoder)) = waves.displayed_items.get(vidx) else {
+            msgs.push(Message::SetFrameTableVisible(None));
+            return;
+        };
+        let time = |t: &BigUint| {
+            time_string(
+                &t.clone().into(),
+                &waves.inner.metadata(),
+                &self.wanted_timescale,
+            )
+        };
+
+        let mut open = true;
+        egui::Window::new(decoder.display_name.as_str())
+            .id(egui::Id::new("frame_table"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.label(format!("{} frames", decoder.frames.len()));
+                let spacing = [10., ui.spacing().item_spacing.y];
+                egui::Grid::new("frame_table_header")
+                    .num_columns(3)
+                    .min_col_width(COLUMN_WIDTH)
+                    .spacing(spacing)
+                    .show(ui, |ui| {
+                        ui.strong("Start");
+                        ui.strong("End");
+                        ui.strong("Value");
+                        ui.end_row();
+                    });
+
+                // Decoders can produce a lot of frames, so only the visible ones are drawn
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical().show_rows(
+                    ui,
+                    row_height,
+                    decoder.frames.len(),
+                    |ui, rows| {
+                        egui::Grid::new("frame_table_grid")
+                            .num_columns(3)
+                            .min_col_width(COLUMN_WIDTH)
+                            .striped(true)
+                            .spacing(spacing)
+                            .show(ui, |ui| {
+                                for frame in &decoder.frames[rows] {
+                                    if ui.link(time(&frame.start)).clicked() {
+                                        let start = BigInt::from(frame.start.clone());
+                                        msgs.push(Message::CursorSet(start.clone()));
+                                        msgs.push(Message::GoToTime(start));
+                                    }
+                                    ui.label(time(&frame.end));
+                                    ui.label(&frame.text);
+                                    ui.end_row();
+                                }
+                            });
+                    },
+                );
+            });
+        if !open {
+            msgs.push(Message::SetFrameTableVisible(None))
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use num::{BigUint, FromPrimitive, ToPrimitive};
+
+use super::{bits_value, value_at, BitTrace, DecodedFrame, ProtocolDecoder, ValueTrace};
+use crate::translation::ValueKind;
+use crate::wave_container::VarName;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Decodes an asynchronous serial line which idles high. Each frame is a low start bit,
+/// the data bits starting with the least significant bit, an optional parity bit and
+/// high stop bits. Bits are sampled in the middle of their bit period.
+pub struct UartDecoder {
+    pub line: VarName,
+    pub baud: u32,
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: u8,
+}
+
+impl UartDecoder {
+    /// The frame format in the usual notation, e.g. 8N1
+    fn format(&self) -> String {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Even => 'E',
+            Parity::Odd => 'O',
+        };
+        format!("{}{parity}{}", self.data_bits, self.stop_bits)
+    }
+
+    /// The text and kind of a frame with the sampled `data` bits, least significant
+    /// bit first
+    fn frame_text(
+        &self,
+        data: &[char],
+        parity: Option<char>,
+        stop: &[char],
+    ) -> (String, ValueKind) {
+        let Some(value) = bits_value(data.iter().rev().cloned()) else {
+            return ("UNDEF".to_string(), ValueKind::Undef);
+        };
+        let mut text = format!("{value:02x}");
+        if let Some(c) = char::from_u32(value as u32).filter(|c| c.is_ascii_graphic()) {
+            text = format!("{text} '{c}'")
+        }
+
+        let ones = value.count_ones() + parity.map_or(0, |p| (p == '1') as u32);
+        let parity_ok = match self.parity {
+            Parity::None => true,
+            Parity::Even => ones % 2 == 0,
+            Parity::Odd => ones % 2 == 1,
+        };
+        if !stop.iter().all(|bit| *bit == '1') {
+            (format!("{text} framing error"), ValueKind::Warn)
+        } else if !parity_ok {
+            (format!("{text} parity error"), ValueKind::Warn)
+        } else {
+            (text, ValueKind::Normal)
+        }
+    }
+}
+
+impl ProtocolDecoder for UartDecoder {
+    fn name(&self) -> String {
+        format!(
+            "UART {} ({} baud {})",
+            self.line.name,
+            self.baud,
+            self.format()
+        )
+    }
+
+    fn inputs(&self) -> Vec<VarName> {
+        vec![self.line.clone()]
+    }
+
+    fn decode(
+        &self,
+        traces: &[BitTrace],
+        _values: &[ValueTrace],
+        seconds_per_tick: f64,
+        _end: &BigUint,
+    ) -> Vec<DecodedFrame> {
+        let [trace] = traces else {
+            return vec![];
+        };
+        let period = 1. / (self.baud as f64 * seconds_per_tick);
+        let data_bits = self.data_bits as usize;
+        let parity_bits = if self.parity == Parity::None { 0 } else { 1 };
+        let frame_bits = 1 + data_bits + parity_bits + self.stop_bits as usize;
+        let to_time = |t: f64| BigUint::from_f64(t.round()).unwrap_or_default();
+
+        let mut frames = vec![];
+        // The earliest time at which the next start bit can begin
+        let mut idle_from = 0.;
+        for (i, (time, value)) in trace.iter().enumerate().skip(1) {
+            let start = time.to_f64().unwrap_or_default();
+            let is_falling_edge = *value == '0' && trace[i - 1].1 == '1';
+            if !is_falling_edge || start < idle_from {
+                continue;
+            }
+
+            let sample =
+                |bit: usize| value_at(trace, &to_time(start + (bit as f64 + 0.5) * period));
+            // A start bit which is not low in the middle is a glitch
+            if sample(0) != '0' {
+                continue;
+            }
+            let data = (1..=data_bits).map(sample).collect::<Vec<_>>();
+            let parity = (parity_bits == 1).then(|| sample(1 + data_bits));
+            let stop = (1 + data_bits + parity_bits..frame_bits)
+                .map(sample)
+                .collect::<Vec<_>>();
+
+            let (text, kind) = self.frame_text(&data, parity, &stop);
+            frames.push(DecodedFrame {
+                start: time.clone(),
+                end: to_time(start + frame_bits as f64 * period),
+                text,
+                kind,
+            });
+            // Look for the next start bit after the middle of the last stop bit
+            idle_from = start + (frame_bits as f64 - 0.5) * period;
+        }
+        frames
+    }
+}
+
+/// Parse the arguments of the UART decoder command, `<line> <baud> [<format>]`, where
+/// the format is given as e.g. 8N1 and defaults to that
+pub fn parse_uart_args(args: &str) -> Option<UartDecoder> {
+    let words = args.split_whitespace().collect::<Vec<_>>();
+    let (line, baud, format) = match words.as_slice() {
+        [line, baud] => (line, baud, "8N1"),
+        [line, baud, format] => (line, baud, *format),
+        _ => return None,
+    };
+
+    let mut chars = format.chars();
+    let data_bits = chars.next()?.to_digit(10)? as u8;
+    let parity = match chars.next()?.to_ascii_uppercase() {
+        'N' => Parity::None,
+        'E' => Parity::Even,
+        'O' => Parity::Odd,
+        _ => return None,
+    };
+    let stop_bits = chars.as_str().parse().ok()?;
+    if !(5..=9).contains(&data_bits) || !(1..=2).contains(&stop_bits) {
+        return None;
+    }
+
+    Some(UartDecoder {
+        line: VarName::from_hierarchy_string(line),
+        baud: baud.parse().ok().filter(|baud| *baud > 0)?,
+        data_bits,
+        parity,
+        stop_bits,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A trace which idles high and then sends `bits` with 10 ticks per bit from time 10
+    fn uart_trace(bits: &str) -> BitTrace {
+        std::iter::once((BigUint::from(0u32), '1'))
+            .chain(
+                bits.chars()
+                    .enumerate()
+                    .map(|(i, bit)| (BigUint::from(10 + 10 * i as u32), bit)),
+            )
+            .collect()
+    }
+
+    fn decode(args: &str, bits: &str) -> Vec<(String, ValueKind)> {
+        // 10 ticks per bit at 1 baud
+        parse_uart_args(&format!("top.tx 1 {args}"))
+            .unwrap()
+            .decode(&[uart_trace(bits)], &[], 0.1, &BigUint::from(1000u32))
+            .into_iter()
+            .map(|frame| (frame.text, frame.kind))
+            .collect()
+    }
+
+    #[test]
+    fn bytes_are_decoded_lsb_first() {
+        // 0x41 = 'A' and 0x0f, each with a start and stop bit
+        let frames = decode("8N1", "0100000101");
+        assert_eq!(frames, vec![("41 'A'".to_string(), ValueKind::Normal)]);
+
+        let frames = decode("8N1", "01000001010111100001");
+        assert_eq!(
+            frames,
+            vec![
+                ("41 'A'".to_string(), ValueKind::Normal),
+                ("0f".to_string(), ValueKind::Normal)
+            ]
+        );
+    }
+
+    #[test]
+    fn framing_errors_are_warnings() {
+        let frames = decode("8N1", "0100000100");
+        assert_eq!(
+            frames,
+            vec![("41 'A' framing error".to_string(), ValueKind::Warn)]
+        );
+    }
+
+    #[test]
+    fn parity_is_checked() {
+        // 0x41 has two set bits
+        assert_eq!(decode("8E1", "01000001001")[0].1, ValueKind::Normal);
+        assert_eq!(decode("8O1", "01000001001")[0].1, ValueKind::Warn);
+        assert_eq!(decode("8O1", "01000001011")[0].1, ValueKind::Normal);
+    }
+
+    #[test]
+    fn format_is_parsed() {
+        let decoder = parse_uart_args("top.tx 9600 7E2").unwrap();
+        assert_eq!(decoder.data_bits, 7);
+        assert_eq!(decoder.parity, Parity::Even);
+        assert_eq!(decoder.stop_bits, 2);
+        assert!(parse_uart_args("top.tx 9600 8X1").is_none());
+        assert!(parse_uart_args("top.tx fast").is_none());
+    }
+}
@@ -2,14 +2,20 @@ use eframe::egui;
 use log::warn;
 
 use crate::{
-    message::Message, signal_name_type::SignalNameType, translation::SignalInfo,
-    wave_container::VarName, State,
+    decoders::{DecodedFrame, ProtocolDecoder},
+    message::Message,
+    qualifier::Qualifier,
+    signal_name_type::SignalNameType,
+    translation::SignalInfo,
+    wave_container::VarName,
+    State,
 };
 
 pub enum DisplayedItem {
     Signal(DisplayedSignal),
     Divider(DisplayedDivider),
     Cursor(DisplayedCursor),
+    Decoder(DisplayedDecoder),
 }
 
 pub struct DisplayedSignal {
@@ -19,6 +25,10 @@ pub struct DisplayedSignal {
     pub background_color: Option<String>,
     pub display_name: String,
     pub display_name_type: SignalNameType,
+    /// Values are only shown while the qualifier is high
+    pub qualifier: Option<Qualifier>,
+    /// Show the individual bits as one-bit fields, see [SignalInfo::with_bits]
+    pub bits_expanded: bool,
 }
 
 pub struct DisplayedDivider {
@@ -34,12 +44,22 @@ pub struct DisplayedCursor {
     pub idx: u8,
 }
 
+pub struct DisplayedDecoder {
+    pub decoder: Box<dyn ProtocolDecoder + Send>,
+    /// The decoded frames, recomputed when the waveform is reloaded
+    pub frames: Vec<DecodedFrame>,
+    pub color: Option<String>,
+    pub background_color: Option<String>,
+    pub display_name: String,
+}
+
 impl DisplayedItem {
     pub fn color(&self) -> Option<String> {
         let color = match self {
             DisplayedItem::Signal(signal) => &signal.color,
             DisplayedItem::Divider(divider) => &divider.color,
             DisplayedItem::Cursor(cursor) => &cursor.color,
+            DisplayedItem::Decoder(decoder) => &decoder.color,
         };
         color.clone()
     }
@@ -55,6 +75,9 @@ impl DisplayedItem {
             DisplayedItem::Cursor(cursor) => {
                 cursor.color = color_name.clone();
             }
+            DisplayedItem::Decoder(decoder) => {
+                decoder.color = color_name.clone();
+            }
         }
     }
 
@@ -63,6 +86,7 @@ impl DisplayedItem {
             DisplayedItem::Signal(signal) => &signal.display_name,
             DisplayedItem::Divider(divider) => &divider.name,
             DisplayedItem::Cursor(cursor) => &cursor.name,
+            DisplayedItem::Decoder(decoder) => &decoder.display_name,
         };
         name.clone()
     }
@@ -74,6 +98,7 @@ impl DisplayedItem {
             DisplayedItem::Cursor(cursor) => {
                 format!("{idx}: {name}", idx = cursor.idx, name = cursor.name)
             }
+            DisplayedItem::Decoder(decoder) => decoder.display_name.clone(),
         }
     }
 
@@ -88,6 +113,9 @@ impl DisplayedItem {
             DisplayedItem::Cursor(cursor) => {
                 cursor.name = name.clone();
             }
+            DisplayedItem::Decoder(decoder) => {
+                decoder.display_name = name.clone();
+            }
         }
     }
 
@@ -96,6 +124,7 @@ impl DisplayedItem {
             DisplayedItem::Signal(signal) => &signal.background_color,
             DisplayedItem::Divider(divider) => &divider.background_color,
             DisplayedItem::Cursor(cursor) => &cursor.background_color,
+            DisplayedItem::Decoder(decoder) => &decoder.background_color,
         };
         background_color.clone()
     }
@@ -111,6 +140,9 @@ impl DisplayedItem {
             DisplayedItem::Cursor(cursor) => {
                 cursor.background_color = color_name.clone();
             }
+            DisplayedItem::Decoder(decoder) => {
+                decoder.background_color = color_name.clone();
+            }
         }
     }
 }
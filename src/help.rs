@@ -3,7 +3,11 @@ use eframe::{
     epaint::Vec2,
 };
 
-use crate::{message::Message, State};
+use crate::{
+    keys::{describe_action, KeyBindings},
+    message::Message,
+    State,
+};
 
 impl State {
     pub fn help_message(&self, ui: &mut egui::Ui) {
@@ -39,7 +43,7 @@ impl State {
             ui.add_space(20.0);
         }
 
-        controls_listing(ui);
+        controls_listing(ui, &self.config.keys);
 
         ui.add_space(20.0);
         ui.separator();
@@ -95,6 +99,7 @@ pub fn draw_about_window(ctx: &egui::Context, msgs: &mut Vec<Message>) {
 
 pub fn draw_control_help_window(
     ctx: &egui::Context,
+    keys: &KeyBindings,
     max_width: f32,
     max_height: f32,
     msgs: &mut Vec<Message>,
@@ -113,7 +118,7 @@ pub fn draw_control_help_window(
                         y: max_height * 0.5,
                     },
                     layout,
-                    |ui| key_listing(ui),
+                    |ui| key_listing(ui, keys),
                 );
                 ui.add_space(10.);
                 if ui.button("Close").clicked() {
@@ -126,35 +131,20 @@ pub fn draw_control_help_window(
     }
 }
 
-fn key_listing(ui: &mut egui::Ui) {
-    let keys = vec![
-        ("🚀", "Space", "Show command prompt"),
-        ("↔", "Scroll", "Pan"),
-        ("🔎", "Ctrl+Scroll", "Zoom"),
-        ("〰", "b", "Show or hide the design hierarchy"),
-        ("☰", "m", "Show or hide menu"),
-        ("🔎➕", "+", "Zoom in"),
-        ("🔎➖", "-", "Zoom out"),
-        ("", "k/⬆", "Scroll up"),
-        ("", "j/⬇", "Scroll down"),
-        ("", "Ctrl+k/⬆", "Move focused item up"),
-        ("", "Ctrl+j/⬇", "Move focused item down"),
-        ("", "Alt+k/⬆", "Move focus up"),
-        ("", "Alt+j/⬇", "Move focus down"),
+fn key_listing(ui: &mut egui::Ui, keys: &KeyBindings) {
+    let mouse = fixed_controls(&[("↔", "Scroll", "Pan"), ("🔎", "Ctrl+Scroll", "Zoom")]);
+    let cursors = fixed_controls(&[
         ("", "Ctrl+0-9", "Add numbered cursor"),
         ("", "0-9", "Center view at numbered cursor"),
-        ("🔙", "s", "Scroll to start"),
-        ("🔚", "e", "Scroll to end"),
-        ("🗙", "Delete", "Delete focused item"),
-        #[cfg(not(target_arch = "wasm32"))]
-        ("⛶", "F11", "Toggle full screen"),
-    ];
+    ]);
 
     Grid::new("keys")
         .num_columns(3)
         .spacing([5., 5.])
         .show(ui, |ui| {
-            for (symbol, control, description) in keys {
+            for (symbol, control, description) in
+                mouse.into_iter().chain(keys.listing()).chain(cursors)
+            {
                 ui.label(symbol);
                 ui.label(control);
                 ui.label(description);
@@ -165,23 +155,33 @@ fn key_listing(ui: &mut egui::Ui) {
     add_hint_text(ui);
 }
 
-fn controls_listing(ui: &mut egui::Ui) {
-    let controls = vec![
-        ("🚀", "Space", "Show command prompt"),
+fn controls_listing(ui: &mut egui::Ui, keys: &KeyBindings) {
+    let bound = [
+        "show_command_prompt",
+        "scroll_down",
+        "scroll_up",
+        "move_focus_down",
+        "move_focus_up",
+        "move_focused_item_down",
+        "move_focused_item_up",
+        "toggle_side_panel",
+        "toggle_menu",
+    ]
+    .into_iter()
+    .filter_map(|action| {
+        let (symbol, description) = describe_action(action);
+        Some((symbol, keys.chords_for(action)?, description))
+    });
+    let controls = fixed_controls(&[
         ("↔", "Horizontal Scroll", "Pan"),
-        ("↕", "j, k, Up, Down", "Scroll down/up"),
-        ("⌖", "Ctrl+j, k, Up, Down", "Move focus down/up"),
-        ("🔃", "Alt+j, k, Up, Down", "Move focused item down/up"),
         ("🔎", "Ctrl+Scroll", "Zoom"),
-        ("〰", "b", "Show or hide the design hierarchy"),
-        ("☰", "m", "Show or hide menu"),
-    ];
+    ]);
 
     Grid::new("controls")
         .num_columns(2)
         .spacing([20., 5.])
         .show(ui, |ui| {
-            for (symbol, control, description) in controls {
+            for (symbol, control, description) in bound.chain(controls) {
                 ui.label(format!("{symbol}  {control}"));
                 ui.label(description);
                 ui.end_row();
@@ -190,6 +190,16 @@ fn controls_listing(ui: &mut egui::Ui) {
     add_hint_text(ui);
 }
 
+/// Controls which can not be rebound, such as the mouse
+fn fixed_controls(controls: &[(&'static str, &str, &str)]) -> Vec<(&'static str, String, String)> {
+    controls
+        .iter()
+        .map(|(symbol, control, description)| {
+            (*symbol, control.to_string(), description.to_string())
+        })
+        .collect()
+}
+
 fn add_hint_text(ui: &mut egui::Ui) {
     ui.add_space(20.);
     ui.label(RichText::new("Hint: You can repeat keybinds by typing Alt+0-9 before them. For example, Alt+1 Alt+0 k scrolls 10 steps up."));
@@ -0,0 +1,597 @@
+use std::fmt::Write;
+
+use camino::Utf8PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use color_eyre::eyre::Context;
+use color_eyre::eyre::{anyhow, bail};
+use color_eyre::Result;
+use eframe::egui::{self, DragValue};
+use eframe::epaint::Color32;
+#[cfg(not(target_arch = "wasm32"))]
+use eframe::epaint::Vec2;
+use log::info;
+
+use crate::displayed_item::DisplayedItem;
+use crate::signal_canvas::SignalExt;
+use crate::translation::SignalInfo;
+use crate::view::DrawConfig;
+use crate::viewport::Viewport;
+use crate::wave_container::FieldRef;
+use crate::{message::Message, State};
+
+/// Width of the item name column in exported SVG images
+const SVG_NAME_WIDTH: f32 = 200.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Utf8PathBuf::from(path).extension()?.to_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "svg" => Some(ImageFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageFormat::Png => write!(f, "PNG"),
+            ImageFormat::Svg => write!(f, "SVG"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageExportSettings {
+    pub path: String,
+    pub format: ImageFormat,
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels. SVG images grow to fit all items
+    pub height: u32,
+    /// Pixels per point, used to render PNG images at a higher resolution
+    pub scale: f32,
+    /// Time range to export. The current viewport is used if this is `None`
+    pub range: Option<(f64, f64)>,
+}
+
+impl Default for ImageExportSettings {
+    fn default() -> Self {
+        // PNG export needs skia, which does not build for the web
+        #[cfg(not(target_arch = "wasm32"))]
+        let format = ImageFormat::Png;
+        #[cfg(target_arch = "wasm32")]
+        let format = ImageFormat::Svg;
+        Self {
+            path: format!("surfer.{}", format.to_string().to_lowercase()),
+            format,
+            width: 1280,
+            height: 720,
+            scale: 1.,
+            range: None,
+        }
+    }
+}
+
+impl State {
+    /// Export an image of the displayed items. On the web, the image is offered as a
+    /// download named after the file name of `settings.path`
+    pub fn export_image(&mut self, settings: &ImageExportSettings) -> Result<()> {
+        let Some(waves) = &mut self.waves else {
+            bail!("Can not export an image without a loaded waveform");
+        };
+
+        // Render only the item list and the canvas
+        let old_viewport = waves.viewport.clone();
+        if let Some((start, end)) = settings.range {
+            waves.viewport = Viewport::new(start, end);
+        }
+        let old_layout = (
+            self.config.layout.show_menu,
+            self.config.layout.show_hierarchy,
+            self.show_wave_source,
+            self.command_prompt.visible,
+        );
+        self.config.layout.show_menu = false;
+        self.config.layout.show_hierarchy = false;
+        self.show_wave_source = false;
+        self.command_prompt.visible = false;
+        self.invalidate_draw_commands();
+
+        let mut msgs = vec![];
+        let result = match settings.format {
+            #[cfg(not(target_arch = "wasm32"))]
+            ImageFormat::Png => self.render_png(settings, &mut msgs),
+            #[cfg(target_arch = "wasm32")]
+            ImageFormat::Png => Err(anyhow!("Exporting PNG images is not supported on the web")),
+            ImageFormat::Svg => self.render_svg(settings, &mut msgs).map(String::into_bytes),
+        }
+        .and_then(|bytes| write_image(settings, bytes));
+
+        (
+            self.config.layout.show_menu,
+            self.config.layout.show_hierarchy,
+            self.show_wave_source,
+            self.command_prompt.visible,
+        ) = old_layout;
+        if let Some(waves) = &mut self.waves {
+            waves.viewport = old_viewport;
+        }
+        self.invalidate_draw_commands();
+        *self.last_canvas_rect.borrow_mut() = None;
+
+        // Rendering may find translators which fail, which has to be handled like when
+        // drawing the UI
+        while let Some(msg) = msgs.pop() {
+            self.update(msg);
+        }
+
+        if result.is_ok() {
+            info!("Exported image to {}", settings.path);
+        }
+        result
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_png(
+        &self,
+        settings: &ImageExportSettings,
+        msgs: &mut Vec<Message>,
+    ) -> Result<Vec<u8>> {
+        let size = Vec2::new(
+            settings.width as f32 / settings.scale,
+            settings.height as f32 / settings.scale,
+        );
+
+        let mut surface =
+            skia_safe::surfaces::raster_n32_premul((settings.width as i32, settings.height as i32))
+                .ok_or_else(|| anyhow!("Failed to create a surface to render to"))?;
+        surface.canvas().clear(skia_safe::Color::BLACK);
+
+        egui_skia::draw_onto_surface(
+            &mut surface,
+            |ctx| {
+                ctx.set_visuals(self.get_visuals());
+                msgs.extend(self.draw(ctx, Some(size)));
+            },
+            Some(egui_skia::RasterizeOptions {
+                pixels_per_point: settings.scale,
+                frames_before_screenshot: 2,
+            }),
+        );
+
+        // NOTE: The warning suggests a method which rust-analyzer doesn't find
+        #[allow(deprecated)]
+        let data = surface
+            .image_snapshot()
+            .encode_to_data(skia_safe::EncodedImageFormat::PNG)
+            .ok_or_else(|| anyhow!("Failed to encode image"))?;
+        Ok(data.as_bytes().to_vec())
+    }
+
+    /// Render the displayed items as an SVG image. Unlike the PNG export, this does
+    /// not go through egui, which allows us to emit text as real text elements.
+    fn render_svg(
+        &self,
+        settings: &ImageExportSettings,
+        msgs: &mut Vec<Message>,
+    ) -> Result<String> {
+        let Some(waves) = &self.waves else {
+            bail!("Can not export an image without a loaded waveform");
+        };
+
+        let theme = &self.config.theme;
+        let cfg = DrawConfig::new(settings.height as f32);
+        let gap = 4.;
+        let row_height = cfg.line_height + 2. * gap;
+        let canvas_width = settings.width as f32 - SVG_NAME_WIDTH;
+        if canvas_width <= 0. {
+            bail!("Image must be wider than {SVG_NAME_WIDTH} pixels");
+        }
+
+        // Collect the rows to draw, including the expanded sub-fields of compound signals
+        let mut rows = vec![];
+        for (vidx, item) in waves.displayed_items.iter().enumerate() {
+            match item {
+                DisplayedItem::Signal(signal) => self.svg_signal_rows(
+                    vidx,
+                    signal.display_name.clone(),
+                    FieldRef::without_fields(signal.signal_ref.clone()),
+                    &signal.info,
+                    0,
+                    &mut rows,
+                ),
+                DisplayedItem::Divider(_)
+                | DisplayedItem::Cursor(_)
+                | DisplayedItem::Decoder(_) => rows.push((vidx, item.display_name(), None, 0)),
+            }
+        }
+        let height = (rows.len() as f32 * row_height).max(settings.height as f32);
+
+        self.generate_draw_commands(&cfg, canvas_width, msgs);
+        let draw_data = self.draw_data.borrow();
+        let Some(draw_data) = draw_data.as_ref() else {
+            bail!("Failed to generate draw commands");
+        };
+
+        let text_size = cfg.line_height - 5.;
+        let char_width = text_size * (20. / 31.);
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{height}" viewBox="0 0 {w} {height}" font-family="monospace" font-size="{text_size}">"#,
+            w = settings.width,
+        )?;
+        writeln!(
+            svg,
+            r#"<rect width="{SVG_NAME_WIDTH}" height="{height}" fill="{}"/>"#,
+            svg_color(theme.secondary_ui_color.background)
+        )?;
+        writeln!(
+            svg,
+            r#"<rect x="{SVG_NAME_WIDTH}" width="{canvas_width}" height="{height}" fill="{}"/>"#,
+            svg_color(theme.canvas_colors.background)
+        )?;
+
+        for (row, (vidx, name, field_ref, depth)) in rows.iter().enumerate() {
+            let item = &waves.displayed_items[*vidx];
+            let top = row as f32 * row_height;
+            let y = top + gap;
+
+            let background = item
+                .background_color()
+                .and_then(|color| theme.colors.get(&color).copied())
+                .unwrap_or_else(|| self.get_default_alternating_background_color(row));
+            if background != Color32::TRANSPARENT {
+                writeln!(
+                    svg,
+                    r#"<rect x="{SVG_NAME_WIDTH}" y="{top}" width="{canvas_width}" height="{row_height}" fill="{}"/>"#,
+                    svg_color(background)
+                )?;
+            }
+
+            let color = item
+                .color()
+                .and_then(|color| theme.colors.get(&color).copied())
+                .unwrap_or(theme.signal_default);
+            writeln!(
+                svg,
+                r#"<text x="{x}" y="{ty}" fill="{}" dominant-baseline="middle">{}</text>"#,
+                svg_color(theme.foreground),
+                xml_escape(name),
+                x = 5. + *depth as f32 * 10.,
+                ty = y + cfg.line_height / 2.,
+            )?;
+
+            let commands = match (field_ref, item) {
+                (Some(f), _) => draw_data.draw_commands.get(f),
+                (None, DisplayedItem::Decoder(_)) => draw_data.decoder_commands.get(vidx),
+                (None, _) => None,
+            };
+            let Some(commands) = commands else {
+                continue;
+            };
+
+            for ((old_x, prev), (new_x, new)) in
+                commands.values.iter().zip(commands.values.iter().skip(1))
+            {
+                let old_x = old_x.clamp(0., canvas_width) + SVG_NAME_WIDTH;
+                let new_x = new_x.clamp(0., canvas_width) + SVG_NAME_WIDTH;
+                if new_x <= old_x {
+                    continue;
+                }
+                if commands.is_bool {
+                    let (Some((prev_value, prev_kind)), Some((new_value, new_kind))) =
+                        (&prev.inner, &new.inner)
+                    else {
+                        continue;
+                    };
+                    let (mut old_height, old_color, old_bg) =
+                        prev_value.bool_drawing_spec(color, theme, *prev_kind);
+                    let (mut new_height, _, _) =
+                        new_value.bool_drawing_spec(color, theme, *new_kind);
+                    if new.force_anti_alias {
+                        old_height = 0.;
+                        new_height = 1.;
+                    }
+                    if let Some(bg) = old_bg {
+                        writeln!(
+                            svg,
+                            r#"<rect x="{old_x}" y="{y}" width="{}" height="{}" fill="{}" fill-opacity="{}"/>"#,
+                            new_x - old_x,
+                            cfg.line_height,
+                            svg_color(bg),
+                            bg.a() as f32 / 255.
+                        )?;
+                    }
+                    let old_y = y + (1. - old_height) * cfg.line_height;
+                    let new_y = y + (1. - new_height) * cfg.line_height;
+                    writeln!(
+                        svg,
+                        r#"<polyline points="{old_x},{old_y} {new_x},{old_y} {new_x},{new_y}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                        svg_color(old_color),
+                        theme.linewidth
+                    )?;
+                } else if let Some((value, kind)) = &prev.inner {
+                    let transition_width = (new_x - old_x).min(cfg.max_transition_width as f32);
+                    let (top, mid, bottom) = (y, y + cfg.line_height / 2., y + cfg.line_height);
+                    writeln!(
+                        svg,
+                        r#"<polygon points="{old_x},{mid} {a},{top} {b},{top} {new_x},{mid} {b},{bottom} {a},{bottom}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                        svg_color(kind.color(color, theme)),
+                        theme.linewidth,
+                        a = old_x + transition_width / 2.,
+                        b = new_x - transition_width / 2.,
+                    )?;
+
+                    let num_chars = ((new_x - old_x - transition_width) / char_width).floor();
+                    if num_chars >= 1. {
+                        let content = if value.chars().count() > num_chars as usize {
+                            value
+                                .chars()
+                                .take(num_chars as usize - 1)
+                                .chain(['…'])
+                                .collect::<String>()
+                        } else {
+                            value.clone()
+                        };
+                        writeln!(
+                            svg,
+                            r#"<text x="{}" y="{mid}" fill="{}" dominant-baseline="middle">{}</text>"#,
+                            old_x + transition_width,
+                            svg_color(theme.foreground),
+                            xml_escape(&content)
+                        )?;
+                    }
+                }
+            }
+        }
+
+        if let Some(cursor) = &waves.cursor {
+            let x = waves.viewport.from_time(cursor, canvas_width as f64) as f32;
+            if (0. ..=canvas_width).contains(&x) {
+                writeln!(
+                    svg,
+                    r#"<line x1="{x}" y1="0" x2="{x}" y2="{height}" stroke="{}" stroke-width="{}"/>"#,
+                    svg_color(theme.cursor.color),
+                    theme.cursor.width,
+                    x = x + SVG_NAME_WIDTH,
+                )?;
+            }
+        }
+
+        writeln!(svg, "</svg>")?;
+        Ok(svg)
+    }
+
+    fn svg_signal_rows(
+        &self,
+        vidx: usize,
+        name: String,
+        field: FieldRef,
+        info: &SignalInfo,
+        depth: usize,
+        rows: &mut Vec<(usize, String, Option<FieldRef>, usize)>,
+    ) {
+        // Sub-fields are only exported if they are expanded in the item list
+        let expanded = self.context.as_ref().map_or(false, |ctx| {
+            egui::collapsing_header::CollapsingState::load(ctx, egui::Id::new(&field))
+                .map(|state| state.is_open())
+                .unwrap_or(false)
+        });
+        rows.push((vidx, name, Some(field.clone()), depth));
+
+        if let (SignalInfo::Compound { subfields }, true) = (info, expanded) {
+            for (name, info) in subfields {
+                let mut new_path = field.clone();
+                new_path.field.push(name.clone());
+                self.svg_signal_rows(vidx, name.clone(), new_path, info, depth + 1, rows);
+            }
+        }
+    }
+
+    pub fn draw_export_image_window(&self, ctx: &egui::Context, msgs: &mut Vec<Message>) {
+        let mut open = true;
+        let settings = &mut *self.image_export_settings.borrow_mut();
+        egui::Window::new("Export image")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::Grid::new("export_image_settings")
+                    .num_columns(2)
+                    .spacing([10., 5.])
+                    .show(ui, |ui| {
+                        ui.label("File");
+                        if ui.text_edit_singleline(&mut settings.path).changed() {
+                            if let Some(format) = ImageFormat::from_path(&settings.path) {
+                                settings.format = format;
+                            }
+                        }
+                        ui.end_row();
+
+                        ui.label("Format");
+                        ui.horizontal(|ui| {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            let formats = [ImageFormat::Png, ImageFormat::Svg];
+                            #[cfg(target_arch = "wasm32")]
+                            let formats = [ImageFormat::Svg];
+                            for format in formats {
+                                ui.radio_value(&mut settings.format, format, format.to_string());
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Size");
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut settings.width).clamp_range(1..=16384));
+                            ui.label("×");
+                            ui.add(DragValue::new(&mut settings.height).clamp_range(1..=16384));
+                        });
+                        ui.end_row();
+
+                        if settings.format == ImageFormat::Png {
+                            ui.label("Scale");
+                            ui.add(
+                                DragValue::new(&mut settings.scale)
+                                    .clamp_range(0.25..=8.)
+                                    .speed(0.05),
+                            );
+                            ui.end_row();
+                        }
+
+                        let mut use_range = settings.range.is_some();
+                        ui.label("Time range");
+                        ui.checkbox(&mut use_range, "Custom");
+                        ui.end_row();
+                        match (use_range, &mut settings.range) {
+                            (true, Some((start, end))) => {
+                                ui.label("");
+                                ui.horizontal(|ui| {
+                                    ui.add(DragValue::new(start));
+                                    ui.label("–");
+                                    ui.add(DragValue::new(end));
+                                });
+                                ui.end_row();
+                            }
+                            (true, None) => {
+                                settings.range = self
+                                    .waves
+                                    .as_ref()
+                                    .map(|w| (w.viewport.curr_left, w.viewport.curr_right));
+                            }
+                            (false, _) => settings.range = None,
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Export").clicked() {
+                        msgs.push(Message::ExportImage(settings.clone()));
+                        msgs.push(Message::SetExportImageVisible(false));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        msgs.push(Message::SetExportImageVisible(false));
+                    }
+                });
+            });
+        if !open {
+            msgs.push(Message::SetExportImageVisible(false));
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_image(settings: &ImageExportSettings, bytes: Vec<u8>) -> Result<()> {
+    std::fs::write(&settings.path, bytes)
+        .with_context(|| format!("Failed to write {}", settings.path))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_image(settings: &ImageExportSettings, bytes: Vec<u8>) -> Result<()> {
+    let path = Utf8PathBuf::from(&settings.path);
+    let name = path.file_name().unwrap_or(path.as_str());
+    let mime = match settings.format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Svg => "image/svg+xml",
+    };
+    crate::wasm_util::download_file(name, &bytes, mime)
+}
+
+fn svg_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Parse the arguments of the `export_image` command: `<file> [<width> <height>]`
+pub fn parse_export_image_args(args: &str) -> Option<ImageExportSettings> {
+    let mut words = args.split_whitespace();
+    let path = words.next()?.to_string();
+    let format = ImageFormat::from_path(&path)?;
+    let mut settings = ImageExportSettings {
+        path,
+        format,
+        ..Default::default()
+    };
+    match (words.next(), words.next()) {
+        (Some(width), Some(height)) => {
+            settings.width = width.parse().ok()?;
+            settings.height = height.parse().ok()?;
+        }
+        (None, None) => {}
+        _ => return None,
+    }
+    Some(settings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::load_example;
+    use crate::wave_container::VarName;
+
+    fn export(name: &str, settings: impl FnOnce(String) -> ImageExportSettings) -> Vec<u8> {
+        let mut state = load_example("examples/counter.vcd");
+        state.update(Message::AddSignal(VarName::from_hierarchy_string("tb.clk")));
+        state.update(Message::AddSignal(VarName::from_hierarchy_string(
+            "tb.dut.counter",
+        )));
+        let path = std::env::temp_dir().join(name);
+        let settings = settings(path.to_str().unwrap().to_string());
+        state.export_image(&settings).unwrap();
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn svg_export_contains_names_and_values() {
+        let svg = export("surfer_test_export.svg", |path| ImageExportSettings {
+            path,
+            format: ImageFormat::Svg,
+            width: 1000,
+            height: 100,
+            range: Some((0., 300.)),
+            ..Default::default()
+        });
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains(">clk</text>"));
+        assert!(svg.contains(">counter</text>"));
+        // The counter is 1 between 110 and 130, which is wide enough for its value
+        assert!(svg.contains(">1</text>"));
+    }
+
+    #[test]
+    fn png_export_writes_png() {
+        let png = export("surfer_test_export.png", |path| ImageExportSettings {
+            path,
+            width: 320,
+            height: 200,
+            ..Default::default()
+        });
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn export_image_args_are_parsed() {
+        let settings = parse_export_image_args("out.svg 800 600").unwrap();
+        assert_eq!(settings.path, "out.svg");
+        assert_eq!(settings.format, ImageFormat::Svg);
+        assert_eq!((settings.width, settings.height), (800, 600));
+        assert_eq!(parse_export_image_args("out.png").unwrap().width, 1280);
+        assert!(parse_export_image_args("out.gif").is_none());
+        assert!(parse_export_image_args("out.png 800").is_none());
+    }
+}
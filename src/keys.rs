@@ -1,6 +1,275 @@
+//! Keyboard handling. Apart from the digits, which select numbered cursors and repeat
+//! counts, and the keys of the command prompt, the bindings are read from the `[keys]`
+//! section of the config. A binding maps a key chord such as `Ctrl+J` to one of the
+//! actions below or to a command as it would be entered in the command prompt.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
 use eframe::egui::{self, Event, Key};
+use fzcmd::parse_command;
+use itertools::Itertools;
+use log::error;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{commands::get_parser, message::Message, MoveDir, State};
+
+/// A key together with the modifiers which are held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+const KEY_NAMES: &[(Key, &str)] = &[
+    (Key::ArrowDown, "Down"),
+    (Key::ArrowLeft, "Left"),
+    (Key::ArrowRight, "Right"),
+    (Key::ArrowUp, "Up"),
+    (Key::Escape, "Escape"),
+    (Key::Tab, "Tab"),
+    (Key::Backspace, "Backspace"),
+    (Key::Enter, "Enter"),
+    (Key::Space, "Space"),
+    (Key::Insert, "Insert"),
+    (Key::Delete, "Delete"),
+    (Key::Home, "Home"),
+    (Key::End, "End"),
+    (Key::PageUp, "PageUp"),
+    (Key::PageDown, "PageDown"),
+    (Key::Minus, "-"),
+    (Key::PlusEquals, "+"),
+    (Key::Num0, "0"),
+    (Key::Num1, "1"),
+    (Key::Num2, "2"),
+    (Key::Num3, "3"),
+    (Key::Num4, "4"),
+    (Key::Num5, "5"),
+    (Key::Num6, "6"),
+    (Key::Num7, "7"),
+    (Key::Num8, "8"),
+    (Key::Num9, "9"),
+    (Key::A, "A"),
+    (Key::B, "B"),
+    (Key::C, "C"),
+    (Key::D, "D"),
+    (Key::E, "E"),
+    (Key::F, "F"),
+    (Key::G, "G"),
+    (Key::H, "H"),
+    (Key::I, "I"),
+    (Key::J, "J"),
+    (Key::K, "K"),
+    (Key::L, "L"),
+    (Key::M, "M"),
+    (Key::N, "N"),
+    (Key::O, "O"),
+    (Key::P, "P"),
+    (Key::Q, "Q"),
+    (Key::R, "R"),
+    (Key::S, "S"),
+    (Key::T, "T"),
+    (Key::U, "U"),
+    (Key::V, "V"),
+    (Key::W, "W"),
+    (Key::X, "X"),
+    (Key::Y, "Y"),
+    (Key::Z, "Z"),
+    (Key::F1, "F1"),
+    (Key::F2, "F2"),
+    (Key::F3, "F3"),
+    (Key::F4, "F4"),
+    (Key::F5, "F5"),
+    (Key::F6, "F6"),
+    (Key::F7, "F7"),
+    (Key::F8, "F8"),
+    (Key::F9, "F9"),
+    (Key::F10, "F10"),
+    (Key::F11, "F11"),
+    (Key::F12, "F12"),
+];
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    /// Parse chords such as `Ctrl+Alt+J`, `Space` or `Ctrl++`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The key itself may be `+`
+        let (modifiers, key) = match s.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => {
+                (rest.strip_suffix('+').unwrap_or(rest), "+")
+            }
+            _ => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let key = KEY_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(key.trim()))
+            .map(|(key, _)| *key)
+            .ok_or_else(|| format!("Unknown key '{key}' in '{s}'"))?;
+
+        let mut chord = KeyChord {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.trim().to_lowercase().as_str() {
+                "ctrl" => chord.ctrl = true,
+                "alt" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return Err(format!("Unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = KEY_NAMES
+            .iter()
+            .find(|(key, _)| *key == self.key)
+            .map_or("?", |(_, name)| *name);
+        for (held, modifier) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ] {
+            if held {
+                write!(f, "{modifier}+")?;
+            }
+        }
+        write!(f, "{name}")
+    }
+}
+
+/// Symbols and descriptions of actions in the order they are listed in the help.
+/// Actions not listed here are described by their command
+const ACTION_DESCRIPTIONS: &[(&str, &str, &str)] = &[
+    ("show_command_prompt", "🚀", "Show command prompt"),
+    (
+        "toggle_side_panel",
+        "〰",
+        "Show or hide the design hierarchy",
+    ),
+    ("toggle_menu", "☰", "Show or hide menu"),
+    ("zoom_in", "🔎➕", "Zoom in"),
+    ("zoom_out", "🔎➖", "Zoom out"),
+    ("scroll_up", "", "Scroll up"),
+    ("scroll_down", "", "Scroll down"),
+    ("move_focused_item_up", "", "Move focused item up"),
+    ("move_focused_item_down", "", "Move focused item down"),
+    ("move_focus_up", "", "Move focus up"),
+    ("move_focus_down", "", "Move focus down"),
+    ("scroll_to_top", "", "Scroll to the first item"),
+    ("scroll_to_bottom", "", "Scroll to the last item"),
+    ("goto_start", "🔙", "Scroll to start"),
+    ("goto_end", "🔚", "Scroll to end"),
+    ("remove_focused_item", "🗙", "Delete focused item"),
+    #[cfg(not(target_arch = "wasm32"))]
+    ("toggle_fullscreen", "⛶", "Toggle full screen"),
+];
 
-use crate::{message::Message, MoveDir, State};
+/// Actions which do nothing on this platform, and are left out of the help
+#[cfg(target_arch = "wasm32")]
+const UNSUPPORTED_ACTIONS: &[&str] = &["toggle_fullscreen"];
+#[cfg(not(target_arch = "wasm32"))]
+const UNSUPPORTED_ACTIONS: &[&str] = &[];
+
+/// The symbol and description of `action`
+pub fn describe_action(action: &str) -> (&'static str, String) {
+    ACTION_DESCRIPTIONS
+        .iter()
+        .find(|(name, _, _)| *name == action)
+        .map_or(("", action.to_string()), |(_, symbol, description)| {
+            (*symbol, description.to_string())
+        })
+}
+
+/// The actions bound to key chords
+#[derive(Debug, Default)]
+pub struct KeyBindings(HashMap<KeyChord, String>);
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut bindings = KeyBindings::default();
+        bindings
+            .merge(HashMap::<String, String>::deserialize(deserializer)?)
+            .map_err(de::Error::custom)?;
+        Ok(bindings)
+    }
+}
+
+impl KeyBindings {
+    /// Bind the chords of `overrides`, replacing the current bindings of the same chords.
+    /// The chords are compared after parsing, so `ctrl+j` replaces `Ctrl+J`, and chords
+    /// bound to `none` are removed
+    pub fn merge(&mut self, overrides: HashMap<String, String>) -> Result<(), String> {
+        // Sorted so that different spellings of a chord in one file resolve the same way
+        // every time
+        for (chord, action) in overrides.into_iter().sorted() {
+            let chord = chord.parse::<KeyChord>()?;
+            if action.is_empty() || action == "none" {
+                self.0.remove(&chord);
+            } else {
+                self.0.insert(chord, action);
+            }
+        }
+        Ok(())
+    }
+
+    /// The action bound to `chord`. Shift is ignored unless a binding requires it, as
+    /// some keys such as `+` need shift on some keyboard layouts
+    pub fn action(&self, chord: &KeyChord) -> Option<&str> {
+        self.0
+            .get(chord)
+            .or_else(|| {
+                self.0.get(&KeyChord {
+                    shift: false,
+                    ..*chord
+                })
+            })
+            .map(String::as_str)
+    }
+
+    /// The chords bound to `action`, such as `J/Down`
+    pub fn chords_for(&self, action: &str) -> Option<String> {
+        let chords = self
+            .0
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| chord.to_string())
+            .sorted_by_key(|chord| (chord.len(), chord.clone()))
+            .join("/");
+        (!chords.is_empty()).then_some(chords)
+    }
+
+    /// The bound actions in the order of the help, with their symbols, chords and
+    /// descriptions
+    pub fn listing(&self) -> Vec<(&'static str, String, String)> {
+        self.0
+            .values()
+            .unique()
+            .filter(|action| !UNSUPPORTED_ACTIONS.contains(&action.as_str()))
+            .sorted_by_key(|action| {
+                let position = ACTION_DESCRIPTIONS
+                    .iter()
+                    .position(|(name, _, _)| *name == action.as_str());
+                (position.unwrap_or(usize::MAX), *action)
+            })
+            .filter_map(|action| {
+                let (symbol, description) = describe_action(action);
+                Some((symbol, self.chords_for(action)?, description))
+            })
+            .collect()
+    }
+}
 
 impl State {
     pub fn handle_pressed_keys(&self, ctx: &egui::Context, msgs: &mut Vec<Message>) {
@@ -47,81 +316,34 @@ impl State {
                     (Key::Num9, true, false, false) => {
                         handle_digit(9, modifiers, msgs);
                     }
-                    (Key::Home, true, false, false) => msgs.push(Message::SetVerticalScroll(0)),
-                    (Key::End, true, false, false) => {
-                        if let Some(vcd) = &self.waves {
-                            if vcd.displayed_items.len() > 1 {
-                                msgs.push(Message::SetVerticalScroll(
-                                    vcd.displayed_items.len() - 1,
-                                ));
-                            }
-                        }
-                    }
-                    (Key::Space, true, false, false) => msgs.push(Message::ShowCommandPrompt(true)),
                     (Key::Escape, true, true, false) => {
                         msgs.push(Message::ShowCommandPrompt(false))
                     }
-                    (Key::Escape, true, false, false) => msgs.push(Message::InvalidateCount),
-                    (Key::Escape, true, _, true) => msgs.push(Message::SetFilterFocused(false)),
-                    (Key::B, true, false, false) => msgs.push(Message::ToggleSidePanel),
-                    (Key::M, true, false, false) => msgs.push(Message::ToggleMenu),
-                    (Key::F11, true, false, _) => msgs.push(Message::ToggleFullscreen),
-                    (Key::S, true, false, false) => msgs.push(Message::GoToStart),
-                    (Key::E, true, false, false) => msgs.push(Message::GoToEnd),
-                    (Key::Minus, true, false, false) => msgs.push(Message::CanvasZoom {
-                        mouse_ptr_timestamp: None,
-                        delta: 2.0,
-                    }),
-                    (Key::PlusEquals, true, false, false) => msgs.push(Message::CanvasZoom {
-                        mouse_ptr_timestamp: None,
-                        delta: 0.5,
-                    }),
-                    (Key::J, true, false, false) => {
-                        if modifiers.alt {
-                            msgs.push(Message::MoveFocus(MoveDir::Down, self.get_count()));
-                        } else if modifiers.ctrl {
-                            msgs.push(Message::MoveFocusedItem(MoveDir::Down, self.get_count()));
-                        } else {
-                            msgs.push(Message::VerticalScroll(MoveDir::Down, self.get_count()));
-                        }
-                        msgs.push(Message::InvalidateCount);
-                    }
-                    (Key::K, true, false, false) => {
-                        if modifiers.alt {
-                            msgs.push(Message::MoveFocus(MoveDir::Up, self.get_count()));
-                        } else if modifiers.ctrl {
-                            msgs.push(Message::MoveFocusedItem(MoveDir::Up, self.get_count()));
-                        } else {
-                            msgs.push(Message::VerticalScroll(MoveDir::Up, self.get_count()));
-                        }
-                        msgs.push(Message::InvalidateCount);
+                    (Key::ArrowUp, true, true, false) => {
+                        msgs.push(Message::CommandPromptHistory(MoveDir::Up))
                     }
-                    (Key::ArrowDown, true, false, false) => {
-                        if modifiers.alt {
-                            msgs.push(Message::MoveFocus(MoveDir::Down, self.get_count()));
-                        } else if modifiers.ctrl {
-                            msgs.push(Message::MoveFocusedItem(MoveDir::Down, self.get_count()));
-                        } else {
-                            msgs.push(Message::VerticalScroll(MoveDir::Down, self.get_count()));
-                        }
-                        msgs.push(Message::InvalidateCount);
+                    (Key::ArrowDown, true, true, false) => {
+                        msgs.push(Message::CommandPromptHistory(MoveDir::Down))
                     }
-                    (Key::ArrowUp, true, false, false) => {
-                        if modifiers.alt {
-                            msgs.push(Message::MoveFocus(MoveDir::Up, self.get_count()));
-                        } else if modifiers.ctrl {
-                            msgs.push(Message::MoveFocusedItem(MoveDir::Up, self.get_count()));
-                        } else {
-                            msgs.push(Message::VerticalScroll(MoveDir::Up, self.get_count()));
-                        }
-                        msgs.push(Message::InvalidateCount);
+                    (Key::R, true, true, false) if modifiers.ctrl => {
+                        msgs.push(Message::CommandPromptSearch(true))
                     }
-                    (Key::Delete, true, false, false) => {
-                        if let Some(vcd) = &self.waves {
-                            if let Some(idx) = vcd.focused_item {
-                                msgs.push(Message::RemoveItem(idx, self.get_count()));
-                                msgs.push(Message::InvalidateCount);
+                    (Key::Escape, true, false, false) => msgs.push(Message::InvalidateCount),
+                    (Key::Escape, true, _, true) => msgs.push(Message::SetFilterFocused(false)),
+                    (key, true, false, filter_focused) => {
+                        let chord = KeyChord {
+                            key: *key,
+                            ctrl: modifiers.ctrl,
+                            alt: modifiers.alt,
+                            shift: modifiers.shift,
+                        };
+                        match self.config.keys.action(&chord) {
+                            // While typing in the signal filter, keys are text except for
+                            // toggling full screen
+                            Some(action) if !filter_focused || action == "toggle_fullscreen" => {
+                                self.run_key_action(action, msgs)
                             }
+                            _ => {}
                         }
                     }
                     _ => {}
@@ -131,6 +353,54 @@ impl State {
         });
     }
 
+    fn run_key_action(&self, action: &str, msgs: &mut Vec<Message>) {
+        let count = self.get_count();
+        let message = match action {
+            "show_command_prompt" => Message::ShowCommandPrompt(true),
+            "toggle_side_panel" => Message::ToggleSidePanel,
+            "toggle_menu" => Message::ToggleMenu,
+            "toggle_fullscreen" => Message::ToggleFullscreen,
+            "goto_start" => Message::GoToStart,
+            "goto_end" => Message::GoToEnd,
+            "zoom_in" => Message::CanvasZoom {
+                mouse_ptr_timestamp: None,
+                delta: 0.5,
+            },
+            "zoom_out" => Message::CanvasZoom {
+                mouse_ptr_timestamp: None,
+                delta: 2.0,
+            },
+            "scroll_up" => Message::VerticalScroll(MoveDir::Up, count),
+            "scroll_down" => Message::VerticalScroll(MoveDir::Down, count),
+            "move_focus_up" => Message::MoveFocus(MoveDir::Up, count),
+            "move_focus_down" => Message::MoveFocus(MoveDir::Down, count),
+            "move_focused_item_up" => Message::MoveFocusedItem(MoveDir::Up, count),
+            "move_focused_item_down" => Message::MoveFocusedItem(MoveDir::Down, count),
+            "scroll_to_top" => Message::SetVerticalScroll(0),
+            "scroll_to_bottom" => match &self.waves {
+                Some(vcd) if vcd.displayed_items.len() > 1 => {
+                    Message::SetVerticalScroll(vcd.displayed_items.len() - 1)
+                }
+                _ => return,
+            },
+            "remove_focused_item" => {
+                let Some(idx) = self.waves.as_ref().and_then(|vcd| vcd.focused_item) else {
+                    return;
+                };
+                Message::RemoveItem(idx, count)
+            }
+            command => match parse_command(command, get_parser(self)) {
+                Ok(message) => message,
+                Err(e) => {
+                    error!("Failed to run key binding `{command}`: {e:?}");
+                    return;
+                }
+            },
+        };
+        msgs.push(message);
+        msgs.push(Message::InvalidateCount);
+    }
+
     pub fn get_count(&self) -> usize {
         if let Some(count) = &self.count {
             usize::from_str_radix(count, 10).unwrap_or(1)
@@ -149,3 +419,97 @@ fn handle_digit(digit: u8, modifiers: &egui::Modifiers, msgs: &mut Vec<Message>)
         msgs.push(Message::GoToCursorPosition(digit))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chord(key: Key, ctrl: bool, alt: bool, shift: bool) -> KeyChord {
+        KeyChord {
+            key,
+            ctrl,
+            alt,
+            shift,
+        }
+    }
+
+    #[test]
+    fn chords_are_parsed() {
+        assert_eq!("j".parse(), Ok(chord(Key::J, false, false, false)));
+        assert_eq!(
+            "Ctrl+Alt+Down".parse(),
+            Ok(chord(Key::ArrowDown, true, true, false))
+        );
+        assert_eq!("shift+f11".parse(), Ok(chord(Key::F11, false, false, true)));
+        assert_eq!("+".parse(), Ok(chord(Key::PlusEquals, false, false, false)));
+        assert_eq!(
+            "ctrl++".parse(),
+            Ok(chord(Key::PlusEquals, true, false, false))
+        );
+        assert!("Hyper+J".parse::<KeyChord>().is_err());
+        assert!("Ctrl+".parse::<KeyChord>().is_err());
+        assert!("Enterprise".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn chords_are_displayed() {
+        assert_eq!(chord(Key::K, true, false, true).to_string(), "Ctrl+Shift+K");
+        assert_eq!(chord(Key::Minus, false, false, false).to_string(), "-");
+    }
+
+    #[test]
+    fn bindings_ignore_shift_unless_required() {
+        let bindings: KeyBindings = toml::from_str(
+            r#"
+            "+" = "zoom_in"
+            "j" = "scroll_down"
+            "Shift+J" = "goto_end"
+            "Down" = "scroll_down"
+            "b" = "none"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            bindings.action(&chord(Key::PlusEquals, false, false, true)),
+            Some("zoom_in")
+        );
+        assert_eq!(
+            bindings.action(&chord(Key::J, false, false, true)),
+            Some("goto_end")
+        );
+        assert_eq!(bindings.action(&chord(Key::B, false, false, false)), None);
+        assert_eq!(
+            bindings.chords_for("scroll_down").as_deref(),
+            Some("J/Down")
+        );
+        assert_eq!(
+            bindings.listing()[0],
+            ("🔎➕", "+".to_string(), "Zoom in".to_string())
+        );
+    }
+
+    #[test]
+    fn overrides_replace_chords_spelled_differently() {
+        let mut bindings: KeyBindings = toml::from_str(
+            r#"
+            "Ctrl+J" = "move_focused_item_down"
+            "B" = "toggle_side_panel"
+            "M" = "toggle_menu"
+            "#,
+        )
+        .unwrap();
+        let overrides = [("ctrl+j", "goto_end"), ("b", "none"), ("m", "")]
+            .into_iter()
+            .map(|(chord, action)| (chord.to_string(), action.to_string()))
+            .collect();
+        bindings.merge(overrides).unwrap();
+
+        assert_eq!(
+            bindings.action(&chord(Key::J, true, false, false)),
+            Some("goto_end")
+        );
+        assert_eq!(bindings.action(&chord(Key::B, false, false, false)), None);
+        assert_eq!(bindings.action(&chord(Key::M, false, false, false)), None);
+        assert_eq!(bindings.chords_for("move_focused_item_down"), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! User-defined macros from the `[macros]` section of the config. A macro runs a
+//! sequence of commands, in which `$1`, `$2`, ... are replaced by its arguments, for
+//! example `view_bus = ["divider_add $1", "signal_add $1.valid", "signal_add $1.data"]`.
+//! Macros can run other macros and command files, up to
+//! [MAX_COMMAND_DEPTH](crate::commands::MAX_COMMAND_DEPTH) levels deep.
+use fzcmd::{Command, ParamGreed};
+use regex::{Captures, Regex};
+
+use crate::message::Message;
+
+fn param_regex() -> Regex {
+    Regex::new(r"\$(\d+)").unwrap()
+}
+
+/// The number of arguments of a macro, which is the highest `$N` in its commands
+pub fn num_params(commands: &[String]) -> usize {
+    let regex = param_regex();
+    commands
+        .iter()
+        .flat_map(|command| {
+            regex
+                .captures_iter(command)
+                .filter_map(|caps| caps[1].parse::<usize>().ok())
+                .collect::<Vec<_>>()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Replace the parameters in `commands` by the whitespace separated `args`, or return
+/// `None` if the number of arguments is wrong
+pub fn expand_macro(commands: &[String], args: &str) -> Option<Vec<String>> {
+    let args = args.split_whitespace().collect::<Vec<_>>();
+    if args.len() != num_params(commands) {
+        return None;
+    }
+    let regex = param_regex();
+    Some(
+        commands
+            .iter()
+            .map(|command| {
+                regex
+                    .replace_all(command, |caps: &Captures| {
+                        caps[1]
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|n| args.get(n.checked_sub(1)?))
+                            .map_or(caps[0].to_string(), |arg| arg.to_string())
+                    })
+                    .into_owned()
+            })
+            .collect(),
+    )
+}
+
+/// The command which runs the macro `name`
+pub fn macro_command(name: &str, commands: &[String]) -> Command<Message> {
+    let name = name.to_string();
+    let commands = commands.to_vec();
+    if num_params(&commands) == 0 {
+        return Command::Terminal(Message::RunMacro { name, commands });
+    }
+    Command::NonTerminal(
+        ParamGreed::Rest,
+        vec![],
+        Box::new(move |query, _| {
+            expand_macro(&commands, query).map(|commands| {
+                Command::Terminal(Message::RunMacro {
+                    name: name.clone(),
+                    commands,
+                })
+            })
+        }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use fzcmd::parse_command;
+
+    use super::*;
+    use crate::commands::{get_parser, MAX_COMMAND_DEPTH};
+    use crate::displayed_item::DisplayedItem;
+    use crate::tests::load_example;
+    use crate::State;
+
+    fn commands(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parameters_are_counted() {
+        assert_eq!(num_params(&commands(&["zoom_fit", "goto_end"])), 0);
+        assert_eq!(
+            num_params(&commands(&["signal_add $1.a", "signal_add $3.$2"])),
+            3
+        );
+    }
+
+    #[test]
+    fn parameters_are_replaced() {
+        let commands = commands(&["divider_add $1", "signal_add $1.$2", "zoom_fit"]);
+        assert_eq!(
+            expand_macro(&commands, "tb.axi  awvalid"),
+            Some(vec![
+                "divider_add tb.axi".to_string(),
+                "signal_add tb.axi.awvalid".to_string(),
+                "zoom_fit".to_string(),
+            ])
+        );
+        assert_eq!(expand_macro(&commands, "tb.axi"), None);
+        assert_eq!(expand_macro(&commands, "tb.axi awvalid awready"), None);
+    }
+
+    fn num_dividers(state: &State) -> usize {
+        state
+            .waves
+            .as_ref()
+            .unwrap()
+            .displayed_items
+            .iter()
+            .filter(|item| matches!(item, DisplayedItem::Divider(_)))
+            .count()
+    }
+
+    #[test]
+    fn configured_macros_are_commands() {
+        let mut state = load_example("examples/counter.vcd");
+        state
+            .config
+            .macros
+            .insert("mark".to_string(), commands(&["divider_add $1"]));
+
+        let Ok(Message::RunMacro { name, commands }) =
+            parse_command("mark here", get_parser(&state))
+        else {
+            panic!("Expected the macro to be run")
+        };
+        assert_eq!(name, "mark");
+        assert_eq!(commands, vec!["divider_add here".to_string()]);
+
+        state.update(Message::RunMacro { name, commands });
+        assert_eq!(num_dividers(&state), 1);
+    }
+
+    #[test]
+    fn recursive_macros_stop() {
+        let mut state = load_example("examples/counter.vcd");
+        let macros = &mut state.config.macros;
+        macros.insert("again".to_string(), commands(&["divider_add x", "again"]));
+        macros.insert("ping".to_string(), commands(&["divider_add x", "pong"]));
+        macros.insert("pong".to_string(), commands(&["ping"]));
+
+        state.update(parse_command("again", get_parser(&state)).unwrap());
+        assert_eq!(num_dividers(&state), MAX_COMMAND_DEPTH);
+
+        state.update(parse_command("ping", get_parser(&state)).unwrap());
+        assert_eq!(
+            num_dividers(&state),
+            MAX_COMMAND_DEPTH + MAX_COMMAND_DEPTH / 2
+        );
+        assert_eq!(state.command_depth, 0);
+    }
+}
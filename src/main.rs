@@ -1,29 +1,41 @@
+mod analysis;
 mod benchmark;
+mod clipboard;
 mod clock_highlighting;
 mod command_prompt;
 mod commands;
 mod config;
 mod cursor;
+mod decoders;
 mod displayed_item;
 mod help;
+mod image_export;
 mod keys;
+mod macros;
 mod menus;
 mod message;
 mod mousegestures;
+mod qualifier;
+mod remote;
+mod saved_view;
 mod signal_canvas;
 mod signal_filter;
 mod signal_name_type;
 #[cfg(test)]
 mod tests;
 mod time;
+mod tooltips;
 mod translation;
 mod util;
+mod value_table;
+mod vcd_export;
 mod view;
 mod viewport;
 mod wasm_util;
 mod wave_container;
 mod wave_source;
 
+use analysis::Finding;
 use camino::Utf8PathBuf;
 #[cfg(not(target_arch = "wasm32"))]
 use clap::Parser;
@@ -31,6 +43,7 @@ use color_eyre::eyre::Context;
 use color_eyre::Result;
 use config::SurferConfig;
 use displayed_item::DisplayedCursor;
+use displayed_item::DisplayedDecoder;
 use displayed_item::DisplayedDivider;
 use displayed_item::DisplayedItem;
 use displayed_item::DisplayedSignal;
@@ -48,6 +61,7 @@ use eframe::epaint::Vec2;
 use waveform::{Hierarchy, ScopeRef, Timescale, Var, Waveform};
 #[cfg(not(target_arch = "wasm32"))]
 use fern::colors::ColoredLevelConfig;
+use image_export::ImageExportSettings;
 use log::error;
 use log::info;
 use log::trace;
@@ -55,15 +69,20 @@ use log::warn;
 use message::Message;
 use num::bigint::ToBigInt;
 use num::BigInt;
+use num::BigUint;
 use num::FromPrimitive;
 use num::ToPrimitive;
+use remote::{RemoteEvent, RemoteServer};
 use signal_filter::SignalFilterType;
 use signal_name_type::SignalNameType;
 use translation::all_translators;
+use translation::numeric_translators::FixedPointTranslator;
 use translation::spade::SpadeTranslator;
+use translation::user::{load_dropped_translator, load_user_translator, load_user_translators};
 use translation::TranslationPreference;
 use translation::Translator;
 use translation::TranslatorList;
+use util::swapped_index;
 use viewport::Viewport;
 use wasm_util::perform_work;
 use wave_container::FieldRef;
@@ -85,12 +104,41 @@ struct Args {
     spade_state: Option<Utf8PathBuf>,
     #[clap(long)]
     spade_top: Option<String>,
+    /// Render the waveform to an image instead of opening a window. The format
+    /// is determined by the file extension, either .png or .svg
+    #[clap(long)]
+    render: Option<Utf8PathBuf>,
+    /// Size of the rendered image in pixels
+    #[clap(long, num_args = 2, value_names = ["WIDTH", "HEIGHT"], requires = "render")]
+    render_size: Option<Vec<u32>>,
+    /// Export the values of the displayed signals instead of opening a window. The
+    /// format is determined by the file extension, either .csv or .json
+    #[clap(long)]
+    export_values: Option<Utf8PathBuf>,
+    /// Sample the exported values at the rising edges of this clock instead of at
+    /// every change
+    #[clap(long, requires = "export_values")]
+    sample_clock: Option<String>,
+    /// Only export values between these two timestamps
+    #[clap(long, num_args = 2, value_names = ["START", "END"], requires = "export_values")]
+    export_range: Option<Vec<u64>>,
+    /// View to apply before --render or --export-values
+    #[clap(long)]
+    state: Option<Utf8PathBuf>,
+    /// Accept remote control commands on this address, either a TCP address such as
+    /// 127.0.0.1:4000 or unix: followed by the path of a Unix socket
+    #[clap(long)]
+    listen: Option<String>,
+    /// File of commands, one per line, to run once the waveform is loaded
+    #[clap(long)]
+    command_file: Option<Utf8PathBuf>,
 }
 
 struct StartupParams {
     pub spade_state: Option<Utf8PathBuf>,
     pub spade_top: Option<String>,
     pub waves: Option<WaveSource>,
+    pub command_file: Option<Utf8PathBuf>,
 }
 
 impl StartupParams {
@@ -100,6 +148,7 @@ impl StartupParams {
             spade_state: None,
             spade_top: None,
             waves: None,
+            command_file: None,
         }
     }
 
@@ -109,6 +158,7 @@ impl StartupParams {
             spade_state: None,
             spade_top: None,
             waves: url.map(WaveSource::Url),
+            command_file: None,
         }
     }
 
@@ -118,6 +168,7 @@ impl StartupParams {
             spade_state: args.spade_state,
             spade_top: args.spade_top,
             waves: args.vcd_file.map(WaveSource::File),
+            command_file: args.command_file,
         }
     }
 }
@@ -137,9 +188,13 @@ fn main() -> Result<()> {
     let stdout_config = fern::Dispatch::new()
         .level(log::LevelFilter::Info)
         .format(move |out, message, record| {
+            let location = commands::command_location()
+                .map(|location| format!("{location}: "))
+                .unwrap_or_default();
             out.finish(format_args!(
-                "[{}] {}",
+                "[{}] {}{}",
                 colors.color(record.level()),
+                location,
                 message
             ))
         })
@@ -168,6 +223,10 @@ fn main() -> Result<()> {
     });
 
     let args = Args::parse();
+    if args.render.is_some() || args.export_values.is_some() {
+        return run_headless(args);
+    }
+    let listen = args.listen.clone();
     let mut state = State::new(StartupParams::from_args(args))?;
 
     let options = eframe::NativeOptions {
@@ -184,6 +243,12 @@ fn main() -> Result<()> {
         Box::new(|cc| {
             state.context = Some(cc.egui_ctx.clone());
             cc.egui_ctx.set_visuals(state.get_visuals());
+            if let Some(addr) = listen {
+                match RemoteServer::listen(&addr, state.msg_sender.clone(), cc.egui_ctx.clone()) {
+                    Ok(server) => state.remote = Some(server),
+                    Err(e) => error!("{e:#?}"),
+                }
+            }
             Box::new(state)
         }),
     )
@@ -192,6 +257,81 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Load the waveform and the saved view, then render and export the results
+/// requested by the command line arguments without opening a window
+#[cfg(not(target_arch = "wasm32"))]
+fn run_headless(args: Args) -> Result<()> {
+    if args.vcd_file.is_none() {
+        color_eyre::eyre::bail!("--render and --export-values require a waveform file");
+    }
+
+    let image_settings = args
+        .render
+        .as_ref()
+        .map(|output| {
+            let Some(format) = image_export::ImageFormat::from_path(output.as_str()) else {
+                color_eyre::eyre::bail!("Can not render to {output}, expected a .png or .svg file");
+            };
+            let mut settings = ImageExportSettings {
+                path: output.to_string(),
+                format,
+                ..Default::default()
+            };
+            if let Some([width, height]) = args.render_size.as_deref() {
+                settings.width = *width;
+                settings.height = *height;
+            }
+            Ok(settings)
+        })
+        .transpose()?;
+
+    let table_settings = args
+        .export_values
+        .as_ref()
+        .map(|output| {
+            let Some(format) = value_table::TableFormat::from_path(output.as_str()) else {
+                color_eyre::eyre::bail!(
+                    "Can not export values to {output}, expected a .csv or .json file"
+                );
+            };
+            Ok(value_table::ValueTableSettings {
+                path: output.to_string(),
+                format,
+                sampling: args
+                    .sample_clock
+                    .as_deref()
+                    .map(|clock| {
+                        value_table::Sampling::RisingEdge(VarName::from_hierarchy_string(clock))
+                    })
+                    .unwrap_or(value_table::Sampling::Changes),
+                range: match args.export_range.as_deref() {
+                    Some([start, end]) => {
+                        value_table::TableRange::Time((*start).into(), (*end).into())
+                    }
+                    _ => value_table::TableRange::All,
+                },
+            })
+        })
+        .transpose()?;
+
+    let view = args
+        .state
+        .as_ref()
+        .map(saved_view::SavedView::load)
+        .transpose()?;
+
+    let mut state = State::new(StartupParams::from_args(args))?;
+    state.load_headless(view.as_ref())?;
+
+    if let Some(settings) = image_settings {
+        state.export_image(&settings)?;
+    }
+    if let Some(settings) = table_settings {
+        state.export_value_table(&settings)?;
+    }
+    Ok(())
+}
+
 // When compiling to web using trunk:
 #[cfg(target_arch = "wasm32")]
 fn main() -> Result<()> {
@@ -204,7 +344,7 @@ fn main() -> Result<()> {
 
     let url = wasm_util::vcd_from_url();
 
-    let state = State::new(StartupParams::vcd_from_url(url))?;
+    let mut state = State::new(StartupParams::vcd_from_url(url))?;
 
     wasm_bindgen_futures::spawn_local(async {
         eframe::WebRunner::new()
@@ -212,6 +352,7 @@ fn main() -> Result<()> {
                 "the_canvas_id", // hardcode it
                 web_options,
                 Box::new(|cc| {
+                    state.context = Some(cc.egui_ctx.clone());
                     cc.egui_ctx.set_visuals(state.get_visuals());
                     Box::new(state)
                 }),
@@ -260,6 +401,11 @@ impl WaveData {
                 DisplayedItem::Signal(s) => new_waves.signal_exists(&s.signal_ref),
                 DisplayedItem::Divider(_) => true,
                 DisplayedItem::Cursor(_) => true,
+                DisplayedItem::Decoder(d) => d
+                    .decoder
+                    .all_inputs()
+                    .iter()
+                    .all(|input| new_waves.signal_exists(input)),
             })
             .collect::<Vec<_>>();
         let mut nested_format = self
@@ -320,6 +466,21 @@ impl WaveData {
                 .unwrap_or(false)
         });
         new_wave.signal_format.extend(nested_format);
+        // Decoders see the full traces of their inputs, so they are decoded again
+        // rather than translated lazily like signals
+        let frames = new_wave
+            .displayed_items
+            .iter()
+            .map(|item| match item {
+                DisplayedItem::Decoder(d) => new_wave.decode(d.decoder.as_ref()),
+                _ => vec![],
+            })
+            .collect::<Vec<_>>();
+        for (item, frames) in new_wave.displayed_items.iter_mut().zip(frames) {
+            if let DisplayedItem::Decoder(d) = item {
+                d.frames = frames;
+            }
+        }
         new_wave
     }
 }
@@ -339,6 +500,8 @@ pub enum ColorSpecifier {
 
 struct CachedDrawData {
     pub draw_commands: HashMap<FieldRef, signal_canvas::DrawingCommands>,
+    /// Drawing commands of decoders, by their index in the displayed items
+    pub decoder_commands: HashMap<usize, signal_canvas::DrawingCommands>,
     pub clock_edges: Vec<f32>,
 }
 
@@ -374,9 +537,22 @@ pub struct State {
     wanted_timescale: Timescale,
     gesture_start_location: Option<emath::Pos2>,
     show_url_entry: bool,
+    show_export_image: bool,
     signal_filter_focused: bool,
     signal_filter_type: SignalFilterType,
     rename_target: Option<usize>,
+    /// The decoder whose frames are listed in a table
+    frame_table_target: Option<usize>,
+    /// The findings of the last analysis, listed in a panel
+    analysis_findings: Option<Vec<Finding>>,
+    /// Remote control clients, which are sent events
+    remote: Option<RemoteServer>,
+    /// Command file given at startup, which is run once the waveform is loaded
+    startup_command_file: Option<Utf8PathBuf>,
+    /// The source and numbered lines of commands which wait for a waveform to load
+    deferred_commands: Option<(String, Vec<(usize, String)>)>,
+    /// How many command files and macros are running inside each other
+    command_depth: usize,
 
     /// The draw commands for every signal currently selected
     // For performance reasons, these need caching so we have them in a RefCell for interior
@@ -389,6 +565,9 @@ pub struct State {
     last_canvas_rect: RefCell<Option<Rect>>,
     signal_filter: RefCell<String>,
     item_renaming_string: RefCell<String>,
+    image_export_settings: RefCell<ImageExportSettings>,
+    /// Signedness and number of fractional bits in the fixed-point format menu
+    fixed_point_settings: RefCell<(bool, u64)>,
 }
 
 impl State {
@@ -396,7 +575,8 @@ impl State {
         let (sender, receiver) = channel();
 
         // Basic translators that we can load quickly
-        let translators = all_translators();
+        let mut translators = all_translators();
+        load_user_translators(&mut translators);
 
         // Long running translators which we load in a thread
         {
@@ -431,6 +611,9 @@ impl State {
                 visible: false,
                 expanded: String::from(""),
                 suggestions: vec![],
+                history: command_prompt::load_history(),
+                history_index: None,
+                searching: false,
             },
             context: None,
             show_about: false,
@@ -439,7 +622,14 @@ impl State {
             wanted_timescale: Timescale::Unit,
             gesture_start_location: None,
             show_url_entry: false,
+            show_export_image: false,
             rename_target: None,
+            frame_table_target: None,
+            analysis_findings: None,
+            remote: None,
+            startup_command_file: args.command_file,
+            deferred_commands: None,
+            command_depth: 0,
             show_wave_source: true,
             signal_filter_focused: false,
             signal_filter_type: SignalFilterType::Fuzzy,
@@ -449,6 +639,8 @@ impl State {
             last_canvas_rect: RefCell::new(None),
             signal_filter: RefCell::new(String::new()),
             item_renaming_string: RefCell::new(String::new()),
+            image_export_settings: RefCell::new(ImageExportSettings::default()),
+            fixed_point_settings: RefCell::new((true, 8)),
         };
 
         match args.waves {
@@ -457,7 +649,11 @@ impl State {
             Some(WaveSource::DragAndDrop(_)) => {
                 error!("Attempted to load from drag and drop at startup (how?)")
             }
-            None => {}
+            None => {
+                if let Some(file) = result.startup_command_file.take() {
+                    result.run_command_file(&file)
+                }
+            }
         }
 
         Ok(result)
@@ -480,7 +676,9 @@ impl State {
                 let Some(waves) = self.waves.as_mut() else {
                     return;
                 };
-                waves.add_signal(&self.translators, &sig)
+                if let Some(translator) = waves.add_signal(&self.translators, &sig) {
+                    self.blacklisted_translators.insert((sig, translator));
+                }
             }
             Message::AddDivider(name) => {
                 let Some(waves) = self.waves.as_mut() else {
@@ -494,6 +692,34 @@ impl State {
                         name,
                     }));
             }
+            Message::AddDecoder(decoder) => {
+                let Some(waves) = self.waves.as_mut() else {
+                    warn!("Adding decoder without waves loaded");
+                    return;
+                };
+                if let Some(missing) = decoder
+                    .all_inputs()
+                    .into_iter()
+                    .find(|input| !waves.inner.signal_exists(input))
+                {
+                    error!(
+                        "Decoder input {} does not exist",
+                        missing.full_path_string()
+                    );
+                    return;
+                }
+                let frames = waves.decode(decoder.as_ref());
+                waves
+                    .displayed_items
+                    .push(DisplayedItem::Decoder(DisplayedDecoder {
+                        display_name: decoder.name(),
+                        decoder,
+                        frames,
+                        color: None,
+                        background_color: None,
+                    }));
+                self.invalidate_draw_commands();
+            }
             Message::AddModule(module) => {
                 let Some(waves) = self.waves.as_mut() else {
                     warn!("Adding module without waves loaded");
@@ -502,7 +728,9 @@ impl State {
 
                 let signals = waves.inner.signals_in_module(&module);
                 for signal in signals {
-                    waves.add_signal(&self.translators, &signal);
+                    if let Some(translator) = waves.add_signal(&self.translators, &signal) {
+                        self.blacklisted_translators.insert((signal, translator));
+                    }
                 }
                 self.invalidate_draw_commands();
             }
@@ -522,6 +750,8 @@ impl State {
                 let visible_signals_len = waves.displayed_items.len();
                 if visible_signals_len > 0 && idx < visible_signals_len {
                     waves.focused_item = Some(idx);
+                    let name = waves.displayed_items[idx].name();
+                    self.remote_event(RemoteEvent::ItemFocused { index: idx, name });
                 } else {
                     error!(
                         "Can not focus signal {idx} because only {visible_signals_len} signals are visible.",
@@ -605,6 +835,11 @@ impl State {
                     }
                     if visible_signals_len > 0 && idx <= (visible_signals_len - 1) {
                         waves.displayed_items.remove(idx);
+                        self.frame_table_target = match self.frame_table_target {
+                            Some(target) if target == idx => None,
+                            Some(target) if target > idx => Some(target - 1),
+                            target => target,
+                        };
                         if let Some(focused) = waves.focused_item {
                             if focused == idx {
                                 if (idx > 0) && (idx == (visible_signals_len - 1)) {
@@ -642,12 +877,18 @@ impl State {
                                 {
                                     waves.displayed_items.swap(i, i - 1);
                                     waves.focused_item = Some(i - 1);
+                                    self.frame_table_target = self
+                                        .frame_table_target
+                                        .map(|target| swapped_index(target, i, i - 1));
                                 }
                             }
                             MoveDir::Down => {
                                 for i in idx..(idx + count).clamp(0, visible_signals_len - 1) {
                                     waves.displayed_items.swap(i, i + 1);
                                     waves.focused_item = Some(i + 1);
+                                    self.frame_table_target = self
+                                        .frame_table_target
+                                        .map(|target| swapped_index(target, i, i + 1));
                                 }
                             }
                         }
@@ -695,6 +936,14 @@ impl State {
                     return;
                 };
 
+                // Fixed-point translators are created on demand since they are
+                // parametrised by the number of fractional bits
+                if !self.translators.all_translator_names().contains(&&format) {
+                    if let Some(translator) = FixedPointTranslator::from_name(&format) {
+                        self.translators.add_basic(Box::new(translator));
+                    }
+                }
+
                 if self.translators.all_translator_names().contains(&&format) {
                     *waves.signal_format.entry(field.clone()).or_default() = format;
 
@@ -707,18 +956,42 @@ impl State {
                             return;
                         };
                         let translator = waves.signal_translator(&field, &self.translators);
-                        let new_info = translator.signal_info(&meta).unwrap();
+                        let new_info = match translator.signal_info(&meta) {
+                            Ok(info) => info,
+                            Err(e) => {
+                                error!("{e:#?}");
+                                self.blacklisted_translators
+                                    .insert((field.root.clone(), translator.name()));
+                                waves.signal_format.remove(&field);
+                                return;
+                            }
+                        };
+
+                        for (path, format) in translator.default_field_formats() {
+                            waves
+                                .signal_format
+                                .entry(FieldRef {
+                                    root: field.root.clone(),
+                                    field: path,
+                                })
+                                .or_insert(format);
+                        }
 
                         for item in &mut waves.displayed_items {
                             match item {
                                 DisplayedItem::Signal(disp) => {
                                     if &disp.signal_ref == &field.root {
-                                        disp.info = new_info;
+                                        disp.info = if disp.bits_expanded {
+                                            new_info.with_bits(meta.num_bits.unwrap_or(0) as u64)
+                                        } else {
+                                            new_info
+                                        };
                                         break;
                                     }
                                 }
                                 DisplayedItem::Cursor(_) => {}
                                 DisplayedItem::Divider(_) => {}
+                                DisplayedItem::Decoder(_) => {}
                             }
                         }
                     }
@@ -745,6 +1018,76 @@ impl State {
                     waves.displayed_items[idx].set_name(name);
                 };
             }
+            Message::SetSignalQualifier(vidx, qualifier) => {
+                let Some(waves) = self.waves.as_mut() else {
+                    return;
+                };
+                if let Some(missing) = qualifier.as_ref().and_then(|qualifier| {
+                    qualifier
+                        .signals()
+                        .into_iter()
+                        .find(|signal| !waves.inner.signal_exists(signal))
+                }) {
+                    error!(
+                        "Qualifier signal {} does not exist",
+                        missing.full_path_string()
+                    );
+                    return;
+                }
+                if let Some(wide) = qualifier.as_ref().and_then(|qualifier| {
+                    qualifier.signals().into_iter().find(|signal| {
+                        let num_bits = waves.inner.signal_meta(signal).map(|meta| meta.num_bits);
+                        !matches!(num_bits, Ok(Some(1)))
+                    })
+                }) {
+                    error!(
+                        "Qualifier signal {} is not a single bit",
+                        wide.full_path_string()
+                    );
+                    return;
+                }
+                if let Some(DisplayedItem::Signal(signal)) = vidx
+                    .or(waves.focused_item)
+                    .and_then(|idx| waves.displayed_items.get_mut(idx))
+                {
+                    signal.qualifier = qualifier;
+                }
+                self.invalidate_draw_commands();
+            }
+            Message::SetBitsExpanded(vidx, expanded) => {
+                let Some(waves) = self.waves.as_mut() else {
+                    return;
+                };
+                let Some(idx) = vidx.or(waves.focused_item) else {
+                    return;
+                };
+                let Some(DisplayedItem::Signal(signal)) = waves.displayed_items.get(idx) else {
+                    return;
+                };
+                let Ok(meta) = waves
+                    .inner
+                    .signal_meta(&signal.signal_ref)
+                    .map_err(|e| warn!("{e:#?}"))
+                else {
+                    return;
+                };
+                let translator = waves.signal_translator(
+                    &FieldRef::without_fields(signal.signal_ref.clone()),
+                    &self.translators,
+                );
+                let Ok(info) = translator.signal_info(&meta).map_err(|e| error!("{e:#?}")) else {
+                    return;
+                };
+                if let Some(DisplayedItem::Signal(signal)) = waves.displayed_items.get_mut(idx) {
+                    signal.bits_expanded = expanded;
+                    signal.info = if expanded {
+                        info.with_bits(meta.num_bits.unwrap_or(0) as u64)
+                    } else {
+                        info
+                    };
+                }
+                self.invalidate_draw_commands();
+            }
             Message::ItemBackgroundColorChange(vidx, color_name) => {
                 let Some(waves) = self.waves.as_mut() else {
                     return;
@@ -762,7 +1105,9 @@ impl State {
             }
             Message::CursorSet(new) => {
                 if let Some(waves) = self.waves.as_mut() {
-                    waves.cursor = Some(new)
+                    let time = new.to_i64();
+                    waves.cursor = Some(new);
+                    self.remote_event(RemoteEvent::CursorMoved { time });
                 }
             }
             Message::LoadVcd(filename) => {
@@ -772,6 +1117,14 @@ impl State {
                 self.load_vcd_from_url(url, false);
             }
             Message::FileDropped(dropped_file) => {
+                if let Some(result) = load_dropped_translator(&mut self.translators, &dropped_file)
+                {
+                    if let Err(e) = result {
+                        error!("{e:#?}")
+                    }
+                    self.invalidate_draw_commands();
+                    return;
+                }
                 self.load_vcd_from_dropped(dropped_file, false)
                     .map_err(|e| error!("{e:#?}"))
                     .ok();
@@ -810,12 +1163,22 @@ impl State {
                     }
                 };
                 self.invalidate_draw_commands();
+                // The findings refer to the old waveform
+                self.analysis_findings = None;
 
                 // Must clone timescale before consuming new_vcd
                 self.wanted_timescale = new_wave.inner.metadata().timescale.1;
+                self.remote_event(RemoteEvent::WavesLoaded {
+                    source: new_wave.source.to_string(),
+                });
                 self.waves = Some(new_wave);
                 self.vcd_progress = None;
                 info!("Done setting up VCD file");
+                if let Some((source, lines)) = self.deferred_commands.take() {
+                    self.run_numbered_commands(&source, lines)
+                } else if let Some(file) = self.startup_command_file.take() {
+                    self.run_command_file(&file)
+                }
             }
             Message::BlacklistTranslator(idx, translator) => {
                 self.blacklisted_translators.insert((idx, translator));
@@ -823,6 +1186,15 @@ impl State {
             Message::Error(e) => {
                 error!("{e:?}")
             }
+            Message::LoadUserTranslator(path) => {
+                if let Err(e) = load_user_translator(&mut self.translators, &path) {
+                    error!("{e:#?}")
+                }
+                self.invalidate_draw_commands();
+            }
+            Message::LoadTranslatorFromUrl(url) => {
+                self.load_translator_from_url(url);
+            }
             Message::TranslatorLoaded(t) => {
                 info!("Translator {} loaded", t.name());
                 self.translators.add(t)
@@ -836,6 +1208,7 @@ impl State {
                     *self.command_prompt_text.borrow_mut() = "".to_string();
                     self.command_prompt.suggestions = vec![];
                     self.command_prompt.expanded = "".to_string();
+                    self.command_prompt.reset_history();
                 }
                 self.command_prompt.visible = new_visibility;
             }
@@ -919,6 +1292,10 @@ impl State {
                     self.invalidate_draw_commands();
                 }
             }
+            Message::GoToTime(time) => {
+                self.go_to_time(&time);
+                self.invalidate_draw_commands();
+            }
             Message::ChangeSignalNameType(vidx, name_type) => {
                 let Some(waves) = self.waves.as_mut() else {
                     return;
@@ -949,6 +1326,21 @@ impl State {
                 *self.command_prompt_text.borrow_mut() = "".to_string();
                 self.command_prompt.expanded = "".to_string();
                 self.command_prompt.suggestions = vec![];
+                self.command_prompt.reset_history();
+            }
+            Message::CommandPromptHistory(dir) => {
+                let command = self.command_prompt.step_history(&dir);
+                self.expand_command_prompt(&command);
+                *self.command_prompt_text.borrow_mut() = command;
+            }
+            Message::CommandPromptSearch(older) => {
+                self.command_prompt.searching = true;
+                let query = self.command_prompt_text.borrow().clone();
+                let found = self.command_prompt.search_history(&query, older);
+                self.expand_command_prompt(&found.unwrap_or_default());
+            }
+            Message::CommandPromptHistoryAdd(command) => {
+                self.command_prompt.add_to_history(command)
             }
             Message::CommandPromptUpdate {
                 expanded,
@@ -964,12 +1356,69 @@ impl State {
             Message::SetKeyHelpVisible(s) => self.show_keys = s,
             Message::SetGestureHelpVisible(s) => self.show_gestures = s,
             Message::SetUrlEntryVisible(s) => self.show_url_entry = s,
+            Message::SetExportImageVisible(s) => self.show_export_image = s,
             Message::SetRenameItemVisible(_) => self.rename_target = None,
+            Message::SetFrameTableVisible(vidx) => self.frame_table_target = vidx,
+            Message::Analyze(settings) => {
+                let Some(waves) = &self.waves else {
+                    warn!("Running analysis without waves loaded");
+                    return;
+                };
+                let traces = waves.analysis_traces(&settings.target);
+                let end = waves.num_timestamps.to_biguint().unwrap_or_default();
+                let sender = self.msg_sender.clone();
+                perform_work(move || {
+                    let findings = analysis::analyze(traces, &settings, &end);
+                    sender.send(Message::AnalysisDone(findings)).unwrap();
+                });
+            }
+            Message::AnalysisDone(findings) => {
+                info!("Analysis found {} problems", findings.len());
+                self.analysis_findings = Some(findings);
+                self.invalidate_draw_commands();
+            }
+            Message::ClearAnalysis => self.analysis_findings = None,
             Message::SetDragStart(pos) => self.gesture_start_location = pos,
             Message::SetFilterFocused(s) => self.signal_filter_focused = s,
             Message::SetSignalFilterType(signal_filter_type) => {
                 self.signal_filter_type = signal_filter_type
             }
+            Message::CopyToClipboard(content) => self.copy_to_clipboard(content),
+            Message::ExportValueTable(settings) => {
+                if let Err(e) = self.export_value_table(&settings) {
+                    error!("{e:#?}")
+                }
+            }
+            Message::ExportVcd(settings) => {
+                if let Err(e) = self.export_vcd(&settings) {
+                    error!("{e:#?}")
+                }
+            }
+            Message::ExportImage(settings) => {
+                if let Err(e) = self.export_image(&settings) {
+                    error!("{e:#?}")
+                }
+            }
+            Message::SaveView(path) => {
+                let Some(waves) = &self.waves else {
+                    return;
+                };
+                if let Err(e) = waves.saved_view().save(&path) {
+                    error!("{e:#?}")
+                }
+            }
+            Message::LoadView(path) => match saved_view::SavedView::load(&path) {
+                Ok(view) => self.apply_saved_view(&view),
+                Err(e) => error!("{e:#?}"),
+            },
+            Message::RunCommandFile(path) => self.run_command_file(&path),
+            Message::RunMacro { name, commands } => {
+                self.run_commands(&name, commands.iter().map(String::as_str))
+            }
+            Message::RemoteCommand(command, reply) => {
+                // The client may have disconnected while waiting
+                reply.send(self.handle_remote_command(command)).ok();
+            }
             Message::Exit | Message::ToggleFullscreen => {} // Handled in eframe::update
         }
     }
@@ -1172,19 +1621,68 @@ impl WaveData {
         self.viewport.curr_right = target_right;
     }
 
-    pub fn add_signal(&mut self, translators: &TranslatorList, sig: &VarName) {
+    /// Find the first time after `time` at which the value of `signal` changes, or at
+    /// which it gets its first value if it has none at `time`. Returns `None` if the
+    /// signal keeps its value until the end of the waveform
+    pub fn next_change_time(&self, signal: &VarName, time: &BigUint) -> Option<BigUint> {
+        let change_time = |t: &BigUint| {
+            self.inner
+                .query_signal(signal, t)
+                .ok()
+                .flatten()
+                .map(|(change_time, _)| change_time)
+        };
+
+        // `None` if the signal has no value yet, in which case we search for the first one
+        let current = change_time(time);
+        let end = self.num_timestamps.to_biguint()?;
+        if &end <= time || change_time(&end) == current {
+            return None;
+        }
+
+        // The change time returned by a query never decreases as the query time
+        // increases, so we can binary search for the first time where it differs
+        let (mut low, mut high) = (time.clone(), end);
+        while &high - &low > BigUint::from(1u32) {
+            let mid: BigUint = (&low + &high) >> 1;
+            if change_time(&mid) == current {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(high)
+    }
+
+    /// Adds `sig` to the displayed items. If the translator selected for the signal fails
+    /// to produce its info, the signal falls back to the default translator and the name
+    /// of the failing translator is returned so that it can be blacklisted.
+    pub fn add_signal(&mut self, translators: &TranslatorList, sig: &VarName) -> Option<String> {
         let Ok(meta) = self
             .inner
             .signal_meta(&sig)
             .context("When adding signal")
             .map_err(|e| error!("{e:#?}"))
         else {
-            return;
+            return None;
         };
 
-        let translator =
-            self.signal_translator(&FieldRef::without_fields(sig.clone()), translators);
-        let info = translator.signal_info(&meta).unwrap();
+        let field = FieldRef::without_fields(sig.clone());
+        let translator = self.signal_translator(&field, translators);
+        let (info, failed_translator) = match translator.signal_info(&meta) {
+            Ok(info) => (info, None),
+            Err(e) => {
+                error!("{e:#?}");
+                let failed_translator = translator.name();
+                let default = translators.get_translator(&translators.default);
+                let Ok(info) = default.signal_info(&meta).map_err(|e| error!("{e:#?}")) else {
+                    return Some(failed_translator);
+                };
+                self.signal_format
+                    .insert(field, translators.default.clone());
+                (info, Some(failed_translator))
+            }
+        };
 
         self.displayed_items
             .push(DisplayedItem::Signal(DisplayedSignal {
@@ -1194,7 +1692,72 @@ impl WaveData {
                 background_color: None,
                 display_name: sig.name.clone(),
                 display_name_type: self.default_signal_name_type,
+                qualifier: None,
+                bits_expanded: false,
             }));
         self.compute_signal_display_names();
+        failed_translator
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::load_vcd_string;
+
+    const VCD: &str = "$timescale 1ns $end
+$scope module top $end
+$var wire 1 ! clk $end
+$var wire 4 \" late [3:0] $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+#5
+1!
+#10
+0!
+b0011 \"
+#15
+1!
+#30
+b0101 \"
+#40
+0!
+";
+
+    fn next_change(waves: &WaveData, signal: &str, time: u32) -> Option<u32> {
+        waves
+            .next_change_time(&VarName::from_hierarchy_string(signal), &time.into())
+            .map(|time| time.try_into().unwrap())
+    }
+
+    #[test]
+    fn next_change_is_found() {
+        let state = load_vcd_string("next_change_is_found", VCD);
+        let waves = state.waves.as_ref().unwrap();
+        assert_eq!(next_change(waves, "top.clk", 0), Some(5));
+        assert_eq!(next_change(waves, "top.clk", 5), Some(10));
+        assert_eq!(next_change(waves, "top.clk", 7), Some(10));
+        assert_eq!(next_change(waves, "top.clk", 15), Some(40));
+        assert_eq!(next_change(waves, "top.clk", 40), None);
+        assert_eq!(next_change(waves, "top.late", 10), Some(30));
+        assert_eq!(next_change(waves, "top.late", 30), None);
+    }
+
+    #[test]
+    fn next_change_finds_first_value() {
+        let state = load_vcd_string("next_change_finds_first_value", VCD);
+        let waves = state.waves.as_ref().unwrap();
+        assert_eq!(next_change(waves, "top.late", 0), Some(10));
+        assert_eq!(next_change(waves, "top.late", 9), Some(10));
+        assert_eq!(
+            waves.change_times(
+                &VarName::from_hierarchy_string("top.late"),
+                &0u32.into(),
+                &40u32.into()
+            ),
+            vec![0u32.into(), 10u32.into(), 30u32.into()]
+        );
     }
 }
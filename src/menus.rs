@@ -1,10 +1,23 @@
 use color_eyre::eyre::WrapErr;
 use eframe::egui::{self, menu};
+use itertools::Itertools;
 
 use crate::{
-    clock_highlighting::ClockHighlightType, displayed_item::DisplayedItem, message::Message,
-    signal_filter::signal_filter_type_menu, signal_name_type::SignalNameType, time::timescale_menu,
-    translation::TranslationPreference, wave_container::FieldRef, wave_source::OpenMode, State,
+    analysis::{parse_analysis_args, AnalysisTarget},
+    clipboard::ClipboardContent,
+    clock_highlighting::ClockHighlightType,
+    decoders::BusDecoder,
+    displayed_item::DisplayedItem,
+    message::Message,
+    qualifier::Qualifier,
+    signal_filter::signal_filter_type_menu,
+    signal_name_type::SignalNameType,
+    time::timescale_menu,
+    translation::numeric_translators::{FixedPointTranslator, NumericTranslator},
+    translation::{SignalInfo, TranslationPreference},
+    wave_container::FieldRef,
+    wave_source::OpenMode,
+    State,
 };
 
 impl State {
@@ -25,6 +38,14 @@ impl State {
                     ui.close_menu();
                 }
                 #[cfg(not(target_arch = "wasm32"))]
+                if ui
+                    .add_enabled(self.waves.is_some(), egui::Button::new("Export image..."))
+                    .clicked()
+                {
+                    msgs.push(Message::SetExportImageVisible(true));
+                    ui.close_menu();
+                }
+                #[cfg(not(target_arch = "wasm32"))]
                 if ui.button("Exit").clicked() {
                     msgs.push(Message::Exit);
                     ui.close_menu();
@@ -91,6 +112,13 @@ impl State {
                     ui.close_menu();
                     msgs.push(Message::ToggleFullscreen);
                 }
+                ui.separator();
+                if ui.button("Find glitches and unknown values").clicked() {
+                    ui.close_menu();
+                    if let Some(settings) = parse_analysis_args(AnalysisTarget::Displayed, "") {
+                        msgs.push(Message::Analyze(settings));
+                    }
+                }
             });
             ui.menu_button("Settings", |ui| {
                 ui.menu_button("Clock highlighting", |ui| {
@@ -233,6 +261,138 @@ impl State {
                         });
                 }
             });
+
+            // One-bit signals in the list can be picked directly, expressions are set
+            // with the signal_set_qualifier command
+            ui.menu_button("Qualifier", |ui| {
+                let candidates = self
+                    .waves
+                    .as_ref()
+                    .unwrap()
+                    .displayed_items
+                    .iter()
+                    .filter_map(|item| match item {
+                        DisplayedItem::Signal(other)
+                            if matches!(other.info, SignalInfo::Bool | SignalInfo::Clock)
+                                && other.signal_ref != signal.signal_ref =>
+                        {
+                            Some(Qualifier::Signal(other.signal_ref.clone()))
+                        }
+                        _ => None,
+                    })
+                    .unique_by(|qualifier| qualifier.to_string())
+                    .collect::<Vec<_>>();
+                for qualifier in candidates {
+                    ui.radio(
+                        signal.qualifier.as_ref() == Some(&qualifier),
+                        qualifier.to_string(),
+                    )
+                    .clicked()
+                    .then(|| {
+                        ui.close_menu();
+                        msgs.push(Message::SetSignalQualifier(Some(vidx), Some(qualifier)));
+                    });
+                }
+                ui.separator();
+                ui.radio(signal.qualifier.is_none(), "None")
+                    .clicked()
+                    .then(|| {
+                        ui.close_menu();
+                        msgs.push(Message::SetSignalQualifier(Some(vidx), None));
+                    });
+            });
+
+            let waves = self.waves.as_ref().unwrap();
+            let num_bits = waves
+                .inner
+                .signal_meta(&signal.signal_ref)
+                .ok()
+                .and_then(|meta| meta.num_bits)
+                .unwrap_or(0);
+            if num_bits > 1 {
+                let label = if signal.bits_expanded {
+                    "Collapse bits"
+                } else {
+                    "Expand bits"
+                };
+                if ui.button(label).clicked() {
+                    ui.close_menu();
+                    msgs.push(Message::SetBitsExpanded(Some(vidx), !signal.bits_expanded));
+                }
+            }
+
+            // The one-bit signals from this one down to the next signal which is not a
+            // single bit are combined, in the order of the list or the reverse
+            let bits = waves.displayed_items[vidx..]
+                .iter()
+                .map_while(|item| match item {
+                    DisplayedItem::Signal(bit)
+                        if matches!(bit.info, SignalInfo::Bool | SignalInfo::Clock) =>
+                    {
+                        Some(bit.signal_ref.clone())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if bits.len() > 1 {
+                ui.menu_button("Combine into bus", |ui| {
+                    for (label, lsb_first) in [
+                        ("First signal is the most significant bit", false),
+                        ("First signal is the least significant bit", true),
+                    ] {
+                        if ui.button(label).clicked() {
+                            ui.close_menu();
+                            msgs.push(Message::AddDecoder(Box::new(BusDecoder::new(
+                                bits.clone(),
+                                lsb_first,
+                            ))));
+                        }
+                    }
+                });
+            }
+        }
+
+        if let DisplayedItem::Decoder(_) = &self.waves.as_ref().unwrap().displayed_items[vidx] {
+            if ui.button("Show frame table").clicked() {
+                ui.close_menu();
+                msgs.push(Message::SetFrameTableVisible(Some(vidx)));
+            }
+        }
+
+        if let Some(path) = path {
+            ui.menu_button("Copy", |ui| {
+                let cursor_set = self.waves.as_ref().unwrap().cursor.is_some();
+                let copy_entries = [
+                    (
+                        "Value",
+                        cursor_set,
+                        ClipboardContent::Value(Some(vidx), path.field.clone()),
+                    ),
+                    (
+                        "Raw value",
+                        cursor_set && path.field.is_empty(),
+                        ClipboardContent::RawValue(Some(vidx)),
+                    ),
+                    (
+                        "Name",
+                        true,
+                        ClipboardContent::Name(Some(vidx), path.field.clone()),
+                    ),
+                    (
+                        "All values at cursor",
+                        cursor_set,
+                        ClipboardContent::ValueTable,
+                    ),
+                ];
+                for (label, enabled, content) in copy_entries {
+                    ui.add_enabled(enabled, egui::Button::new(label))
+                        .clicked()
+                        .then(|| {
+                            ui.close_menu();
+                            msgs.push(Message::CopyToClipboard(content));
+                        });
+                }
+            });
         }
 
         if ui.button("Remove").clicked() {
@@ -308,6 +468,23 @@ impl State {
                     msgs.push(msg);
                 });
             }
+            ui.separator();
+            ui.menu_button("Fixed point", |ui| {
+                let (signed, frac_bits) = &mut *self.fixed_point_settings.borrow_mut();
+                ui.checkbox(signed, "Signed");
+                ui.horizontal(|ui| {
+                    ui.label("Fractional bits");
+                    ui.add(egui::DragValue::new(frac_bits).clamp_range(0..=1024));
+                });
+                let name = NumericTranslator::name(&FixedPointTranslator {
+                    signed: *signed,
+                    frac_bits: *frac_bits,
+                });
+                if ui.button(format!("Use {name}")).clicked() {
+                    ui.close_menu();
+                    msgs.push(Message::SignalFormatChange(path.clone(), name));
+                }
+            });
         });
     }
 }
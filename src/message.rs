@@ -9,9 +9,17 @@ use waveform::{TimescaleUnit, Waveform};
 use num::BigInt;
 
 use crate::{
+    analysis::{AnalysisSettings, Finding},
+    clipboard::ClipboardContent,
     clock_highlighting::ClockHighlightType,
+    decoders::ProtocolDecoder,
+    image_export::ImageExportSettings,
+    qualifier::Qualifier,
+    remote::{RemoteCommand, RemoteResult},
     signal_name_type::SignalNameType,
     translation::Translator,
+    value_table::ValueTableSettings,
+    vcd_export::VcdExportSettings,
     wave_container::{FieldRef, ScopeName, VarName},
     wave_source::OpenMode,
     CommandCount, MoveDir, SignalFilterType, WaveSource,
@@ -37,6 +45,11 @@ pub enum Message {
     ItemColorChange(Option<usize>, Option<String>),
     ItemBackgroundColorChange(Option<usize>, Option<String>),
     ItemNameChange(Option<usize>, String),
+    /// Only show the values of the signal while the qualifier is high. `None` removes
+    /// the qualifier
+    SetSignalQualifier(Option<usize>, Option<Qualifier>),
+    /// Show or hide the individual bits of a signal as fields
+    SetBitsExpanded(Option<usize>, bool),
     ChangeSignalNameType(Option<usize>, SignalNameType),
     ForceSignalNameTypes(SignalNameType),
     SetClockHighlightType(ClockHighlightType),
@@ -60,6 +73,10 @@ pub enum Message {
     WavesLoaded(WaveSource, Box<Waveform>, bool),
     Error(color_eyre::eyre::Error),
     TranslatorLoaded(#[derivative(Debug = "ignore")] Box<dyn Translator + Send>),
+    /// Load a user defined translator from a TOML, Python or WebAssembly file
+    LoadUserTranslator(Utf8PathBuf),
+    /// Download a WebAssembly translator and load it
+    LoadTranslatorFromUrl(String),
     /// Take note that the specified translator errored on a `translates` call on the
     /// specified signal
     BlacklistTranslator(VarName, String),
@@ -75,6 +92,12 @@ pub enum Message {
     ToggleMenu,
     SetTimeScale(TimescaleUnit),
     CommandPromptClear,
+    /// Show an older or newer command from the history in the command prompt
+    CommandPromptHistory(MoveDir),
+    /// Search the history for the command prompt input, continuing with older commands
+    /// if the argument is set
+    CommandPromptSearch(bool),
+    CommandPromptHistoryAdd(String),
     CommandPromptUpdate {
         expanded: String,
         suggestions: Vec<(String, Vec<bool>)>,
@@ -84,14 +107,47 @@ pub enum Message {
     SetKeyHelpVisible(bool),
     SetGestureHelpVisible(bool),
     SetUrlEntryVisible(bool),
+    SetExportImageVisible(bool),
     SetRenameItemVisible(bool),
+    /// Show the table of frames of the decoder at the index, or hide it
+    SetFrameTableVisible(Option<usize>),
+    /// Scan signals for glitches and unknown values
+    Analyze(AnalysisSettings),
+    /// The findings of an analysis which ran in the background
+    AnalysisDone(Vec<Finding>),
+    /// Close the list of findings of the last analysis
+    ClearAnalysis,
     SetDragStart(Option<Pos2>),
     SetFilterFocused(bool),
     SetSignalFilterType(SignalFilterType),
     ToggleFullscreen,
     AddDivider(String),
+    /// Add a row with the frames decoded by a protocol decoder
+    AddDecoder(#[derivative(Debug = "ignore")] Box<dyn ProtocolDecoder + Send>),
     SetCursorPosition(u8),
     GoToCursorPosition(u8),
+    /// Center the view on a time
+    GoToTime(BigInt),
+    CopyToClipboard(ClipboardContent),
+    ExportImage(ImageExportSettings),
+    ExportValueTable(ValueTableSettings),
+    ExportVcd(VcdExportSettings),
+    /// Save the displayed items, time range and cursor to a `.ron` file
+    SaveView(Utf8PathBuf),
+    /// Restore a view saved with `SaveView`
+    LoadView(Utf8PathBuf),
+    /// Run the commands in a file, one per line
+    RunCommandFile(Utf8PathBuf),
+    /// Run the commands of a user-defined macro
+    RunMacro {
+        name: String,
+        commands: Vec<String>,
+    },
+    /// A command from a remote control client, which is sent the result
+    RemoteCommand(
+        RemoteCommand,
+        #[derivative(Debug = "ignore")] std::sync::mpsc::Sender<RemoteResult>,
+    ),
     /// Exit the application. This has no effect on wasm and closes the window
     /// on other platforms
     Exit,
@@ -0,0 +1,277 @@
+//! Qualifiers of displayed signals. A qualifier is a one-bit signal, or an expression
+//! of one-bit signals combined with `!`, `&`, `|` and parentheses, such as
+//! `tb.dut.valid & !tb.dut.stall`. The values of a qualified signal are only shown while
+//! its qualifier is high, and are drawn as not present otherwise.
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use color_eyre::eyre::{anyhow, bail};
+use color_eyre::Result;
+use num::{BigUint, Zero};
+
+use crate::translation::{BasicTranslator, BinaryTranslator};
+use crate::wave_container::VarName;
+use crate::WaveData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Qualifier {
+    Signal(VarName),
+    Not(Box<Qualifier>),
+    And(Box<Qualifier>, Box<Qualifier>),
+    Or(Box<Qualifier>, Box<Qualifier>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+fn tokenize(expr: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = expr.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '!' | '~' => Token::Not,
+            // `&&` and `||` are accepted as well
+            '&' => {
+                chars.next_if_eq(&'&');
+                Token::And
+            }
+            '|' => {
+                chars.next_if_eq(&'|');
+                Token::Or
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            c if c.is_whitespace() => continue,
+            c => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"!~&|()".contains(*c))
+                {
+                    name.push(c)
+                }
+                Token::Name(name)
+            }
+        };
+        tokens.push(token)
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Qualifier> {
+        let mut lhs = self.and()?;
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            lhs = Qualifier::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Qualifier> {
+        let mut lhs = self.unary()?;
+        while self.tokens.next_if_eq(&Token::And).is_some() {
+            lhs = Qualifier::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Qualifier> {
+        match self.tokens.next() {
+            Some(Token::Name(name)) => Ok(Qualifier::Signal(VarName::from_hierarchy_string(&name))),
+            Some(Token::Not) => Ok(Qualifier::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => bail!("Expected )"),
+                }
+            }
+            Some(token) => bail!("Unexpected {token:?}"),
+            None => bail!("Unexpected end of qualifier"),
+        }
+    }
+}
+
+impl Qualifier {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(expr).into_iter().peekable(),
+        };
+        let qualifier = parser.or()?;
+        match parser.tokens.next() {
+            None => Ok(qualifier),
+            Some(token) => Err(anyhow!("Unexpected {token:?} in qualifier {expr}")),
+        }
+    }
+
+    /// The signals used by the qualifier
+    pub fn signals(&self) -> Vec<&VarName> {
+        match self {
+            Qualifier::Signal(signal) => vec![signal],
+            Qualifier::Not(inner) => inner.signals(),
+            Qualifier::And(lhs, rhs) | Qualifier::Or(lhs, rhs) => {
+                lhs.signals().into_iter().chain(rhs.signals()).collect()
+            }
+        }
+    }
+
+    /// Evaluate the qualifier with the values of the signals given by `value`. Unknown
+    /// values, such as `x` and `z`, are `None` and make the result unknown unless the
+    /// other operand decides it
+    pub fn eval(&self, value: &impl Fn(&VarName) -> Option<bool>) -> Option<bool> {
+        match self {
+            Qualifier::Signal(signal) => value(signal),
+            Qualifier::Not(inner) => inner.eval(value).map(|v| !v),
+            Qualifier::And(lhs, rhs) => match (lhs.eval(value), rhs.eval(value)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Qualifier::Or(lhs, rhs) => match (lhs.eval(value), rhs.eval(value)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Display for Qualifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Qualifier::Signal(signal) => write!(f, "{}", signal.full_path_string()),
+            Qualifier::Not(inner) => match inner.as_ref() {
+                Qualifier::Signal(_) | Qualifier::Not(_) => write!(f, "!{inner}"),
+                _ => write!(f, "!({inner})"),
+            },
+            Qualifier::And(lhs, rhs) => {
+                let operand = |q: &Qualifier| match q {
+                    Qualifier::Or(_, _) => format!("({q})"),
+                    _ => q.to_string(),
+                };
+                write!(f, "{} & {}", operand(lhs), operand(rhs))
+            }
+            Qualifier::Or(lhs, rhs) => write!(f, "{lhs} | {rhs}"),
+        }
+    }
+}
+
+impl WaveData {
+    /// Whether `qualifier` is high at `time`, together with the last time at or before
+    /// `time` at which one of its signals changed
+    pub fn qualifier_at(&self, qualifier: &Qualifier, time: &BigUint) -> (bool, BigUint) {
+        let mut last_change = BigUint::zero();
+        let values = qualifier
+            .signals()
+            .into_iter()
+            .map(|signal| {
+                let meta = self.inner.signal_meta(signal).ok();
+                let bit = self
+                    .inner
+                    .query_signal(signal, time)
+                    .ok()
+                    .flatten()
+                    .and_then(|(change_time, value)| {
+                        last_change = last_change.clone().max(change_time);
+                        let num_bits = meta?.num_bits.unwrap_or(0) as u64;
+                        let bits = BinaryTranslator {}.basic_translate(num_bits, &value).0;
+                        match bits.chars().last() {
+                            Some('1') => Some(true),
+                            Some('0') => Some(false),
+                            _ => None,
+                        }
+                    });
+                (signal.clone(), bit)
+            })
+            .collect::<HashMap<_, _>>();
+        let high = qualifier.eval(&|signal| values.get(signal).copied().flatten()) == Some(true);
+        (high, last_change)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::Message;
+    use crate::tests::load_example;
+    use crate::State;
+
+    fn signal(name: &str) -> Qualifier {
+        Qualifier::Signal(VarName::from_hierarchy_string(name))
+    }
+
+    #[test]
+    fn qualifiers_are_parsed_with_precedence() {
+        let parsed = Qualifier::parse("tb.valid & !tb.stall | tb.flush").unwrap();
+        let expected = Qualifier::Or(
+            Box::new(Qualifier::And(
+                Box::new(signal("tb.valid")),
+                Box::new(Qualifier::Not(Box::new(signal("tb.stall")))),
+            )),
+            Box::new(signal("tb.flush")),
+        );
+        assert_eq!(parsed, expected);
+        assert_eq!(
+            Qualifier::parse("tb.valid&&(tb.a||tb.b)")
+                .unwrap()
+                .to_string(),
+            "tb.valid & (tb.a | tb.b)"
+        );
+        assert_eq!(Qualifier::parse("tb.valid").unwrap(), signal("tb.valid"));
+    }
+
+    #[test]
+    fn malformed_qualifiers_are_rejected() {
+        assert!(Qualifier::parse("").is_err());
+        assert!(Qualifier::parse("(tb.valid").is_err());
+        assert!(Qualifier::parse("tb.valid tb.ready").is_err());
+        assert!(Qualifier::parse("tb.valid &").is_err());
+    }
+
+    #[test]
+    fn unknown_values_only_matter_if_they_decide_the_result() {
+        let values = |name: &VarName| match name.name.as_str() {
+            "high" => Some(true),
+            "low" => Some(false),
+            _ => None,
+        };
+        let eval = |expr| Qualifier::parse(expr).unwrap().eval(&values);
+        assert_eq!(eval("high & !low"), Some(true));
+        assert_eq!(eval("x & low"), Some(false));
+        assert_eq!(eval("x & high"), None);
+        assert_eq!(eval("x | high"), Some(true));
+        assert_eq!(eval("!x"), None);
+    }
+
+    #[test]
+    fn only_single_bit_qualifiers_are_accepted() {
+        let mut state = load_example("examples/counter.vcd");
+        state.update(Message::AddSignal(VarName::from_hierarchy_string(
+            "tb.dut.overflow",
+        )));
+        let qualifier = |state: &State| match &state.waves.as_ref().unwrap().displayed_items[0] {
+            crate::displayed_item::DisplayedItem::Signal(signal) => signal.qualifier.clone(),
+            _ => panic!("Expected a signal"),
+        };
+
+        state.update(Message::SetSignalQualifier(
+            Some(0),
+            Some(signal("tb.dut.counter")),
+        ));
+        assert_eq!(qualifier(&state), None);
+        state.update(Message::SetSignalQualifier(
+            Some(0),
+            Some(signal("tb.reset")),
+        ));
+        assert_eq!(qualifier(&state), Some(signal("tb.reset")));
+    }
+}
@@ -0,0 +1,470 @@
+//! Remote control of Surfer over a local TCP or Unix socket, for editors and test
+//! benches which want to drive the viewer.
+//!
+//! The protocol is newline delimited JSON. Every request carries the protocol version,
+//! an optional id which is echoed in the response, and a command, for example
+//! `{"version": 1, "id": 3, "command": "add_signal", "name": "tb.dut.clk"}`. Requests
+//! from one client are handled in order and each gets a response, either
+//! `{"version": 1, "id": 3, "status": "ok"}` or `{..., "status": "error", "error": "..."}`.
+//! In addition, all clients are sent events such as
+//! `{"version": 1, "event": "cursor_moved", "time": 100}`.
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use color_eyre::{eyre::Context, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use eframe::egui;
+#[cfg(not(target_arch = "wasm32"))]
+use log::{info, warn};
+use num::{BigInt, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+use crate::wave_container::{ScopeName, VarName};
+use crate::State;
+
+pub const PROTOCOL_VERSION: u64 = 1;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    LoadFile { path: String },
+    LoadUrl { url: String },
+    AddSignal { name: String },
+    AddScope { name: String },
+    AddDivider { name: String },
+    RemoveItem { index: usize },
+    FocusItem { index: usize },
+    SetCursor { time: i64 },
+    ZoomToRange { start: f64, end: f64 },
+    ZoomToFit,
+    GetState,
+}
+
+/// The state of the viewer, sent in response to `get_state`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RemoteState {
+    source: Option<String>,
+    items: Vec<String>,
+    focused_item: Option<usize>,
+    cursor: Option<i64>,
+    num_timestamps: Option<i64>,
+}
+
+pub type RemoteResult = std::result::Result<Option<RemoteState>, String>;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RemoteEvent {
+    WavesLoaded { source: String },
+    CursorMoved { time: Option<i64> },
+    ItemFocused { index: usize, name: String },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum ResponseStatus {
+    Ok {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        state: Option<RemoteState>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct Response {
+    version: u64,
+    id: Option<u64>,
+    #[serde(flatten)]
+    status: ResponseStatus,
+}
+
+#[derive(Serialize)]
+struct EventMessage<'a> {
+    version: u64,
+    #[serde(flatten)]
+    event: &'a RemoteEvent,
+}
+
+/// The lines to write to a client. They are written by a thread per client, so that a
+/// slow client does not block the UI thread
+type Client = Arc<Sender<String>>;
+
+/// Start the thread writing the lines sent to the returned client to `writer`. The thread
+/// stops when the client is dropped or the writer fails
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_writer(mut writer: Box<dyn Write + Send>) -> Client {
+    let (lines, receiver) = channel::<String>();
+    std::thread::spawn(move || {
+        for line in receiver {
+            if writer.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+    Arc::new(lines)
+}
+
+/// Queue `value` to be written to `client`, returning false if it has disconnected
+fn send_line(client: &Client, value: &impl Serialize) -> bool {
+    let line = serde_json::to_string(value).expect("Remote messages are serializable");
+    client.send(line + "\n").is_ok()
+}
+
+/// Parse a request, returning its id, if any, along with the command
+fn parse_request(line: &str) -> (Option<u64>, std::result::Result<RemoteCommand, String>) {
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return (None, Err(format!("Malformed request: {e}"))),
+    };
+    let id = value.get("id").and_then(|id| id.as_u64());
+    let command = match value.get("version").and_then(|version| version.as_u64()) {
+        Some(PROTOCOL_VERSION) => {
+            serde_json::from_value(value).map_err(|e| format!("Malformed request: {e}"))
+        }
+        Some(version) => Err(format!(
+            "Unsupported protocol version {version}, expected {PROTOCOL_VERSION}"
+        )),
+        None => Err("Missing protocol version".to_string()),
+    };
+    (id, command)
+}
+
+/// The connected clients, which are sent events
+#[derive(Clone, Default)]
+pub struct RemoteServer {
+    clients: Arc<Mutex<Vec<Client>>>,
+}
+
+impl RemoteServer {
+    /// Listen on `addr`, which is either a loopback TCP address such as `127.0.0.1:4000`
+    /// or `unix:` followed by the path of a Unix socket. Commands are sent to the UI thread
+    /// through `sender` and `ctx` is repainted to handle them right away
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn listen(addr: &str, sender: Sender<Message>, ctx: egui::Context) -> Result<Self> {
+        let server = Self::default();
+
+        let accept = {
+            let server = server.clone();
+            move |reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>| {
+                let server = server.clone();
+                let sender = sender.clone();
+                let ctx = ctx.clone();
+                std::thread::spawn(move || server.serve(reader, writer, sender, ctx));
+            }
+        };
+
+        if let Some(path) = addr.strip_prefix("unix:") {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::FileTypeExt;
+                use std::os::unix::net::UnixListener;
+
+                // A socket left behind by an earlier run would make binding fail. Other
+                // files are kept, so that a mistyped path does not delete them
+                match std::fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)
+                        .with_context(|| format!("Failed to remove old socket {path}"))?,
+                    Ok(_) => color_eyre::eyre::bail!("{path} exists and is not a socket"),
+                    Err(_) => {}
+                }
+                let listener = UnixListener::bind(path)
+                    .with_context(|| format!("Failed to listen on {path}"))?;
+                std::thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        match stream.and_then(|s| Ok((s.try_clone()?, s))) {
+                            Ok((reader, writer)) => accept(Box::new(reader), Box::new(writer)),
+                            Err(e) => warn!("Failed to accept remote connection: {e}"),
+                        }
+                    }
+                });
+            }
+            #[cfg(not(unix))]
+            color_eyre::eyre::bail!("Unix sockets are not supported on this platform ({path})");
+        } else {
+            use std::net::ToSocketAddrs;
+
+            // Anyone who can connect can load files, so only local clients are allowed
+            let addrs = addr
+                .to_socket_addrs()
+                .with_context(|| format!("Invalid address {addr}"))?
+                .collect::<Vec<_>>();
+            if let Some(public) = addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+                color_eyre::eyre::bail!(
+                    "Refusing to listen on {public}, which is not a loopback address"
+                );
+            }
+            let listener = std::net::TcpListener::bind(&addrs[..])
+                .with_context(|| format!("Failed to listen on {addr}"))?;
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    match stream.and_then(|s| Ok((s.try_clone()?, s))) {
+                        Ok((reader, writer)) => accept(Box::new(reader), Box::new(writer)),
+                        Err(e) => warn!("Failed to accept remote connection: {e}"),
+                    }
+                }
+            });
+        }
+        info!("Listening for remote commands on {addr}");
+        Ok(server)
+    }
+
+    /// Handle the requests of one client until it disconnects. A request is answered
+    /// once the UI thread has handled it, so requests take effect in order
+    #[cfg(not(target_arch = "wasm32"))]
+    fn serve(
+        &self,
+        reader: Box<dyn Read + Send>,
+        writer: Box<dyn Write + Send>,
+        sender: Sender<Message>,
+        ctx: egui::Context,
+    ) {
+        let client = spawn_writer(writer);
+        self.clients.lock().unwrap().push(client.clone());
+
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (id, command) = parse_request(&line);
+            let result = match command {
+                Ok(command) => {
+                    let (reply, response) = channel();
+                    if sender.send(Message::RemoteCommand(command, reply)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                    response
+                        .recv()
+                        .unwrap_or_else(|_| Err("The command was dropped".to_string()))
+                }
+                Err(e) => Err(e),
+            };
+            let status = match result {
+                Ok(state) => ResponseStatus::Ok { state },
+                Err(error) => ResponseStatus::Error { error },
+            };
+            let response = Response {
+                version: PROTOCOL_VERSION,
+                id,
+                status,
+            };
+            if !send_line(&client, &response) {
+                break;
+            }
+        }
+
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|other| !Arc::ptr_eq(other, &client));
+    }
+
+    /// Send `event` to all clients, dropping those which have disconnected
+    pub fn broadcast(&self, event: RemoteEvent) {
+        let message = EventMessage {
+            version: PROTOCOL_VERSION,
+            event: &event,
+        };
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| send_line(client, &message));
+    }
+}
+
+impl State {
+    /// Send `event` to the remote clients, if any
+    pub fn remote_event(&self, event: RemoteEvent) {
+        if let Some(remote) = &self.remote {
+            remote.broadcast(event)
+        }
+    }
+
+    pub fn handle_remote_command(&mut self, command: RemoteCommand) -> RemoteResult {
+        let message = match command {
+            RemoteCommand::LoadFile { path } => Message::LoadVcd(path.into()),
+            RemoteCommand::LoadUrl { url } => Message::LoadVcdFromUrl(url),
+            RemoteCommand::GetState => return Ok(Some(self.remote_state())),
+            command => {
+                let waves = self.waves.as_ref().ok_or("No waveform loaded")?;
+                let num_items = waves.displayed_items.len();
+                match command {
+                    RemoteCommand::AddSignal { name } => {
+                        let signal = VarName::from_hierarchy_string(&name);
+                        if !waves.inner.signal_exists(&signal) {
+                            return Err(format!("No signal named {name}"));
+                        }
+                        Message::AddSignal(signal)
+                    }
+                    RemoteCommand::AddScope { name } => {
+                        let scope = ScopeName::from_hierarchy_string(&name);
+                        if !waves.inner.has_module(&scope) {
+                            return Err(format!("No scope named {name}"));
+                        }
+                        Message::AddModule(scope)
+                    }
+                    RemoteCommand::AddDivider { name } => Message::AddDivider(name),
+                    RemoteCommand::RemoveItem { index } | RemoteCommand::FocusItem { index }
+                        if index >= num_items =>
+                    {
+                        return Err(format!("No item {index}, there are {num_items} items"));
+                    }
+                    RemoteCommand::RemoveItem { index } => Message::RemoveItem(index, 1),
+                    RemoteCommand::FocusItem { index } => Message::FocusItem(index),
+                    RemoteCommand::SetCursor { time } => Message::CursorSet(BigInt::from(time)),
+                    RemoteCommand::ZoomToRange { start, end } => {
+                        Message::ZoomToRange { start, end }
+                    }
+                    RemoteCommand::ZoomToFit => Message::ZoomToFit,
+                    RemoteCommand::LoadFile { .. }
+                    | RemoteCommand::LoadUrl { .. }
+                    | RemoteCommand::GetState => unreachable!(),
+                }
+            }
+        };
+        self.update(message);
+        Ok(None)
+    }
+
+    fn remote_state(&self) -> RemoteState {
+        let waves = self.waves.as_ref();
+        RemoteState {
+            source: waves.map(|waves| waves.source.to_string()),
+            items: waves
+                .map(|waves| {
+                    waves
+                        .displayed_items
+                        .iter()
+                        .map(|item| item.name())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            focused_item: waves.and_then(|waves| waves.focused_item),
+            cursor: waves
+                .and_then(|waves| waves.cursor.as_ref())
+                .and_then(|cursor| cursor.to_i64()),
+            num_timestamps: waves.and_then(|waves| waves.num_timestamps.to_i64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::load_example;
+
+    #[test]
+    fn requests_are_parsed() {
+        let (id, command) =
+            parse_request(r#"{"version": 1, "id": 3, "command": "add_signal", "name": "tb.clk"}"#);
+        assert_eq!(id, Some(3));
+        assert_eq!(
+            command,
+            Ok(RemoteCommand::AddSignal {
+                name: "tb.clk".to_string()
+            })
+        );
+        assert_eq!(
+            parse_request(r#"{"version": 1, "command": "zoom_to_fit"}"#),
+            (None, Ok(RemoteCommand::ZoomToFit))
+        );
+    }
+
+    #[test]
+    fn bad_requests_are_rejected() {
+        let (id, command) = parse_request(r#"{"version": 2, "id": 5, "command": "zoom_to_fit"}"#);
+        assert_eq!(id, Some(5));
+        assert!(command
+            .unwrap_err()
+            .contains("Unsupported protocol version 2"));
+        assert!(parse_request(r#"{"command": "zoom_to_fit"}"#).1.is_err());
+        assert!(parse_request(r#"{"version": 1, "command": "fly"}"#)
+            .1
+            .is_err());
+        assert!(parse_request(r#"{"version": 1, "command": "set_cursor"}"#)
+            .1
+            .is_err());
+        assert!(parse_request("version 1").1.is_err());
+    }
+
+    #[test]
+    fn responses_and_events_are_serialized() {
+        let response = Response {
+            version: PROTOCOL_VERSION,
+            id: Some(3),
+            status: ResponseStatus::Error {
+                error: "No waveform loaded".to_string(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"{"version":1,"id":3,"status":"error","error":"No waveform loaded"}"#
+        );
+        let event = EventMessage {
+            version: PROTOCOL_VERSION,
+            event: &RemoteEvent::CursorMoved { time: Some(100) },
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"version":1,"event":"cursor_moved","time":100}"#
+        );
+    }
+
+    #[test]
+    fn commands_are_handled_by_the_state() {
+        let mut state = load_example("examples/counter.vcd");
+        let add = |name: &str| RemoteCommand::AddSignal {
+            name: name.to_string(),
+        };
+
+        assert_eq!(state.handle_remote_command(add("tb.dut.counter")), Ok(None));
+        assert_eq!(
+            state.handle_remote_command(add("tb.dut.missing")),
+            Err("No signal named tb.dut.missing".to_string())
+        );
+        assert_eq!(
+            state.handle_remote_command(RemoteCommand::RemoveItem { index: 1 }),
+            Err("No item 1, there are 1 items".to_string())
+        );
+
+        let Ok(Some(remote_state)) = state.handle_remote_command(RemoteCommand::GetState) else {
+            panic!("Expected the state")
+        };
+        assert_eq!(remote_state.items.len(), 1);
+        assert_eq!(remote_state.focused_item, None);
+        assert!(remote_state.source.unwrap().ends_with("counter.vcd"));
+
+        assert_eq!(
+            state.handle_remote_command(RemoteCommand::RemoveItem { index: 0 }),
+            Ok(None)
+        );
+        assert!(state.waves.unwrap().displayed_items.is_empty());
+    }
+
+    #[test]
+    fn public_addresses_are_refused() {
+        let (sender, _) = channel();
+        let result = RemoteServer::listen("0.0.0.0:0", sender, egui::Context::default());
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_which_are_not_sockets_are_kept() {
+        let path = std::env::temp_dir().join("surfer_test_not_a_socket");
+        std::fs::write(&path, "data").unwrap();
+        let (sender, _) = channel();
+        let addr = format!("unix:{}", path.display());
+        assert!(RemoteServer::listen(&addr, sender, egui::Context::default()).is_err());
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,284 @@
+use camino::Utf8PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use color_eyre::eyre::bail;
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use log::error;
+use num::{BigInt, ToPrimitive};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::displayed_item::DisplayedItem;
+use crate::qualifier::Qualifier;
+use crate::wave_container::{FieldRef, ScopeName, VarName};
+use crate::{Message, State, WaveData};
+
+/// An item to add to the list of displayed items when restoring a view
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub enum SavedItem {
+    Signal {
+        /// Full hierarchical name of the signal, e.g. `tb.dut.counter`
+        name: String,
+        /// Name of the translator to use for the signal
+        #[serde(default)]
+        format: Option<String>,
+        /// Name to show in the item list instead of the signal name
+        #[serde(default)]
+        display_name: Option<String>,
+        /// Expression of one-bit signals which must be high for values to be shown, e.g.
+        /// `tb.dut.valid & tb.dut.ready`
+        #[serde(default)]
+        qualifier: Option<String>,
+    },
+    /// Add all signals in the module, e.g. `tb.dut`
+    Module(String),
+    Divider(String),
+}
+
+/// A view of a waveform which can be stored in a `.ron` file, for example
+///
+/// ```ron
+/// (
+///     items: [
+///         Signal(name: "tb.clk"),
+///         Signal(name: "tb.dut.counter", format: Some("Unsigned")),
+///         Signal(name: "tb.dut.data", qualifier: Some("tb.dut.valid")),
+///         Divider("dut"),
+///         Module("tb.dut"),
+///     ],
+///     range: Some((0, 1000)),
+///     cursor: Some(500),
+/// )
+/// ```
+///
+/// Views are saved with the `view_save` command and restored with `view_load` or the
+/// `--state` command line argument. Cursors and decoders are not part of a view.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct SavedView {
+    #[serde(default)]
+    pub items: Vec<SavedItem>,
+    /// The time range to show. The whole waveform is shown if this is `None`
+    #[serde(default)]
+    pub range: Option<(f64, f64)>,
+    #[serde(default)]
+    pub cursor: Option<u64>,
+}
+
+impl SavedView {
+    pub fn load(path: &Utf8PathBuf) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read view from {path}"))?;
+        ron::from_str(&content).with_context(|| format!("Failed to parse view from {path}"))
+    }
+
+    pub fn save(&self, path: &Utf8PathBuf) -> Result<()> {
+        let content = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .context("Failed to serialize view")?;
+        std::fs::write(path, content).with_context(|| format!("Failed to write view to {path}"))
+    }
+}
+
+impl WaveData {
+    /// The currently displayed items, time range and cursor as a view
+    pub fn saved_view(&self) -> SavedView {
+        let items = self
+            .displayed_items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayedItem::Signal(signal) => Some(SavedItem::Signal {
+                    name: signal.signal_ref.full_path_string(),
+                    format: self
+                        .signal_format
+                        .get(&FieldRef::without_fields(signal.signal_ref.clone()))
+                        .cloned(),
+                    // Names which are computed from the signal name are computed again
+                    // when the view is restored
+                    display_name: (signal.display_name != signal.signal_ref.name
+                        && signal.display_name != signal.signal_ref.full_path_string())
+                    .then(|| signal.display_name.clone()),
+                    qualifier: signal.qualifier.as_ref().map(Qualifier::to_string),
+                }),
+                DisplayedItem::Divider(divider) => Some(SavedItem::Divider(divider.name.clone())),
+                DisplayedItem::Cursor(_) | DisplayedItem::Decoder(_) => None,
+            })
+            .collect();
+        SavedView {
+            items,
+            range: Some((self.viewport.curr_left, self.viewport.curr_right)),
+            cursor: self.cursor.as_ref().and_then(BigInt::to_u64),
+        }
+    }
+}
+
+impl State {
+    /// Wait for the waveform to load and apply `view` to it. This is used when running
+    /// without a window, for example when rendering images from the command line.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_headless(&mut self, view: Option<&SavedView>) -> Result<()> {
+        // The messages sent while loading are normally handled by the UI each frame,
+        // so we have to pump them ourselves
+        while self.waves.is_none() {
+            match self.msg_receiver.recv() {
+                Ok(Message::Error(e)) => return Err(e),
+                Ok(msg) => self.update(msg),
+                Err(_) => bail!("Stopped loading before the waveform was loaded"),
+            }
+        }
+        self.handle_async_messages();
+
+        if let Some(view) = view {
+            self.apply_saved_view(view);
+        }
+        Ok(())
+    }
+
+    /// Apply `view` to the currently loaded waveform
+    pub fn apply_saved_view(&mut self, view: &SavedView) {
+        for item in &view.items {
+            match item {
+                SavedItem::Signal {
+                    name,
+                    format,
+                    display_name,
+                    qualifier,
+                } => {
+                    let var = VarName::from_hierarchy_string(name);
+                    self.update(Message::AddSignal(var.clone()));
+                    if let Some(format) = format {
+                        self.update(Message::SignalFormatChange(
+                            FieldRef::without_fields(var),
+                            format.clone(),
+                        ));
+                    }
+                    let vidx = self
+                        .waves
+                        .as_ref()
+                        .and_then(|waves| waves.displayed_items.len().checked_sub(1));
+                    if let (Some(display_name), Some(_)) = (display_name, vidx) {
+                        self.update(Message::ItemNameChange(vidx, display_name.clone()));
+                    }
+                    if let (Some(qualifier), Some(_)) = (qualifier, vidx) {
+                        match Qualifier::parse(qualifier) {
+                            Ok(qualifier) => {
+                                self.update(Message::SetSignalQualifier(vidx, Some(qualifier)))
+                            }
+                            Err(e) => error!("{e:#?}"),
+                        }
+                    }
+                }
+                SavedItem::Module(name) => {
+                    self.update(Message::AddModule(ScopeName::from_hierarchy_string(name)))
+                }
+                SavedItem::Divider(name) => self.update(Message::AddDivider(name.clone())),
+            }
+        }
+
+        if let Some((start, end)) = view.range {
+            self.update(Message::ZoomToRange { start, end });
+        }
+        if let Some(cursor) = view.cursor {
+            self.update(Message::CursorSet(BigInt::from(cursor)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::load_example;
+
+    #[test]
+    fn views_are_parsed() {
+        let view: SavedView = ron::from_str(
+            r#"(
+                items: [
+                    Signal(name: "tb.clk"),
+                    Signal(name: "tb.dut.counter", format: Some("Unsigned")),
+                    Divider("dut"),
+                    Module("tb.dut"),
+                ],
+                cursor: Some(500),
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(
+            view,
+            SavedView {
+                items: vec![
+                    SavedItem::Signal {
+                        name: "tb.clk".to_string(),
+                        format: None,
+                        display_name: None,
+                        qualifier: None,
+                    },
+                    SavedItem::Signal {
+                        name: "tb.dut.counter".to_string(),
+                        format: Some("Unsigned".to_string()),
+                        display_name: None,
+                        qualifier: None,
+                    },
+                    SavedItem::Divider("dut".to_string()),
+                    SavedItem::Module("tb.dut".to_string()),
+                ],
+                range: None,
+                cursor: Some(500),
+            }
+        );
+        assert!(ron::from_str::<SavedView>("(items: [Cursor(1)])").is_err());
+    }
+
+    #[test]
+    fn saved_views_are_restored() {
+        let view = SavedView {
+            items: vec![
+                SavedItem::Signal {
+                    name: "tb.dut.counter".to_string(),
+                    format: Some("Unsigned".to_string()),
+                    display_name: None,
+                    qualifier: None,
+                },
+                SavedItem::Divider("reset".to_string()),
+                SavedItem::Signal {
+                    name: "tb.reset".to_string(),
+                    format: None,
+                    display_name: None,
+                    qualifier: Some("!tb.clk".to_string()),
+                },
+            ],
+            range: Some((100., 200.)),
+            cursor: Some(150),
+        };
+        let path =
+            Utf8PathBuf::from_path_buf(std::env::temp_dir().join("surfer_test_view.ron")).unwrap();
+        view.save(&path).unwrap();
+
+        let mut state = load_example("examples/counter.vcd");
+        state.apply_saved_view(&SavedView::load(&path).unwrap());
+        assert_eq!(state.waves.as_ref().unwrap().saved_view(), view);
+    }
+
+    #[test]
+    fn headless_loading_applies_view() {
+        let mut state = State::new(crate::StartupParams {
+            waves: Some(crate::WaveSource::File(
+                project_root::get_project_root()
+                    .unwrap()
+                    .join("examples/counter.vcd")
+                    .try_into()
+                    .unwrap(),
+            )),
+            ..crate::StartupParams::empty()
+        })
+        .unwrap();
+        let view = SavedView {
+            items: vec![SavedItem::Module("tb.dut".to_string())],
+            range: None,
+            cursor: Some(10),
+        };
+        state.load_headless(Some(&view)).unwrap();
+
+        let waves = state.waves.as_ref().unwrap();
+        assert_eq!(waves.displayed_items.len(), 4);
+        assert_eq!(waves.cursor, Some(BigInt::from(10)));
+    }
+}
@@ -5,29 +5,32 @@ use eframe::egui::{self, Sense};
 use eframe::emath::{self, Align2};
 use eframe::epaint::{Color32, FontId, PathShape, Pos2, Rect, RectShape, Rounding, Stroke, Vec2};
 use log::{error, warn};
+use num::bigint::ToBigInt;
 use num::BigRational;
+use num::BigUint;
 use num::ToPrimitive;
 
 use crate::benchmark::{TimedRegion, TranslationTimings};
 use crate::config::SurferTheme;
-use crate::translation::{SignalInfo, ValueKind};
+use crate::tooltips::PointerPosition;
+use crate::translation::{bit_fields, SignalInfo, ValueKind};
 use crate::view::{DrawConfig, DrawingContext, ItemDrawingInfo};
 use crate::wave_container::FieldRef;
 use crate::{displayed_item::DisplayedItem, CachedDrawData, Message, State};
 
 pub struct DrawnRegion {
-    inner: Option<(String, ValueKind)>,
+    pub inner: Option<(String, ValueKind)>,
     /// True if a transition should be drawn even if there is no change in the value
     /// between the previous and next pixels. Only used by the bool drawing logic to
     /// draw draw a vertical line and prevent apparent aliasing
-    force_anti_alias: bool,
+    pub force_anti_alias: bool,
 }
 
 /// List of values to draw for a signal. It is an ordered list of values that should
 /// be drawn at the *start time* until the *start time* of the next value
 pub struct DrawingCommands {
-    is_bool: bool,
-    values: Vec<(f32, DrawnRegion)>,
+    pub is_bool: bool,
+    pub values: Vec<(f32, DrawnRegion)>,
 }
 
 impl DrawingCommands {
@@ -106,7 +109,26 @@ impl State {
                         &self.translators,
                     );
                     // we need to get the signal info here to get the correct info for aliases
-                    let info = translator.signal_info(&meta).unwrap();
+                    let info = match translator.signal_info(&meta) {
+                        Ok(info) => info,
+                        Err(e) => {
+                            error!("{e:#}");
+                            msgs.push(Message::BlacklistTranslator(
+                                displayed_signal.signal_ref.clone(),
+                                translator.name(),
+                            ));
+                            msgs.push(Message::ResetSignalFormat(FieldRef::without_fields(
+                                displayed_signal.signal_ref.clone(),
+                            )));
+                            return;
+                        }
+                    };
+                    let num_bits = meta.num_bits.unwrap_or(0) as u64;
+                    let info = if displayed_signal.bits_expanded {
+                        info.with_bits(num_bits)
+                    } else {
+                        info
+                    };
 
                     let mut local_commands: HashMap<Vec<_>, _> = HashMap::new();
 
@@ -137,6 +159,15 @@ impl State {
                                     continue;
                                 }
                             };
+                        // A change of the qualifier changes what is drawn just like a
+                        // change of the value
+                        let (qualified, change_time) = match &displayed_signal.qualifier {
+                            Some(qualifier) => {
+                                let (high, qualifier_change) = waves.qualifier_at(qualifier, time);
+                                (high, change_time.max(qualifier_change))
+                            }
+                            None => (true, change_time),
+                        };
 
                         let is_last_timestep = pixel == &end_pixel;
                         let is_first_timestep = pixel == &start_pixel;
@@ -159,6 +190,10 @@ impl State {
                                     sig_name = displayed_signal.signal_ref.full_path_string()
                                 );
                                 error!("{e:#}");
+                                msgs.push(Message::BlacklistTranslator(
+                                    displayed_signal.signal_ref.clone(),
+                                    translator.name(),
+                                ));
                                 msgs.push(Message::ResetSignalFormat(FieldRef {
                                     root: displayed_signal.signal_ref.clone(),
                                     field: vec![],
@@ -179,8 +214,14 @@ impl State {
                                 &self.translators,
                             )
                             .as_fields();
+                        let bits = if displayed_signal.bits_expanded {
+                            bit_fields(num_bits, &val)
+                        } else {
+                            vec![]
+                        };
 
-                        for (path, value) in fields {
+                        for (path, value) in fields.into_iter().chain(bits) {
+                            let value = value.filter(|_| qualified);
                             let prev = prev_values.get(&path);
 
                             // If the value changed between this and the previous pixel, we want to
@@ -241,8 +282,59 @@ impl State {
                     });
                 });
 
+            // Decoded frames are drawn as regions between their start and end, with
+            // an empty region filling the gaps between frames
+            let decoder_commands = waves
+                .displayed_items
+                .iter()
+                .enumerate()
+                .filter_map(|(vidx, item)| match item {
+                    DisplayedItem::Decoder(decoder) => Some((vidx, decoder)),
+                    _ => None,
+                })
+                .map(|(vidx, decoder)| {
+                    let min_x = -cfg.max_transition_width as f32;
+                    let max_x = frame_width + cfg.max_transition_width as f32;
+                    let to_x = |time: &BigUint| {
+                        (waves
+                            .viewport
+                            .from_time(&time.to_bigint().unwrap(), frame_width as f64)
+                            as f32)
+                            .clamp(min_x, max_x)
+                    };
+                    let region = |x, inner| {
+                        (
+                            x,
+                            DrawnRegion {
+                                inner,
+                                force_anti_alias: false,
+                            },
+                        )
+                    };
+
+                    let mut commands = DrawingCommands::new_wide();
+                    let mut prev_end = None;
+                    for frame in &decoder.frames {
+                        let (start, end) = (to_x(&frame.start), to_x(&frame.end));
+                        if end <= min_x || start >= max_x {
+                            continue;
+                        }
+                        if let Some(prev_end) = prev_end.filter(|prev_end| *prev_end < start) {
+                            commands.push(region(prev_end, None));
+                        }
+                        commands.push(region(start, Some((frame.text.clone(), frame.kind))));
+                        prev_end = Some(end);
+                    }
+                    if let Some(prev_end) = prev_end {
+                        commands.push(region(prev_end, None));
+                    }
+                    (vidx, commands)
+                })
+                .collect();
+
             *self.draw_data.borrow_mut() = Some(CachedDrawData {
                 draw_commands,
+                decoder_commands,
                 clock_edges,
             });
         }
@@ -256,11 +348,7 @@ impl State {
     ) {
         let (response, mut painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
 
-        let cfg = DrawConfig {
-            canvas_height: response.rect.size().y,
-            line_height: 16.,
-            max_transition_width: 6,
-        };
+        let cfg = DrawConfig::new(response.rect.size().y);
         // the draw commands have been invalidated, recompute
         if self.draw_data.borrow().is_none()
             || Some(response.rect) != *self.last_canvas_rect.borrow()
@@ -365,6 +453,17 @@ impl State {
                 }
             }
 
+            if let Some(findings) = &self.analysis_findings {
+                self.draw_finding_markers(
+                    vcd,
+                    findings,
+                    item_offsets,
+                    to_screen.transform_pos(Pos2::ZERO).y,
+                    frame_width,
+                    &mut ctx,
+                );
+            }
+
             for drawing_info in item_offsets {
                 // We draw in absolute coords, but the signal offset in the y
                 // direction is also in absolute coordinates, so we need to
@@ -397,12 +496,37 @@ impl State {
                             }
                         }
                     }
+                    ItemDrawingInfo::Decoder(drawing_info) => {
+                        if let Some(commands) = draw_data
+                            .decoder_commands
+                            .get(&drawing_info.signal_list_idx)
+                        {
+                            for (old, new) in
+                                commands.values.iter().zip(commands.values.iter().skip(1))
+                            {
+                                self.draw_region((old, new), color, y_offset, &mut ctx)
+                            }
+                        }
+                    }
                     ItemDrawingInfo::Divider(_) => {}
                     ItemDrawingInfo::Cursor(_) => {}
                 }
             }
         }
 
+        if response.hovered() && !response.dragged() {
+            if let (Some(global), Some(canvas)) = (pointer_pos_global, pointer_pos_canvas) {
+                self.draw_signal_tooltip(
+                    vcd,
+                    item_offsets,
+                    &PointerPosition { global, canvas },
+                    frame_width,
+                    gap,
+                    &ctx,
+                );
+            }
+        }
+
         vcd.draw_cursor(
             &self.config.theme,
             &mut ctx,
@@ -433,7 +557,7 @@ impl State {
                 width: self.config.theme.linewidth,
             };
 
-            let transition_width = (new_x - old_x).min(6.) as f32;
+            let transition_width = (new_x - old_x).min(ctx.cfg.max_transition_width as f32);
 
             let trace_coords = |x, y| (ctx.to_screen)(x, y * ctx.cfg.line_height + offset);
 
@@ -536,7 +660,7 @@ impl State {
     }
 }
 
-trait SignalExt {
+pub(crate) trait SignalExt {
     fn bool_drawing_spec(
         &self,
         user_color: Color32,
@@ -546,7 +670,7 @@ trait SignalExt {
 }
 
 impl ValueKind {
-    fn color(&self, user_color: Color32, theme: &SurferTheme) -> Color32 {
+    pub(crate) fn color(&self, user_color: Color32, theme: &SurferTheme) -> Color32 {
         match self {
             ValueKind::HighImp => theme.signal_highimp,
             ValueKind::Undef => theme.signal_undef,
@@ -1 +1,35 @@
 mod snapshot;
+
+use camino::Utf8PathBuf;
+use project_root::get_project_root;
+
+use crate::{StartupParams, State, WaveSource};
+
+/// Create a state and wait for it to load `file`, which is relative to the project root
+pub fn load_example(file: &str) -> State {
+    load_waves(get_project_root().unwrap().join(file).try_into().unwrap())
+}
+
+/// Create a state and wait for it to load the VCD `content`. The content is written to
+/// a file in the temporary directory, so `name` must be unique among the tests
+pub fn load_vcd_string(name: &str, content: &str) -> State {
+    let path = std::env::temp_dir().join(format!("surfer_test_{name}.vcd"));
+    std::fs::write(&path, content).unwrap();
+    load_waves(path.try_into().unwrap())
+}
+
+fn load_waves(path: Utf8PathBuf) -> State {
+    let mut state = State::new(StartupParams {
+        waves: Some(WaveSource::File(path)),
+        ..StartupParams::empty()
+    })
+    .unwrap();
+
+    loop {
+        state.handle_async_messages();
+        if state.waves.is_some() {
+            break;
+        }
+    }
+    state
+}
@@ -232,6 +232,7 @@ macro_rules! snapshot_ui_with_file_msgs {
                 )),
                 spade_top: None,
                 spade_state: None,
+                command_file: None,
             })
             .unwrap();
 
@@ -280,6 +281,7 @@ snapshot_ui! {example_vcd_renders, || {
         waves: Some(WaveSource::File(get_project_root().unwrap().join("examples/counter.vcd").try_into().unwrap())),
         spade_top: None,
         spade_state: None,
+        command_file: None,
     }).unwrap();
 
     loop {
@@ -315,6 +317,7 @@ snapshot_ui! {resizing_the_canvas_redraws, || {
         waves: Some(WaveSource::File(get_project_root().unwrap().join("examples/counter.vcd").try_into().unwrap())),
         spade_top: None,
         spade_state: None,
+        command_file: None,
     }).unwrap();
 
     loop {
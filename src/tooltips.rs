@@ -0,0 +1,145 @@
+use eframe::egui::{self, Grid, RichText};
+use eframe::epaint::Pos2;
+use itertools::Itertools;
+use num::ToPrimitive;
+
+use crate::time::time_string;
+use crate::translation::{BasicTranslator, BinaryTranslator};
+use crate::view::{DrawingContext, ItemDrawingInfo, SignalDrawingInfo};
+use crate::wave_container::FieldRef;
+use crate::{State, WaveData};
+
+/// The position of the mouse pointer over the canvas
+pub struct PointerPosition {
+    /// Position in screen coordinates, which is compared to the item offsets
+    pub global: Pos2,
+    /// Position relative to the canvas, which is converted to a time
+    pub canvas: Pos2,
+}
+
+impl State {
+    /// Show a tooltip with the full, untruncated value of the signal under the mouse
+    /// pointer, along with its raw value and the time span during which the value is
+    /// constant.
+    pub fn draw_signal_tooltip(
+        &self,
+        waves: &WaveData,
+        item_offsets: &[ItemDrawingInfo],
+        pointer: &PointerPosition,
+        frame_width: f32,
+        gap: f32,
+        ctx: &DrawingContext,
+    ) {
+        let Some(drawing_info) = item_offsets.iter().find_map(|info| match info {
+            ItemDrawingInfo::Signal(signal) => {
+                let top = signal.offset - gap;
+                let bottom = signal.offset + ctx.cfg.line_height + gap;
+                (top..bottom).contains(&pointer.global.y).then_some(signal)
+            }
+            _ => None,
+        }) else {
+            return;
+        };
+
+        let time = waves
+            .viewport
+            .to_time(pointer.canvas.x as f64, frame_width)
+            .round()
+            .to_integer();
+        if time < 0.into() || time > waves.num_timestamps {
+            return;
+        }
+        let Some(time) = time.to_biguint() else {
+            return;
+        };
+
+        let SignalDrawingInfo { field_ref, .. } = drawing_info;
+        let signal = &field_ref.root;
+
+        let Ok(meta) = waves.inner.signal_meta(signal) else {
+            return;
+        };
+        let Some((change_time, value)) = waves.inner.query_signal(signal, &time).ok().flatten()
+        else {
+            return;
+        };
+
+        let translator =
+            waves.signal_translator(&FieldRef::without_fields(signal.clone()), &self.translators);
+        let Ok(translated) = translator.translate(&meta, &value) else {
+            return;
+        };
+        // Show the hovered field along with all of its sub-fields
+        let fields = translated
+            .flatten(
+                FieldRef::without_fields(signal.clone()),
+                &waves.signal_format,
+                &self.translators,
+            )
+            .as_fields()
+            .into_iter()
+            .filter(|(path, _)| path.starts_with(&field_ref.field))
+            .collect_vec();
+
+        let raw = BinaryTranslator {}
+            .basic_translate(meta.num_bits.unwrap_or(0) as u64, &value)
+            .0;
+        let next_change = waves.next_change_time(signal, &time);
+
+        let full_name = if field_ref.field.is_empty() {
+            signal.full_path_string()
+        } else {
+            format!(
+                "{}.{}",
+                signal.full_path_string(),
+                field_ref.field.iter().join(".")
+            )
+        };
+
+        egui::show_tooltip_at_pointer(ctx.painter.ctx(), egui::Id::new("signal_tooltip"), |ui| {
+            ui.label(RichText::new(full_name).strong());
+            ui.separator();
+            Grid::new("signal_tooltip_values")
+                .num_columns(2)
+                .spacing([10., 2.])
+                .show(ui, |ui| {
+                    for (path, value) in &fields {
+                        let name = if path.len() == field_ref.field.len() {
+                            "Value".to_string()
+                        } else {
+                            path[field_ref.field.len()..].join(".")
+                        };
+                        ui.label(name);
+                        ui.label(
+                            RichText::new(value.as_ref().map(|(v, _)| v.as_str()).unwrap_or("-"))
+                                .monospace(),
+                        );
+                        ui.end_row();
+                    }
+                    ui.label("Raw");
+                    ui.label(RichText::new(&raw).monospace());
+                    ui.end_row();
+                    ui.label("Since");
+                    ui.label(time_string(
+                        &change_time.into(),
+                        &waves.inner.metadata(),
+                        &self.wanted_timescale,
+                    ));
+                    ui.end_row();
+                    ui.label("Until");
+                    ui.label(
+                        next_change
+                            .map(|t| {
+                                time_string(
+                                    &t.into(),
+                                    &waves.inner.metadata(),
+                                    &self.wanted_timescale,
+                                )
+                            })
+                            .unwrap_or_else(|| "End of waveform".to_string()),
+                    );
+                    ui.end_row();
+                });
+        });
+    }
+}
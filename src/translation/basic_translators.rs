@@ -1,5 +1,6 @@
 use super::{
-    numeric_translators::NumericTranslator, BasicTranslator, TranslationPreference, ValueKind,
+    numeric_translators::{fixed_point_string, NumericTranslator},
+    BasicTranslator, TranslationPreference, ValueKind,
 };
 
 use color_eyre::Result;
@@ -41,7 +42,7 @@ fn no_of_digits(num_bits: u64, digit_size: u64) -> usize {
 }
 
 /// VCD bit extension
-fn extend_string(val: &String, num_bits: u64) -> String {
+pub(super) fn extend_string(val: &String, num_bits: u64) -> String {
     if num_bits > val.len() as u64 {
         let extra_count = num_bits - val.len() as u64;
         let extra_value = match val.chars().next() {
@@ -157,7 +158,7 @@ fn map_to_radix(s: &String, radix: usize, num_bits: u64) -> (String, ValueKind)
     )
 }
 
-fn check_single_wordlength(len: SignalLength, required: u32) -> Result<TranslationPreference> {
+pub(super) fn check_single_wordlength(len: SignalLength, required: u32) -> Result<TranslationPreference> {
     match len {
         SignalLength::Fixed(bits) if bits.get() == required => Ok(TranslationPreference::Yes),
         _ => Ok(TranslationPreference::No),
@@ -390,6 +391,101 @@ impl NumericTranslator for DoublePrecisionTranslator {
     }
 }
 
+/// The shortest decimal representation of `mantissa * 2^exp` which reads back as the
+/// same value. The digits are computed exactly, so this works for formats which do not
+/// fit in an f64. `narrow_below` is set when the next smaller value is only half a unit
+/// in the last place away, which happens for powers of two
+fn shortest_float_string(
+    negative: bool,
+    mantissa: &BigUint,
+    exp: i64,
+    narrow_below: bool,
+) -> String {
+    let sign = if negative { "-" } else { "" };
+    if mantissa.is_zero() {
+        return format!("{sign}0e0");
+    }
+
+    // The value is exact / 10^shift and the distance to the next value is ulp / 10^shift
+    let (exact, ulp, shift) = if exp >= 0 {
+        (
+            mantissa << exp as usize,
+            BigUint::from(1u32) << exp as usize,
+            0,
+        )
+    } else {
+        let ulp = BigUint::from(5u32).pow(-exp as u32);
+        (mantissa * &ulp, ulp, -exp)
+    };
+    let num_digits = exact.to_string().len();
+    let round_to = |digits: usize| {
+        let scale = BigUint::from(10u32).pow((num_digits - digits) as u32);
+        ((&exact + &scale / 2u32) / &scale, scale)
+    };
+    // A rounded value reads back correctly if it is less than half an ulp away
+    let digits = (1..num_digits)
+        .find(|&digits| {
+            let (rounded, scale) = round_to(digits);
+            let candidate = rounded * scale;
+            if candidate >= exact {
+                (candidate - &exact) * 2u32 < ulp
+            } else if narrow_below {
+                (&exact - candidate) * 4u32 < ulp
+            } else {
+                (&exact - candidate) * 2u32 < ulp
+            }
+        })
+        .unwrap_or(num_digits);
+
+    let rounded = round_to(digits).0.to_string();
+    let exponent = (rounded.len() + num_digits - digits) as i64 - 1 - shift;
+    let (first, rest) = rounded.trim_end_matches('0').split_at(1);
+    if rest.is_empty() {
+        format!("{sign}{first}e{exponent}")
+    } else {
+        format!("{sign}{first}.{rest}e{exponent}")
+    }
+}
+
+/// Decode the bits of an IEEE 754 binary128 number
+fn decode_binary128(v: &BigUint) -> String {
+    let bits = v
+        .iter_u64_digits()
+        .take(2)
+        .enumerate()
+        .fold(0u128, |acc, (i, digit)| acc | (digit as u128) << (64 * i));
+    let negative = bits >> 127 == 1;
+    let exp = (bits >> 112 & 0x7fff) as i64;
+    let frac = bits & ((1u128 << 112) - 1);
+    match exp {
+        0x7fff if frac == 0 => format!("{}inf", if negative { "-" } else { "" }),
+        0x7fff => "NaN".to_string(),
+        // Subnormal, 16494 = 16382 (bias - 1) + 112 (mantissa bits)
+        0 => shortest_float_string(negative, &BigUint::from(frac), -16494, false),
+        // 16495 = 16383 (bias) + 112 (mantissa bits)
+        _ => shortest_float_string(
+            negative,
+            &BigUint::from(frac | 1u128 << 112),
+            exp - 16495,
+            frac == 0 && exp > 1,
+        ),
+    }
+}
+
+pub struct QuadPrecisionTranslator {}
+
+impl NumericTranslator for QuadPrecisionTranslator {
+    fn name(&self) -> String {
+        String::from("FP: 128-bit IEEE 754")
+    }
+    fn translate_biguint(&self, _: u64, v: num::BigUint) -> String {
+        decode_binary128(&v)
+    }
+    fn translates(&self, var: &Var) -> Result<TranslationPreference> {
+        check_single_wordlength(var.length(), 128)
+    }
+}
+
 pub struct HalfPrecisionTranslator {}
 
 impl NumericTranslator for HalfPrecisionTranslator {
@@ -524,6 +620,32 @@ impl NumericTranslator for PositQuire16Translator {
     }
 }
 
+/// The quire for 32-bit posits is a 512-bit two's complement fixed-point number with
+/// 240 fractional bits, i.e. the square of the smallest posit. The most negative value
+/// is NaR. It is too wide for softposit, so it is decoded exactly here
+pub struct PositQuire32Translator {}
+
+impl NumericTranslator for PositQuire32Translator {
+    fn name(&self) -> String {
+        String::from("Posit: quire for 32-bit (two exponent bits)")
+    }
+
+    fn translate_biguint(&self, _: u64, v: num::BigUint) -> String {
+        let sign_bit = BigUint::from(1u32) << 511;
+        if v == sign_bit {
+            "NaR".to_string()
+        } else if v > sign_bit {
+            format!("-{}", fixed_point_string(&((sign_bit << 1) - v), 240))
+        } else {
+            fixed_point_string(&v, 240)
+        }
+    }
+
+    fn translates(&self, var: &Var) -> Result<TranslationPreference> {
+        check_single_wordlength(var.length(), 512)
+    }
+}
+
 /// Decode u8 as 8-bit float with five exponent bits and two mantissa bits
 fn decode_e5m2(v: u8) -> String {
     let mant = v & 3;
@@ -598,78 +720,6 @@ impl NumericTranslator for E4M3Translator {
     }
 }
 
-pub struct RiscvTranslator {}
-
-impl BasicTranslator for RiscvTranslator {
-    fn name(&self) -> String {
-        "RV32I".to_string()
-    }
-
-    fn basic_translate(&self, _num_bits: u64, value: &SignalValue) -> (String, ValueKind) {
-        let u32_value = match value {
-            SignalValue::BigUint(v) => v.to_u32_digits().last().cloned(),
-            SignalValue::String(s) => match check_vector_signal(s) {
-                Some(v) => return v,
-                None => s.parse().ok(),
-            },
-        };
-
-        match asm_riscv::I::try_from(u32_value.unwrap_or(0)) {
-            Ok(insn) => (format!("{}", riscv_to_string(&insn)), ValueKind::Normal),
-            Err(_) => (format!("UNKNOWN INSN"), ValueKind::Warn),
-        }
-    }
-
-    fn translates(&self, var: &Var) -> Result<TranslationPreference> {
-        check_single_wordlength(var.length(), 32)
-    }
-}
-
-fn riscv_to_string(i: &asm_riscv::I) -> String {
-    match i {
-        asm_riscv::I::LUI { d, im } => format!("lui {d:?}, {im:?}"),
-        asm_riscv::I::AUIPC { d, im } => format!("auipc {d:?}, {im:?}"),
-        asm_riscv::I::JAL { d, im } => format!("jal {d:?}, {im:?}"),
-        asm_riscv::I::JALR { d, s, im } => format!("jalr {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::BEQ { s1, s2, im } => format!("beq {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::BNE { s1, s2, im } => format!("bne {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::BLT { s1, s2, im } => format!("blt {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::BGE { s1, s2, im } => format!("bge {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::BLTU { s1, s2, im } => format!("bltu {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::BGEU { s1, s2, im } => format!("bgeu {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::LB { d, s, im } => format!("lb {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::LH { d, s, im } => format!("lh {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::LW { d, s, im } => format!("lw {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::LBU { d, s, im } => format!("lbu {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::LHU { d, s, im } => format!("lhu {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SB { s1, s2, im } => format!("sb {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::SH { s1, s2, im } => format!("sh {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::SW { s1, s2, im } => format!("sw {s1:?}, {s2:?}, {im:?}"),
-        asm_riscv::I::ADDI { d, s, im } => format!("addi {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SLTI { d, s, im } => format!("slti {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SLTUI { d, s, im } => format!("sltui {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::XORI { d, s, im } => format!("xori {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::ORI { d, s, im } => format!("ori {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::ANDI { d, s, im } => format!("andi {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SLLI { d, s, im } => format!("slli {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SRLI { d, s, im } => format!("srli {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::SRAI { d, s, im } => format!("srai {d:?}, {s:?}, {im:?}"),
-        asm_riscv::I::ADD { d, s1, s2 } => format!("add {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SUB { d, s1, s2 } => format!("sub {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SLL { d, s1, s2 } => format!("sll {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SLT { d, s1, s2 } => format!("slt {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SLTU { d, s1, s2 } => format!("sltu {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::XOR { d, s1, s2 } => format!("xor {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SRL { d, s1, s2 } => format!("srl {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::SRA { d, s1, s2 } => format!("sra {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::OR { d, s1, s2 } => format!("or {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::AND { d, s1, s2 } => format!("and {d:?}, {s1:?}, {s2:?}"),
-        asm_riscv::I::ECALL {} => "ecall".to_string(),
-        asm_riscv::I::EBREAK {} => "ebreak".to_string(),
-        asm_riscv::I::FENCE { im } => format!("fence {im:?}"),
-    }
-}
-
 fn decode_lebxxx(value: &num::BigUint) -> Result<num::BigUint, &'static str> {
     let bytes = value.to_bytes_be();
     match bytes.first() {
@@ -1311,4 +1361,50 @@ mod test {
             "invalid flag: 0111 1111 0111 1111"
         )
     }
+
+    fn quad(bits: u128) -> String {
+        QuadPrecisionTranslator {}
+            .basic_translate(128, &SignalValue::BigUint(bits.to_biguint()))
+            .0
+    }
+
+    #[test]
+    fn quad_precision_translation_is_shortest_exact() {
+        assert_eq!(quad(0x3fff0000000000000000000000000000), "1e0");
+        assert_eq!(quad(0x3fff8000000000000000000000000000), "1.5e0");
+        assert_eq!(quad(0xc0000000000000000000000000000000), "-2e0");
+        assert_eq!(quad(0x3ffb999999999999999999999999999a), "1e-1");
+        assert_eq!(
+            quad(0x7ffeffffffffffffffffffffffffffff),
+            "1.189731495357231765085759326628007e4932"
+        );
+    }
+
+    #[test]
+    fn quad_precision_translation_handles_special_values() {
+        assert_eq!(quad(0), "0e0");
+        assert_eq!(quad(1), "6e-4966");
+        assert_eq!(quad(0x7fff0000000000000000000000000000), "inf");
+        assert_eq!(quad(0xffff0000000000000000000000000000), "-inf");
+        assert_eq!(quad(0x7fff8000000000000000000000000000), "NaN");
+    }
+
+    #[test]
+    fn posit_quire32_translation() {
+        let quire = |v: BigUint| {
+            PositQuire32Translator {}
+                .basic_translate(512, &SignalValue::BigUint(v))
+                .0
+        };
+        let one = BigUint::from(1u32);
+        assert_eq!(quire(&one << 240), "1");
+        assert_eq!(quire(BigUint::from(3u32) << 238), "0.75");
+        assert_eq!(quire((&one << 512) - (&one << 239)), "-0.5");
+        assert_eq!(quire(&one << 511), "NaR");
+        // The smallest quire value is 2^-240 = 5^240 / 10^240
+        assert_eq!(
+            quire(one),
+            format!("0.{:0>240}", BigUint::from(5u32).pow(240))
+        );
+    }
 }
@@ -0,0 +1,288 @@
+//! Translators which split a vector into named bit fields using a layout defined by
+//! the user, for example for control and status registers. Layouts are read from
+//! TOML files in the form
+//!
+//! ```toml
+//! name = "mstatus"
+//! width = 32
+//!
+//! [[fields]]
+//! name = "mpp"
+//! bits = "12:11"
+//! translator = "Unsigned"
+//!
+//! [[fields]]
+//! name = "mie"
+//! bits = "3"
+//! ```
+//!
+//! Bit 0 is the least significant bit. The optional `translator` is the initial
+//! format of the field, which can be changed like any other field.
+use std::collections::HashMap;
+
+use color_eyre::eyre::{anyhow, bail, Context};
+use color_eyre::Result;
+use num::BigUint;
+use serde::Deserialize;
+use waveform::{Hierarchy, SignalValue, Var};
+
+use super::basic_translators::{check_single_wordlength, extend_string};
+use super::{
+    SignalInfo, TranslationPreference, TranslationResult, Translator, ValueKind, ValueRepr,
+};
+
+#[derive(Debug, Deserialize)]
+struct FieldSpec {
+    name: String,
+    bits: String,
+    #[serde(default)]
+    translator: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LayoutFile {
+    name: String,
+    width: u32,
+    fields: Vec<FieldSpec>,
+}
+
+struct BitField {
+    name: String,
+    /// Most and least significant bit of the field, inclusive
+    msb: u32,
+    lsb: u32,
+    translator: Option<String>,
+}
+
+pub struct BitFieldTranslator {
+    name: String,
+    width: u32,
+    fields: Vec<BitField>,
+}
+
+fn parse_bits(bits: &str) -> Result<(u32, u32)> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<u32>()
+            .with_context(|| format!("'{bits}' is not a valid bit range"))
+    };
+    match bits.split_once(':') {
+        Some((msb, lsb)) => {
+            let (msb, lsb) = (parse(msb)?, parse(lsb)?);
+            if msb < lsb {
+                bail!("The most significant bit must come first in '{bits}'")
+            }
+            Ok((msb, lsb))
+        }
+        None => parse(bits).map(|bit| (bit, bit)),
+    }
+}
+
+impl BitFieldTranslator {
+    pub fn from_str(content: &str) -> Result<Self> {
+        let file: LayoutFile = toml::from_str(content)?;
+
+        let fields = file
+            .fields
+            .into_iter()
+            .map(|field| {
+                let (msb, lsb) = parse_bits(&field.bits)?;
+                if msb >= file.width {
+                    bail!("Field {} does not fit in {} bits", field.name, file.width);
+                }
+                Ok(BitField {
+                    name: field.name,
+                    msb,
+                    lsb,
+                    translator: field.translator,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name: file.name,
+            width: file.width,
+            fields,
+        })
+    }
+
+    fn translate_bits(&self, bits: &str) -> Result<TranslationResult> {
+        let bits = format!(
+            "{}{bits}",
+            extend_string(&bits.to_string(), self.width as u64)
+        );
+        if bits.len() != self.width as usize {
+            bail!(
+                "{} expects {} bits, got {}",
+                self.name,
+                self.width,
+                bits.len()
+            );
+        }
+
+        let subfields = self
+            .fields
+            .iter()
+            .map(|field| {
+                // The string starts with the most significant bit
+                let start = (self.width - 1 - field.msb) as usize;
+                let end = (self.width - field.lsb) as usize;
+                let field_bits = &bits[start..end];
+                let val = if field.msb == field.lsb {
+                    ValueRepr::Bit(field_bits.chars().next().unwrap())
+                } else {
+                    ValueRepr::Bits((field.msb - field.lsb + 1) as u64, field_bits.to_string())
+                };
+                (
+                    field.name.clone(),
+                    TranslationResult {
+                        val,
+                        subfields: vec![],
+                        color: ValueKind::Normal,
+                        durations: HashMap::new(),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(TranslationResult {
+            val: ValueRepr::Struct,
+            subfields,
+            color: ValueKind::Normal,
+            durations: HashMap::new(),
+        })
+    }
+}
+
+impl Translator for BitFieldTranslator {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn translate(
+        &self,
+        _hierarchy: &Hierarchy,
+        _var: &Var,
+        value: &SignalValue,
+    ) -> Result<TranslationResult> {
+        let bits = match value {
+            SignalValue::Binary(bytes) => format!(
+                "{:0width$b}",
+                BigUint::from_bytes_be(bytes),
+                width = self.width as usize
+            ),
+            SignalValue::String(s) => s.to_string(),
+        };
+        self.translate_bits(&bits)
+    }
+
+    fn signal_info(&self, _hierarchy: &Hierarchy, _var: &Var) -> Result<SignalInfo> {
+        Ok(SignalInfo::Compound {
+            subfields: self
+                .fields
+                .iter()
+                .map(|field| {
+                    let info = if field.msb == field.lsb {
+                        SignalInfo::Bool
+                    } else {
+                        SignalInfo::Bits
+                    };
+                    (field.name.clone(), info)
+                })
+                .collect(),
+        })
+    }
+
+    fn translates(&self, _hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference> {
+        check_single_wordlength(var.length(), self.width)
+    }
+
+    fn default_field_formats(&self) -> Vec<(Vec<String>, String)> {
+        self.fields
+            .iter()
+            .filter_map(|field| Some((vec![field.name.clone()], field.translator.clone()?)))
+            .collect()
+    }
+}
+
+/// Check that a layout only refers to translators which can translate its fields
+pub fn check_field_translators(
+    translator: &BitFieldTranslator,
+    basic_translators: &[&String],
+) -> Result<()> {
+    for field in &translator.fields {
+        if let Some(name) = &field.translator {
+            if !basic_translators.contains(&name) {
+                return Err(anyhow!(
+                    "Field {} of {} uses the unknown translator {name}",
+                    field.name,
+                    translator.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CSR: &str = r#"
+        name = "CSR"
+        width = 8
+
+        [[fields]]
+        name = "mode"
+        bits = "7:6"
+        translator = "Unsigned"
+
+        [[fields]]
+        name = "enable"
+        bits = "0"
+    "#;
+
+    fn field<'a>(result: &'a TranslationResult, name: &str) -> &'a ValueRepr {
+        &result
+            .subfields
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap()
+            .1
+            .val
+    }
+
+    #[test]
+    fn fields_are_extracted_from_the_right_bits() {
+        let t = BitFieldTranslator::from_str(CSR).unwrap();
+        let result = t.translate_bits("10000001").unwrap();
+
+        assert!(matches!(result.val, ValueRepr::Struct));
+        assert!(matches!(field(&result, "mode"), ValueRepr::Bits(2, b) if b == "10"));
+        assert!(matches!(field(&result, "enable"), ValueRepr::Bit('1')));
+    }
+
+    #[test]
+    fn short_values_are_extended() {
+        let t = BitFieldTranslator::from_str(CSR).unwrap();
+        let result = t.translate_bits("1").unwrap();
+
+        assert!(matches!(field(&result, "mode"), ValueRepr::Bits(2, b) if b == "00"));
+        assert!(matches!(field(&result, "enable"), ValueRepr::Bit('1')));
+    }
+
+    #[test]
+    fn field_translators_are_default_formats() {
+        let t = BitFieldTranslator::from_str(CSR).unwrap();
+        assert_eq!(
+            t.default_field_formats(),
+            vec![(vec!["mode".to_string()], "Unsigned".to_string())]
+        );
+    }
+
+    #[test]
+    fn fields_outside_the_signal_are_rejected() {
+        let layout = "name = \"a\"\nwidth = 4\n[[fields]]\nname = \"b\"\nbits = \"4:2\"";
+        assert!(BitFieldTranslator::from_str(layout).is_err());
+    }
+}
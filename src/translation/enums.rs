@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre::anyhow;
+use color_eyre::Result;
+use num::BigUint;
+use waveform::{Hierarchy, SignalLength, SignalValue, Var};
+
+use super::{
+    map_vector_signal, BasicTranslator, HexTranslator, NumberParseResult, SignalInfo,
+    TranslationPreference, TranslationResult, Translator, ValueKind, ValueRepr,
+};
+
+/// Shows the literal names of signals with enum types, using the enum tables
+/// emitted by the simulator
+pub struct EnumTranslator {}
+
+/// Find the literal with the value `bits` in the enum table `literals`. Values are
+/// compared numerically if possible since simulators don't agree on whether leading
+/// zeros are included
+fn enum_literal<'a>(bits: &str, literals: &[(&str, &'a str)]) -> Option<&'a str> {
+    let numeric = BigUint::parse_bytes(bits.as_bytes(), 2);
+    literals
+        .iter()
+        .find(|(value, _)| match &numeric {
+            Some(n) => BigUint::parse_bytes(value.as_bytes(), 2).as_ref() == Some(n),
+            None => *value == bits,
+        })
+        .map(|(_, name)| *name)
+}
+
+impl Translator for EnumTranslator {
+    fn name(&self) -> String {
+        "Enum".to_string()
+    }
+
+    fn translate(
+        &self,
+        hierarchy: &Hierarchy,
+        var: &Var,
+        value: &SignalValue,
+    ) -> Result<TranslationResult> {
+        let (_, literals) = var
+            .enum_type(hierarchy)
+            .ok_or_else(|| anyhow!("{} does not have an enum type", var.full_name(hierarchy)))?;
+        let num_bits = match var.length() {
+            SignalLength::Variable => 0,
+            SignalLength::Fixed(len) => len.get(),
+        } as u64;
+
+        let bits = match value {
+            SignalValue::Binary(bytes) => format!(
+                "{:0width$b}",
+                BigUint::from_bytes_be(bytes),
+                width = num_bits as usize
+            ),
+            SignalValue::String(s) => s.to_string(),
+        };
+
+        let (val, color) = match enum_literal(&bits, &literals) {
+            Some(name) => (name.to_string(), ValueKind::Normal),
+            None => match map_vector_signal(&bits) {
+                NumberParseResult::Unparsable(name, kind) => (name, kind),
+                // A value which is not one of the literals is most likely a bug
+                NumberParseResult::Numerical(_) => (
+                    HexTranslator {}.basic_translate(num_bits, value).0,
+                    ValueKind::Warn,
+                ),
+            },
+        };
+
+        Ok(TranslationResult {
+            val: ValueRepr::String(val),
+            color,
+            subfields: vec![],
+            durations: HashMap::new(),
+        })
+    }
+
+    fn signal_info(&self, _hierarchy: &Hierarchy, _var: &Var) -> Result<SignalInfo> {
+        Ok(SignalInfo::Bits)
+    }
+
+    fn translates(&self, hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference> {
+        if var.enum_type(hierarchy).is_some() {
+            Ok(TranslationPreference::Prefer)
+        } else {
+            Ok(TranslationPreference::No)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STATES: [(&str, &str); 3] = [("00", "Idle"), ("01", "Busy"), ("10", "Done")];
+
+    #[test]
+    fn literals_are_found_by_value() {
+        assert_eq!(enum_literal("01", &STATES), Some("Busy"));
+        assert_eq!(enum_literal("10", &STATES), Some("Done"));
+        assert_eq!(enum_literal("11", &STATES), None);
+    }
+
+    #[test]
+    fn leading_zeros_are_ignored() {
+        assert_eq!(enum_literal("0010", &STATES), Some("Done"));
+        assert_eq!(enum_literal("1", &STATES), Some("Busy"));
+    }
+
+    #[test]
+    fn undefined_values_are_not_literals() {
+        assert_eq!(enum_literal("x1", &STATES), None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Translators which map values to names using tables defined by the user, for example
+//! to show the states of an FSM. The tables are read from TOML files in the form
+//!
+//! ```toml
+//! name = "FSM state"
+//! # Optional, only offer the translator for signals of this width
+//! width = 3
+//!
+//! [values]
+//! 0 = "Idle"
+//! 1 = "Fetch"
+//! 0b10 = { name = "Error", color = "ff0000" }
+//! ```
+//!
+//! Keys are decimal numbers, or hexadecimal/binary numbers prefixed by `0x`/`0b`.
+//! Values without a mapping are shown in hexadecimal. See [super::user] for where the
+//! files are loaded from.
+use std::collections::HashMap;
+
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use eframe::epaint::Color32;
+use num::{BigUint, Num};
+use serde::Deserialize;
+use waveform::{SignalValue, Var};
+
+use super::basic_translators::check_single_wordlength;
+use super::{
+    map_vector_signal, translates_all_bit_types, BasicTranslator, HexTranslator, NumberParseResult,
+    TranslationPreference, ValueKind,
+};
+use crate::config::hex_string_to_color32;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MappingValue {
+    Name(String),
+    Colored { name: String, color: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct MappingFile {
+    name: String,
+    #[serde(default)]
+    width: Option<u32>,
+    values: HashMap<String, MappingValue>,
+}
+
+pub struct MappingTranslator {
+    name: String,
+    width: Option<u32>,
+    values: HashMap<BigUint, (String, Option<Color32>)>,
+}
+
+fn parse_key(key: &str) -> Result<BigUint> {
+    let key = key.trim().replace('_', "");
+    let result = if let Some(hex) = key.strip_prefix("0x") {
+        BigUint::from_str_radix(hex, 16)
+    } else if let Some(bin) = key.strip_prefix("0b") {
+        BigUint::from_str_radix(bin, 2)
+    } else {
+        BigUint::from_str_radix(&key, 10)
+    };
+    result.with_context(|| format!("'{key}' is not a valid value"))
+}
+
+impl MappingTranslator {
+    pub fn from_str(content: &str) -> Result<Self> {
+        let file: MappingFile = toml::from_str(content)?;
+
+        let values = file
+            .values
+            .into_iter()
+            .map(|(key, value)| {
+                let mapped = match value {
+                    MappingValue::Name(name) => (name, None),
+                    MappingValue::Colored { name, color } => {
+                        (name, Some(hex_string_to_color32(color)?))
+                    }
+                };
+                Ok((parse_key(&key)?, mapped))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name: file.name,
+            width: file.width,
+            values,
+        })
+    }
+}
+
+impl BasicTranslator for MappingTranslator {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn basic_translate(&self, num_bits: u64, value: &SignalValue) -> (String, ValueKind) {
+        let numeric = match value {
+            SignalValue::Binary(bytes) => Some(BigUint::from_bytes_be(bytes)),
+            SignalValue::String(s) => match map_vector_signal(s) {
+                NumberParseResult::Numerical(v) => Some(v),
+                NumberParseResult::Unparsable(_, _) => None,
+            },
+        };
+
+        match numeric.and_then(|v| self.values.get(&v)) {
+            Some((name, Some(color))) => (name.clone(), ValueKind::Custom(*color)),
+            Some((name, None)) => (name.clone(), ValueKind::Normal),
+            // Unmapped and undefined values are shown like the hex translator does
+            None => HexTranslator {}.basic_translate(num_bits, value),
+        }
+    }
+
+    fn translates(&self, var: &Var) -> Result<TranslationPreference> {
+        match self.width {
+            Some(width) => check_single_wordlength(var.length(), width),
+            None => translates_all_bit_types(var),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FSM: &str = r#"
+        name = "FSM"
+        width = 2
+
+        [values]
+        0 = "Idle"
+        0x1 = "Busy"
+        0b10 = { name = "Error", color = "ff0000" }
+    "#;
+
+    #[test]
+    fn mapped_values_are_translated() {
+        let t = MappingTranslator::from_str(FSM).unwrap();
+        assert_eq!(
+            t.basic_translate(2, &SignalValue::String("00".to_string()))
+                .0,
+            "Idle"
+        );
+        assert_eq!(
+            t.basic_translate(2, &SignalValue::String("01".to_string()))
+                .0,
+            "Busy"
+        );
+        let (name, kind) = t.basic_translate(2, &SignalValue::String("10".to_string()));
+        assert_eq!(name, "Error");
+        assert!(kind == ValueKind::Custom(Color32::from_rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn unmapped_values_fall_back_to_hex() {
+        let t = MappingTranslator::from_str(FSM).unwrap();
+        let (name, kind) = t.basic_translate(2, &SignalValue::String("11".to_string()));
+        assert_eq!(name, "3");
+        assert!(kind == ValueKind::Normal);
+    }
+
+    #[test]
+    fn undefined_values_keep_their_kind() {
+        let t = MappingTranslator::from_str(FSM).unwrap();
+        let (_, kind) = t.basic_translate(2, &SignalValue::String("x1".to_string()));
+        assert!(kind == ValueKind::Undef);
+        let (_, kind) = t.basic_translate(2, &SignalValue::String("zz".to_string()));
+        assert!(kind == ValueKind::HighImp);
+    }
+
+    #[test]
+    fn invalid_keys_are_reported() {
+        assert!(MappingTranslator::from_str("name = \"a\"\n[values]\nfoo = \"b\"").is_err());
+    }
+}
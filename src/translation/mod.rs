@@ -5,9 +5,17 @@ use eframe::epaint::Color32;
 use waveform::{SignalValue, Var, Hierarchy, SignalLength};
 
 mod basic_translators;
+pub mod bitfield;
 pub mod clock;
+pub mod enums;
+pub mod mapping;
 pub mod numeric_translators;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod riscv;
 pub mod spade;
+pub mod user;
+pub mod wasm;
 
 pub use basic_translators::*;
 use itertools::Itertools;
@@ -28,6 +36,7 @@ pub fn all_translators() -> TranslatorList {
             Box::new(ASCIITranslator {}),
             Box::new(SinglePrecisionTranslator {}),
             Box::new(DoublePrecisionTranslator {}),
+            Box::new(QuadPrecisionTranslator {}),
             Box::new(HalfPrecisionTranslator {}),
             Box::new(BFloat16Translator {}),
             Box::new(Posit32Translator {}),
@@ -35,13 +44,20 @@ pub fn all_translators() -> TranslatorList {
             Box::new(Posit8Translator {}),
             Box::new(PositQuire8Translator {}),
             Box::new(PositQuire16Translator {}),
+            Box::new(PositQuire32Translator {}),
             Box::new(E5M2Translator {}),
             Box::new(E4M3Translator {}),
-            Box::new(RiscvTranslator {}),
+            Box::new(riscv::RiscvTranslator {
+                xlen: riscv::Xlen::Rv32,
+            }),
+            Box::new(riscv::RiscvTranslator {
+                xlen: riscv::Xlen::Rv64,
+            }),
             Box::new(LebTranslator {}),
         ],
         vec![
             Box::new(clock::ClockTranslator::new()),
+            Box::new(enums::EnumTranslator {}),
             Box::new(StringTranslator {}),
         ],
     )
@@ -97,6 +113,10 @@ impl TranslatorList {
         self.inner.insert(t.name(), t);
     }
 
+    pub fn add_basic(&mut self, t: Box<dyn BasicTranslator>) {
+        self.basic.insert(t.name(), t);
+    }
+
     pub fn is_valid_translator(&self, hierarchy: &Hierarchy, var: &Var, candidate: &str) -> bool {
         self.get_translator(candidate)
             .translates(hierarchy, var)
@@ -105,7 +125,7 @@ impl TranslatorList {
     }
 }
 
-#[derive(Clone, PartialEq, Copy)]
+#[derive(Clone, PartialEq, Copy, Debug)]
 pub enum ValueKind {
     Normal,
     Undef,
@@ -341,6 +361,44 @@ impl SignalInfo {
             },
         }
     }
+
+    /// Add the individual bits of a `num_bits` wide signal as one-bit fields named by
+    /// [bit_field_name], most significant bit first
+    pub fn with_bits(self, num_bits: u64) -> SignalInfo {
+        let bits = (0..num_bits)
+            .rev()
+            .map(|idx| (bit_field_name(idx), SignalInfo::Bool));
+        let subfields = match self {
+            SignalInfo::Compound { subfields } => subfields.into_iter().chain(bits).collect(),
+            _ => bits.collect(),
+        };
+        SignalInfo::Compound { subfields }
+    }
+}
+
+/// The name of the field of bit `idx` of a signal whose bits are expanded. The brackets
+/// keep it apart from the fields of translators
+pub fn bit_field_name(idx: u64) -> String {
+    format!("[{idx}]")
+}
+
+/// The values of the fields added by [SignalInfo::with_bits]
+pub fn bit_fields(
+    num_bits: u64,
+    value: &SignalValue,
+) -> Vec<(Vec<String>, Option<(String, ValueKind)>)> {
+    let bits = BinaryTranslator {}.basic_translate(num_bits, value).0;
+    let mut fields = bits
+        .chars()
+        .rev()
+        .enumerate()
+        .map(|(idx, bit)| {
+            let value = BitTranslator {}.basic_translate(1, &SignalValue::String(&bit.to_string()));
+            (vec![bit_field_name(idx as u64)], Some(value))
+        })
+        .collect::<Vec<_>>();
+    fields.reverse();
+    fields
 }
 
 #[derive(PartialEq)]
@@ -370,6 +428,12 @@ pub trait Translator {
     fn signal_info(&self, hierarchy: &Hierarchy, var: &Var) -> Result<SignalInfo>;
 
     fn translates(&self, hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference>;
+
+    /// Formats to use for sub-fields when this translator is selected, unless the
+    /// user has already chosen a format for them
+    fn default_field_formats(&self) -> Vec<(Vec<String>, String)> {
+        vec![]
+    }
 }
 
 pub trait BasicTranslator {
@@ -473,3 +537,79 @@ fn map_vector_signal(s: &str) -> NumberParseResult {
         NumberParseResult::Unparsable("UNKNOWN VALUES".to_string(), ValueKind::Undef)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn field_names(info: &SignalInfo) -> Vec<String> {
+        match info {
+            SignalInfo::Compound { subfields } => {
+                subfields.iter().map(|(name, _)| name.clone()).collect()
+            }
+            _ => panic!("Expected compound signal info"),
+        }
+    }
+
+    #[test]
+    fn bits_are_added_msb_first() {
+        let info = SignalInfo::Bits.with_bits(3);
+        assert_eq!(field_names(&info), vec!["[2]", "[1]", "[0]"]);
+        let SignalInfo::Compound { subfields } = info else {
+            unreachable!()
+        };
+        assert!(subfields
+            .iter()
+            .all(|(_, info)| matches!(info, SignalInfo::Bool)));
+    }
+
+    #[test]
+    fn bits_are_added_after_translator_fields() {
+        let info = SignalInfo::Compound {
+            subfields: vec![("valid".to_string(), SignalInfo::Bool)],
+        }
+        .with_bits(2);
+        assert_eq!(field_names(&info), vec!["valid", "[1]", "[0]"]);
+    }
+
+    #[test]
+    fn bit_fields_are_msb_first() {
+        let fields = bit_fields(3, &SignalValue::String("101"));
+        let expected = [("[2]", "1"), ("[1]", "0"), ("[0]", "1")]
+            .into_iter()
+            .map(|(name, bit)| {
+                (
+                    vec![name.to_string()],
+                    Some((bit.to_string(), ValueKind::Normal)),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn unknown_bits_keep_their_kind() {
+        let fields = bit_fields(4, &SignalValue::String("1x0z"));
+        let values = fields
+            .into_iter()
+            .map(|(_, value)| value.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                ("1".to_string(), ValueKind::Normal),
+                ("x".to_string(), ValueKind::Undef),
+                ("0".to_string(), ValueKind::Normal),
+                ("z".to_string(), ValueKind::HighImp),
+            ]
+        );
+    }
+
+    #[test]
+    fn short_values_are_extended() {
+        let fields = bit_fields(3, &SignalValue::String("1"));
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].0, vec!["[2]".to_string()]);
+        assert_eq!(fields[2].1, Some(("1".to_string(), ValueKind::Normal)));
+    }
+}
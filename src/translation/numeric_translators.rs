@@ -42,3 +42,95 @@ impl<T: NumericTranslator> BasicTranslator for T {
         self.translates(var)
     }
 }
+
+/// Fixed-point numbers with `frac_bits` fractional bits, in two's complement if `signed`.
+/// The translators are named after the Q notation, `Q.n` for signed and `UQ.n` for
+/// unsigned numbers, with the integer bits given by the width of the signal.
+pub struct FixedPointTranslator {
+    pub signed: bool,
+    pub frac_bits: u64,
+}
+
+impl FixedPointTranslator {
+    /// Parse the name of a fixed-point translator, i.e. `Q.n` or `UQ.n`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (signed, frac_bits) = if let Some(frac_bits) = name.strip_prefix("UQ.") {
+            (false, frac_bits)
+        } else {
+            (true, name.strip_prefix("Q.")?)
+        };
+        Some(Self {
+            signed,
+            frac_bits: frac_bits.parse().ok()?,
+        })
+    }
+}
+
+/// The exact decimal representation of `v / 2^frac_bits`
+pub(super) fn fixed_point_string(v: &BigUint, frac_bits: u64) -> String {
+    let integer = v >> frac_bits;
+    let fraction = v - (&integer << frac_bits);
+    if fraction == BigUint::from(0u32) {
+        return format!("{integer}");
+    }
+
+    // fraction / 2^n = fraction * 5^n / 10^n, so the digits of fraction * 5^n are the
+    // n decimals of the result
+    let digits = (fraction * BigUint::from(5u32).pow(frac_bits as u32)).to_string();
+    let decimals = format!("{digits:0>width$}", width = frac_bits as usize);
+    format!("{integer}.{}", decimals.trim_end_matches('0'))
+}
+
+impl NumericTranslator for FixedPointTranslator {
+    fn name(&self) -> String {
+        if self.signed {
+            format!("Q.{}", self.frac_bits)
+        } else {
+            format!("UQ.{}", self.frac_bits)
+        }
+    }
+
+    fn translate_biguint(&self, num_bits: u64, v: BigUint) -> String {
+        let signweight = BigUint::from(1u32) << num_bits.saturating_sub(1);
+        if self.signed && num_bits > 0 && v >= signweight {
+            let magnitude = (signweight << 1) - v;
+            format!("-{}", fixed_point_string(&magnitude, self.frac_bits))
+        } else {
+            fixed_point_string(&v, self.frac_bits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn translate(signed: bool, frac_bits: u64, num_bits: u64, v: u32) -> String {
+        FixedPointTranslator { signed, frac_bits }.translate_biguint(num_bits, BigUint::from(v))
+    }
+
+    #[test]
+    fn unsigned_fixed_point_is_exact() {
+        assert_eq!(translate(false, 4, 8, 0b0001_1000), "1.5");
+        assert_eq!(translate(false, 4, 8, 0b0000_0001), "0.0625");
+        assert_eq!(translate(false, 4, 8, 0b1111_0000), "15");
+        assert_eq!(translate(false, 0, 8, 200), "200");
+    }
+
+    #[test]
+    fn signed_fixed_point_is_twos_complement() {
+        assert_eq!(translate(true, 4, 8, 0b1111_1000), "-0.5");
+        assert_eq!(translate(true, 4, 8, 0b1000_0000), "-8");
+        assert_eq!(translate(true, 4, 8, 0b0111_1111), "7.9375");
+    }
+
+    #[test]
+    fn fixed_point_names_round_trip() {
+        for (signed, frac_bits) in [(true, 15), (false, 3)] {
+            let name = NumericTranslator::name(&FixedPointTranslator { signed, frac_bits });
+            let parsed = FixedPointTranslator::from_name(&name).unwrap();
+            assert_eq!((parsed.signed, parsed.frac_bits), (signed, frac_bits));
+        }
+        assert!(FixedPointTranslator::from_name("Unsigned").is_none());
+    }
+}
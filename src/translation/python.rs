@@ -0,0 +1,293 @@
+//! Translators written in Python. A translator is a Python file which defines a class
+//! named `Translator` with the methods
+//!
+//! - `name(self)`, returning the name shown in the format menus
+//! - `translates(self, name, width)`, returning `"prefer"`, `"yes"` or `"no"` for a
+//!   signal with the full name `name` and `width` bits
+//! - `signal_info(self, name, width)`, returning the structure of the translated values.
+//!   This is one of `"bits"`, `"bool"`, `"clock"`, `"string"` and `"real"`, or the
+//!   structure of the sub-fields as a dict from field names or as a list
+//! - `translate(self, name, width, value)`, where `value` is the value as a string of
+//!   binary digits which can include other values such as `x` and `z`
+//!
+//! `translate` returns a string, `None` if the value is not present, or a dict with one
+//! of the keys
+//!
+//! - `string`, the value as a string
+//! - `bits`, a string of binary digits which is shown in the format chosen for the field
+//! - `struct`, a dict from field names to the values of the fields
+//! - `tuple` and `array`, lists of the values of the fields
+//! - `enum`, the name of the selected variant, with the values of all variants as a dict
+//!   in `fields`
+//!
+//! and optionally `kind`, one of `normal`, `undef`, `highimp`, `warn`, `dontcare` and
+//! `weak`. The values of fields have the same form as the values returned by `translate`.
+use std::collections::HashMap;
+
+use camino::Utf8Path;
+use color_eyre::eyre::{anyhow, Context};
+use color_eyre::Result;
+use num::BigUint;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use waveform::{Hierarchy, SignalLength, SignalValue, Var};
+
+use super::{
+    SignalInfo, TranslationPreference, TranslationResult, Translator, ValueKind, ValueRepr,
+};
+
+pub struct PythonTranslator {
+    name: String,
+    instance: PyObject,
+}
+
+impl PythonTranslator {
+    pub fn new(path: &Utf8Path) -> Result<Self> {
+        let code = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read translator from {path}"))?;
+        let module_name = path.file_stem().unwrap_or("translator");
+        Python::with_gil(|py| -> PyResult<_> {
+            let module = PyModule::from_code(py, &code, path.as_str(), module_name)?;
+            let instance = module.getattr("Translator")?.call0()?;
+            let name = instance.call_method0("name")?.extract()?;
+            Ok(Self {
+                name,
+                instance: instance.into(),
+            })
+        })
+        .map_err(|e| anyhow!("Failed to load Python translator {path}: {e}"))
+    }
+}
+
+fn width(var: &Var) -> u64 {
+    match var.length() {
+        SignalLength::Variable => 0,
+        SignalLength::Fixed(len) => len.get() as u64,
+    }
+}
+
+fn value_bits(var: &Var, value: &SignalValue) -> String {
+    match value {
+        SignalValue::Binary(bytes) => format!(
+            "{:0width$b}",
+            BigUint::from_bytes_be(bytes),
+            width = width(var) as usize
+        ),
+        SignalValue::String(s) => s.to_string(),
+    }
+}
+
+fn value_kind(name: &str) -> PyResult<ValueKind> {
+    match name {
+        "normal" => Ok(ValueKind::Normal),
+        "undef" => Ok(ValueKind::Undef),
+        "highimp" => Ok(ValueKind::HighImp),
+        "warn" => Ok(ValueKind::Warn),
+        "dontcare" => Ok(ValueKind::DontCare),
+        "weak" => Ok(ValueKind::Weak),
+        _ => Err(PyValueError::new_err(format!("Unknown value kind {name}"))),
+    }
+}
+
+/// The fields of a compound value, named by their keys if `fields` is a dict and by
+/// their indices if it is a list
+fn compound_fields<'py, T>(
+    fields: &'py PyAny,
+    convert: impl Fn(&'py PyAny) -> PyResult<T>,
+) -> PyResult<Vec<(String, T)>> {
+    if let Ok(dict) = fields.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(name, field)| Ok((name.extract()?, convert(field)?)))
+            .collect()
+    } else {
+        fields
+            .downcast::<PyList>()?
+            .iter()
+            .enumerate()
+            .map(|(i, field)| Ok((i.to_string(), convert(field)?)))
+            .collect()
+    }
+}
+
+fn result_from_python(value: &PyAny) -> PyResult<TranslationResult> {
+    let result = |val, subfields, color| TranslationResult {
+        val,
+        subfields,
+        color,
+        durations: HashMap::new(),
+    };
+    if value.is_none() {
+        return Ok(result(ValueRepr::NotPresent, vec![], ValueKind::Normal));
+    }
+    if let Ok(s) = value.extract::<String>() {
+        return Ok(result(ValueRepr::String(s), vec![], ValueKind::Normal));
+    }
+
+    let dict = value.downcast::<PyDict>()?;
+    let kind = match dict.get_item("kind")? {
+        Some(kind) => value_kind(kind.extract()?)?,
+        None => ValueKind::Normal,
+    };
+    let (val, subfields) = if let Some(s) = dict.get_item("string")? {
+        (ValueRepr::String(s.extract()?), vec![])
+    } else if let Some(bits) = dict.get_item("bits")? {
+        let bits: String = bits.extract()?;
+        (ValueRepr::Bits(bits.len() as u64, bits), vec![])
+    } else if let Some(fields) = dict.get_item("struct")? {
+        (
+            ValueRepr::Struct,
+            compound_fields(fields, result_from_python)?,
+        )
+    } else if let Some(fields) = dict.get_item("tuple")? {
+        (
+            ValueRepr::Tuple,
+            compound_fields(fields, result_from_python)?,
+        )
+    } else if let Some(fields) = dict.get_item("array")? {
+        (
+            ValueRepr::Array,
+            compound_fields(fields, result_from_python)?,
+        )
+    } else if let Some(name) = dict.get_item("enum")? {
+        let name: String = name.extract()?;
+        let variants = dict
+            .get_item("fields")?
+            .ok_or_else(|| PyValueError::new_err("Enum values must have fields"))?;
+        let variants = compound_fields(variants, result_from_python)?;
+        let idx = variants
+            .iter()
+            .position(|(variant, _)| *variant == name)
+            .ok_or_else(|| PyValueError::new_err(format!("{name} is not an enum variant")))?;
+        (ValueRepr::Enum { idx, name }, variants)
+    } else {
+        return Err(PyValueError::new_err(
+            "Values must have one of the keys string, bits, struct, tuple, array or enum",
+        ));
+    };
+    Ok(result(val, subfields, kind))
+}
+
+fn info_from_python(info: &PyAny) -> PyResult<SignalInfo> {
+    match info.extract::<&str>() {
+        Ok("bits") => Ok(SignalInfo::Bits),
+        Ok("bool") => Ok(SignalInfo::Bool),
+        Ok("clock") => Ok(SignalInfo::Clock),
+        Ok("string") => Ok(SignalInfo::String),
+        Ok("real") => Ok(SignalInfo::Real),
+        Ok(other) => Err(PyValueError::new_err(format!(
+            "Unknown signal info {other}"
+        ))),
+        Err(_) => Ok(SignalInfo::Compound {
+            subfields: compound_fields(info, info_from_python)?,
+        }),
+    }
+}
+
+fn preference_from_python(preference: &PyAny) -> PyResult<TranslationPreference> {
+    match preference.extract::<&str>()? {
+        "prefer" => Ok(TranslationPreference::Prefer),
+        "yes" => Ok(TranslationPreference::Yes),
+        "no" => Ok(TranslationPreference::No),
+        other => Err(PyValueError::new_err(format!(
+            "Unknown translation preference {other}"
+        ))),
+    }
+}
+
+impl Translator for PythonTranslator {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn translate(
+        &self,
+        hierarchy: &Hierarchy,
+        var: &Var,
+        value: &SignalValue,
+    ) -> Result<TranslationResult> {
+        let args = (var.full_name(hierarchy), width(var), value_bits(var, value));
+        Python::with_gil(|py| {
+            let value = self.instance.call_method1(py, "translate", args)?;
+            result_from_python(value.as_ref(py))
+        })
+        .map_err(|e| anyhow!("{} failed to translate: {e}", self.name))
+    }
+
+    fn signal_info(&self, hierarchy: &Hierarchy, var: &Var) -> Result<SignalInfo> {
+        let args = (var.full_name(hierarchy), width(var));
+        Python::with_gil(|py| {
+            let info = self.instance.call_method1(py, "signal_info", args)?;
+            info_from_python(info.as_ref(py))
+        })
+        .map_err(|e| anyhow!("{} failed to get signal info: {e}", self.name))
+    }
+
+    fn translates(&self, hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference> {
+        let args = (var.full_name(hierarchy), width(var));
+        Python::with_gil(|py| {
+            let preference = self.instance.call_method1(py, "translates", args)?;
+            preference_from_python(preference.as_ref(py))
+        })
+        .map_err(|e| anyhow!("{} failed to check signal: {e}", self.name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn eval<T>(expr: &str, convert: impl Fn(&PyAny) -> PyResult<T>) -> PyResult<T> {
+        Python::with_gil(|py| convert(py.eval(expr, None, None)?))
+    }
+
+    #[test]
+    fn strings_are_plain_values() {
+        let result = eval("'idle'", result_from_python).unwrap();
+        assert!(matches!(&result.val, ValueRepr::String(s) if s == "idle"));
+        assert_eq!(result.color, ValueKind::Normal);
+    }
+
+    #[test]
+    fn structs_have_named_fields() {
+        let result = eval(
+            "{'struct': {'op': 'add', 'imm': {'bits': '0101'}}, 'kind': 'warn'}",
+            result_from_python,
+        )
+        .unwrap();
+        assert!(matches!(result.val, ValueRepr::Struct));
+        assert_eq!(result.color, ValueKind::Warn);
+        let names = result
+            .subfields
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["op", "imm"]);
+        assert!(matches!(&result.subfields[1].1.val, ValueRepr::Bits(4, bits) if bits == "0101"));
+    }
+
+    #[test]
+    fn enums_select_their_variant() {
+        let result = eval(
+            "{'enum': 'Some', 'fields': {'None': None, 'Some': '5'}}",
+            result_from_python,
+        )
+        .unwrap();
+        assert!(matches!(result.val, ValueRepr::Enum { idx: 1, .. }));
+        assert!(eval("{'enum': 'Other', 'fields': {}}", result_from_python).is_err());
+    }
+
+    #[test]
+    fn signal_info_can_be_compound() {
+        let info = eval(
+            "{'valid': 'bool', 'payload': ['bits', 'bits']}",
+            info_from_python,
+        );
+        let Ok(SignalInfo::Compound { subfields }) = info else {
+            panic!("Expected compound signal info")
+        };
+        assert_eq!(subfields[0].0, "valid");
+        assert!(subfields[1].1.has_subpath(&["1".to_string()]));
+        assert!(eval("'bytes'", info_from_python).is_err());
+    }
+}
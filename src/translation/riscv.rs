@@ -0,0 +1,722 @@
+//! Disassembly of RISC-V instructions. Covers the base integer instructions of RV32 and
+//! RV64 as well as the M, A, F, D, C, Zicsr, Zifencei, Zba, Zbb, Zbc and Zbs extensions.
+//! Registers are shown by their ABI names and CSRs by their names where known.
+//!
+//! Compressed instructions are recognized by their two least significant bits, so a
+//! 32-bit signal which holds a compressed instruction in its lower half is decoded too.
+use color_eyre::Result;
+use num::BigUint;
+use waveform::{SignalLength, SignalValue, Var};
+
+use super::{
+    map_vector_signal, BasicTranslator, NumberParseResult, TranslationPreference, ValueKind,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Xlen {
+    Rv32,
+    Rv64,
+}
+
+pub struct RiscvTranslator {
+    pub xlen: Xlen,
+}
+
+const REGS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+const FREGS: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+fn x(reg: u32) -> &'static str {
+    REGS[reg as usize]
+}
+
+fn f(reg: u32) -> &'static str {
+    FREGS[reg as usize]
+}
+
+/// Bits `hi` to `lo` of `insn`, inclusive
+fn bits(insn: u32, hi: u32, lo: u32) -> u32 {
+    (insn >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+fn sign_extend(value: u32, width: u32) -> i32 {
+    ((value << (32 - width)) as i32) >> (32 - width)
+}
+
+fn csr_name(csr: u32) -> String {
+    let name = match csr {
+        0x001 => "fflags",
+        0x002 => "frm",
+        0x003 => "fcsr",
+        0x100 => "sstatus",
+        0x104 => "sie",
+        0x105 => "stvec",
+        0x106 => "scounteren",
+        0x10a => "senvcfg",
+        0x140 => "sscratch",
+        0x141 => "sepc",
+        0x142 => "scause",
+        0x143 => "stval",
+        0x144 => "sip",
+        0x180 => "satp",
+        0x300 => "mstatus",
+        0x301 => "misa",
+        0x302 => "medeleg",
+        0x303 => "mideleg",
+        0x304 => "mie",
+        0x305 => "mtvec",
+        0x306 => "mcounteren",
+        0x30a => "menvcfg",
+        0x310 => "mstatush",
+        0x320 => "mcountinhibit",
+        0x340 => "mscratch",
+        0x341 => "mepc",
+        0x342 => "mcause",
+        0x343 => "mtval",
+        0x344 => "mip",
+        0x34a => "mtinst",
+        0x34b => "mtval2",
+        0x7a0 => "tselect",
+        0x7a1 => "tdata1",
+        0x7a2 => "tdata2",
+        0x7a3 => "tdata3",
+        0x7b0 => "dcsr",
+        0x7b1 => "dpc",
+        0x7b2 => "dscratch0",
+        0x7b3 => "dscratch1",
+        0xb00 => "mcycle",
+        0xb02 => "minstret",
+        0xb80 => "mcycleh",
+        0xb82 => "minstreth",
+        0xc00 => "cycle",
+        0xc01 => "time",
+        0xc02 => "instret",
+        0xc80 => "cycleh",
+        0xc81 => "timeh",
+        0xc82 => "instreth",
+        0xf11 => "mvendorid",
+        0xf12 => "marchid",
+        0xf13 => "mimpid",
+        0xf14 => "mhartid",
+        0xf15 => "mconfigptr",
+        0x323..=0x33f => return format!("mhpmevent{}", csr - 0x320),
+        0x3a0..=0x3af => return format!("pmpcfg{}", csr - 0x3a0),
+        0x3b0..=0x3ef => return format!("pmpaddr{}", csr - 0x3b0),
+        0xb03..=0xb1f => return format!("mhpmcounter{}", csr - 0xb00),
+        0xb83..=0xb9f => return format!("mhpmcounter{}h", csr - 0xb80),
+        0xc03..=0xc1f => return format!("hpmcounter{}", csr - 0xc00),
+        0xc83..=0xc9f => return format!("hpmcounter{}h", csr - 0xc80),
+        _ => return format!("{csr:#x}"),
+    };
+    name.to_string()
+}
+
+/// Append the rounding mode to a floating-point instruction unless it is dynamic
+fn with_rounding_mode(insn: String, rm: u32) -> Option<String> {
+    match rm {
+        0..=4 => Some(format!(
+            "{insn}, {}",
+            ["rne", "rtz", "rdn", "rup", "rmm"][rm as usize]
+        )),
+        7 => Some(insn),
+        _ => None,
+    }
+}
+
+fn fp_format(fmt: u32) -> Option<&'static str> {
+    match fmt {
+        0 => Some("s"),
+        1 => Some("d"),
+        _ => None,
+    }
+}
+
+/// The predecessor or successor set of a fence
+fn fence_set(set: u32) -> String {
+    "iorw"
+        .chars()
+        .enumerate()
+        .filter(|(i, _)| set & (8 >> i) != 0)
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Disassemble `insn`, which is a compressed instruction if its two least significant
+/// bits are not both set
+pub fn decode(insn: u32, xlen: Xlen) -> Option<String> {
+    if insn & 0b11 == 0b11 {
+        decode_full(insn, xlen)
+    } else {
+        decode_compressed(insn & 0xffff, xlen)
+    }
+}
+
+fn decode_full(insn: u32, xlen: Xlen) -> Option<String> {
+    let rv64 = xlen == Xlen::Rv64;
+    let opcode = bits(insn, 6, 0);
+    let rd = bits(insn, 11, 7);
+    let funct3 = bits(insn, 14, 12);
+    let rs1 = bits(insn, 19, 15);
+    let rs2 = bits(insn, 24, 20);
+    let funct7 = bits(insn, 31, 25);
+
+    let i_imm = sign_extend(insn >> 20, 12);
+    let s_imm = sign_extend(funct7 << 5 | rd, 12);
+    let b_imm = sign_extend(
+        bits(insn, 31, 31) << 12
+            | bits(insn, 7, 7) << 11
+            | bits(insn, 30, 25) << 5
+            | bits(insn, 11, 8) << 1,
+        13,
+    );
+    let j_imm = sign_extend(
+        bits(insn, 31, 31) << 20
+            | bits(insn, 19, 12) << 12
+            | bits(insn, 20, 20) << 11
+            | bits(insn, 30, 21) << 1,
+        21,
+    );
+
+    let r_type = |name: &str| Some(format!("{name} {}, {}, {}", x(rd), x(rs1), x(rs2)));
+    let unary = |name: &str| Some(format!("{name} {}, {}", x(rd), x(rs1)));
+
+    match opcode {
+        0x37 => Some(format!("lui {}, {:#x}", x(rd), insn >> 12)),
+        0x17 => Some(format!("auipc {}, {:#x}", x(rd), insn >> 12)),
+        0x6f => Some(format!("jal {}, {j_imm}", x(rd))),
+        0x67 if funct3 == 0 => Some(format!("jalr {}, {i_imm}({})", x(rd), x(rs1))),
+        0x63 => {
+            let name = match funct3 {
+                0 => "beq",
+                1 => "bne",
+                4 => "blt",
+                5 => "bge",
+                6 => "bltu",
+                7 => "bgeu",
+                _ => return None,
+            };
+            Some(format!("{name} {}, {}, {b_imm}", x(rs1), x(rs2)))
+        }
+        0x03 => {
+            let name = match (funct3, rv64) {
+                (0, _) => "lb",
+                (1, _) => "lh",
+                (2, _) => "lw",
+                (3, true) => "ld",
+                (4, _) => "lbu",
+                (5, _) => "lhu",
+                (6, true) => "lwu",
+                _ => return None,
+            };
+            Some(format!("{name} {}, {i_imm}({})", x(rd), x(rs1)))
+        }
+        0x23 => {
+            let name = match (funct3, rv64) {
+                (0, _) => "sb",
+                (1, _) => "sh",
+                (2, _) => "sw",
+                (3, true) => "sd",
+                _ => return None,
+            };
+            Some(format!("{name} {}, {s_imm}({})", x(rs2), x(rs1)))
+        }
+        0x13 => {
+            // On RV64 the shift amount has six bits, leaving six bits for the function
+            let (shamt, funct) = if rv64 {
+                (bits(insn, 25, 20), bits(insn, 31, 26) << 1)
+            } else {
+                (rs2, funct7)
+            };
+            let shift = |name: &str| Some(format!("{name} {}, {}, {shamt}", x(rd), x(rs1)));
+            match (funct3, insn >> 20) {
+                (1, 0x600) => unary("clz"),
+                (1, 0x601) => unary("ctz"),
+                (1, 0x602) => unary("cpop"),
+                (1, 0x604) => unary("sext.b"),
+                (1, 0x605) => unary("sext.h"),
+                (5, 0x287) => unary("orc.b"),
+                (5, 0x698) if !rv64 => unary("rev8"),
+                (5, 0x6b8) if rv64 => unary("rev8"),
+                (1, _) => match funct {
+                    0x00 => shift("slli"),
+                    0x14 => shift("bseti"),
+                    0x24 => shift("bclri"),
+                    0x34 => shift("binvi"),
+                    _ => None,
+                },
+                (5, _) => match funct {
+                    0x00 => shift("srli"),
+                    0x20 => shift("srai"),
+                    0x24 => shift("bexti"),
+                    0x30 => shift("rori"),
+                    _ => None,
+                },
+                _ => {
+                    let name = match funct3 {
+                        0 => "addi",
+                        2 => "slti",
+                        3 => "sltiu",
+                        4 => "xori",
+                        6 => "ori",
+                        _ => "andi",
+                    };
+                    Some(format!("{name} {}, {}, {i_imm}", x(rd), x(rs1)))
+                }
+            }
+        }
+        0x1b if rv64 => {
+            let shift =
+                |name: &str, shamt: u32| Some(format!("{name} {}, {}, {shamt}", x(rd), x(rs1)));
+            match (funct3, funct7) {
+                (0, _) => Some(format!("addiw {}, {}, {i_imm}", x(rd), x(rs1))),
+                (1, 0x30) => match rs2 {
+                    0 => unary("clzw"),
+                    1 => unary("ctzw"),
+                    2 => unary("cpopw"),
+                    _ => None,
+                },
+                (1, 0x00) => shift("slliw", rs2),
+                (1, 0x04 | 0x05) => shift("slli.uw", bits(insn, 25, 20)),
+                (5, 0x00) => shift("srliw", rs2),
+                (5, 0x20) => shift("sraiw", rs2),
+                (5, 0x30) => shift("roriw", rs2),
+                _ => None,
+            }
+        }
+        0x33 => match (funct7, funct3) {
+            (0x00, _) => {
+                r_type(["add", "sll", "slt", "sltu", "xor", "srl", "or", "and"][funct3 as usize])
+            }
+            (0x01, _) => r_type(
+                [
+                    "mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu",
+                ][funct3 as usize],
+            ),
+            (0x20, 0) => r_type("sub"),
+            (0x20, 4) => r_type("xnor"),
+            (0x20, 5) => r_type("sra"),
+            (0x20, 6) => r_type("orn"),
+            (0x20, 7) => r_type("andn"),
+            (0x04, 4) if !rv64 && rs2 == 0 => unary("zext.h"),
+            (0x05, 1) => r_type("clmul"),
+            (0x05, 2) => r_type("clmulr"),
+            (0x05, 3) => r_type("clmulh"),
+            (0x05, 4) => r_type("min"),
+            (0x05, 5) => r_type("minu"),
+            (0x05, 6) => r_type("max"),
+            (0x05, 7) => r_type("maxu"),
+            (0x10, 2) => r_type("sh1add"),
+            (0x10, 4) => r_type("sh2add"),
+            (0x10, 6) => r_type("sh3add"),
+            (0x14, 1) => r_type("bset"),
+            (0x24, 1) => r_type("bclr"),
+            (0x24, 5) => r_type("bext"),
+            (0x30, 1) => r_type("rol"),
+            (0x30, 5) => r_type("ror"),
+            (0x34, 1) => r_type("binv"),
+            _ => None,
+        },
+        0x3b if rv64 => match (funct7, funct3) {
+            (0x00, 0) => r_type("addw"),
+            (0x00, 1) => r_type("sllw"),
+            (0x00, 5) => r_type("srlw"),
+            (0x01, 0) => r_type("mulw"),
+            (0x01, 4) => r_type("divw"),
+            (0x01, 5) => r_type("divuw"),
+            (0x01, 6) => r_type("remw"),
+            (0x01, 7) => r_type("remuw"),
+            (0x04, 0) => r_type("add.uw"),
+            (0x04, 4) if rs2 == 0 => unary("zext.h"),
+            (0x10, 2) => r_type("sh1add.uw"),
+            (0x10, 4) => r_type("sh2add.uw"),
+            (0x10, 6) => r_type("sh3add.uw"),
+            (0x20, 0) => r_type("subw"),
+            (0x20, 5) => r_type("sraw"),
+            (0x30, 1) => r_type("rolw"),
+            (0x30, 5) => r_type("rorw"),
+            _ => None,
+        },
+        0x0f => match (funct3, insn) {
+            (0, 0x8330000f) => Some("fence.tso".to_string()),
+            (0, 0x0100000f) => Some("pause".to_string()),
+            (0, _) => Some(format!(
+                "fence {}, {}",
+                fence_set(bits(insn, 27, 24)),
+                fence_set(bits(insn, 23, 20))
+            )),
+            (1, _) => Some("fence.i".to_string()),
+            _ => None,
+        },
+        0x73 => {
+            let csr = csr_name(insn >> 20);
+            match funct3 {
+                0 => match insn {
+                    0x00000073 => Some("ecall".to_string()),
+                    0x00100073 => Some("ebreak".to_string()),
+                    0x10200073 => Some("sret".to_string()),
+                    0x30200073 => Some("mret".to_string()),
+                    0x7b200073 => Some("dret".to_string()),
+                    0x10500073 => Some("wfi".to_string()),
+                    _ if funct7 == 0x09 && rd == 0 => {
+                        Some(format!("sfence.vma {}, {}", x(rs1), x(rs2)))
+                    }
+                    _ => None,
+                },
+                1..=3 => {
+                    let name = ["csrrw", "csrrs", "csrrc"][funct3 as usize - 1];
+                    Some(format!("{name} {}, {csr}, {}", x(rd), x(rs1)))
+                }
+                5..=7 => {
+                    let name = ["csrrwi", "csrrsi", "csrrci"][funct3 as usize - 5];
+                    Some(format!("{name} {}, {csr}, {rs1}", x(rd)))
+                }
+                _ => None,
+            }
+        }
+        0x2f => {
+            let width = match (funct3, rv64) {
+                (2, _) => "w",
+                (3, true) => "d",
+                _ => return None,
+            };
+            let ordering = ["", ".rl", ".aq", ".aqrl"][bits(insn, 26, 25) as usize];
+            let name = match bits(insn, 31, 27) {
+                0x02 if rs2 == 0 => {
+                    return Some(format!("lr.{width}{ordering} {}, ({})", x(rd), x(rs1)))
+                }
+                0x03 => "sc",
+                0x01 => "amoswap",
+                0x00 => "amoadd",
+                0x04 => "amoxor",
+                0x0c => "amoand",
+                0x08 => "amoor",
+                0x10 => "amomin",
+                0x14 => "amomax",
+                0x18 => "amominu",
+                0x1c => "amomaxu",
+                _ => return None,
+            };
+            Some(format!(
+                "{name}.{width}{ordering} {}, {}, ({})",
+                x(rd),
+                x(rs2),
+                x(rs1)
+            ))
+        }
+        0x07 => {
+            let name = match funct3 {
+                2 => "flw",
+                3 => "fld",
+                _ => return None,
+            };
+            Some(format!("{name} {}, {i_imm}({})", f(rd), x(rs1)))
+        }
+        0x27 => {
+            let name = match funct3 {
+                2 => "fsw",
+                3 => "fsd",
+                _ => return None,
+            };
+            Some(format!("{name} {}, {s_imm}({})", f(rs2), x(rs1)))
+        }
+        0x43 | 0x47 | 0x4b | 0x4f => {
+            let name = match opcode {
+                0x43 => "fmadd",
+                0x47 => "fmsub",
+                0x4b => "fnmsub",
+                _ => "fnmadd",
+            };
+            let fmt = fp_format(bits(insn, 26, 25))?;
+            with_rounding_mode(
+                format!(
+                    "{name}.{fmt} {}, {}, {}, {}",
+                    f(rd),
+                    f(rs1),
+                    f(rs2),
+                    f(bits(insn, 31, 27))
+                ),
+                funct3,
+            )
+        }
+        0x53 => decode_fp(insn, xlen),
+        _ => None,
+    }
+}
+
+/// Decode the OP-FP major opcode of the F and D extensions
+fn decode_fp(insn: u32, xlen: Xlen) -> Option<String> {
+    let rv64 = xlen == Xlen::Rv64;
+    let rd = bits(insn, 11, 7);
+    let funct3 = bits(insn, 14, 12);
+    let rs1 = bits(insn, 19, 15);
+    let rs2 = bits(insn, 24, 20);
+    let fmt = fp_format(bits(insn, 26, 25))?;
+
+    let integer = |width: u32| match (width, rv64) {
+        (0, _) => Some("w"),
+        (1, _) => Some("wu"),
+        (2, true) => Some("l"),
+        (3, true) => Some("lu"),
+        _ => None,
+    };
+    // fmv moves the bits of a register, the name gives the integer width
+    let fmv_width = match (fmt, rv64) {
+        ("s", _) => Some("w"),
+        ("d", true) => Some("d"),
+        _ => None,
+    };
+    let binary = |name: &str| Some(format!("{name}.{fmt} {}, {}, {}", f(rd), f(rs1), f(rs2)));
+
+    match (bits(insn, 31, 27), funct3) {
+        (0x00..=0x03, _) => with_rounding_mode(
+            binary(["fadd", "fsub", "fmul", "fdiv"][bits(insn, 31, 27) as usize])?,
+            funct3,
+        ),
+        (0x0b, _) if rs2 == 0 => {
+            with_rounding_mode(format!("fsqrt.{fmt} {}, {}", f(rd), f(rs1)), funct3)
+        }
+        (0x04, 0) => binary("fsgnj"),
+        (0x04, 1) => binary("fsgnjn"),
+        (0x04, 2) => binary("fsgnjx"),
+        (0x05, 0) => binary("fmin"),
+        (0x05, 1) => binary("fmax"),
+        (0x08, _) => {
+            let from = fp_format(rs2).filter(|from| *from != fmt)?;
+            with_rounding_mode(format!("fcvt.{fmt}.{from} {}, {}", f(rd), f(rs1)), funct3)
+        }
+        (0x14, 0..=2) => Some(format!(
+            "{}.{fmt} {}, {}, {}",
+            ["fle", "flt", "feq"][funct3 as usize],
+            x(rd),
+            f(rs1),
+            f(rs2)
+        )),
+        (0x18, _) => with_rounding_mode(
+            format!("fcvt.{}.{fmt} {}, {}", integer(rs2)?, x(rd), f(rs1)),
+            funct3,
+        ),
+        (0x1a, _) => with_rounding_mode(
+            format!("fcvt.{fmt}.{} {}, {}", integer(rs2)?, f(rd), x(rs1)),
+            funct3,
+        ),
+        (0x1c, 0) if rs2 == 0 => Some(format!("fmv.x.{} {}, {}", fmv_width?, x(rd), f(rs1))),
+        (0x1c, 1) if rs2 == 0 => Some(format!("fclass.{fmt} {}, {}", x(rd), f(rs1))),
+        (0x1e, 0) if rs2 == 0 => Some(format!("fmv.{}.x {}, {}", fmv_width?, f(rd), x(rs1))),
+        _ => None,
+    }
+}
+
+fn decode_compressed(insn: u32, xlen: Xlen) -> Option<String> {
+    let rv64 = xlen == Xlen::Rv64;
+    let b = |hi, lo| bits(insn, hi, lo);
+    let funct3 = b(15, 13);
+    // Full register fields, and the three bit fields for x8-x15 of the CIW/CL/CS/CB
+    // formats
+    let rd = b(11, 7);
+    let rs2 = b(6, 2);
+    let rd_p = 8 + b(4, 2);
+    let rs1_p = 8 + b(9, 7);
+
+    let imm = sign_extend(b(12, 12) << 5 | b(6, 2), 6);
+    let shamt = b(12, 12) << 5 | b(6, 2);
+    // Offsets of loads and stores, which are scaled by the access size
+    let word_offset = b(12, 10) << 3 | b(6, 6) << 2 | b(5, 5) << 6;
+    let double_offset = b(12, 10) << 3 | b(6, 5) << 6;
+    let word_sp_offset = b(12, 12) << 5 | b(6, 4) << 2 | b(3, 2) << 6;
+    let double_sp_offset = b(12, 12) << 5 | b(6, 5) << 3 | b(4, 2) << 6;
+    let word_store_sp_offset = b(12, 9) << 2 | b(8, 7) << 6;
+    let double_store_sp_offset = b(12, 10) << 3 | b(9, 7) << 6;
+    let j_imm = sign_extend(
+        b(12, 12) << 11
+            | b(11, 11) << 4
+            | b(10, 9) << 8
+            | b(8, 8) << 10
+            | b(7, 7) << 6
+            | b(6, 6) << 7
+            | b(5, 3) << 1
+            | b(2, 2) << 5,
+        12,
+    );
+    let b_imm = sign_extend(
+        b(12, 12) << 8 | b(11, 10) << 3 | b(6, 5) << 6 | b(4, 3) << 1 | b(2, 2) << 5,
+        9,
+    );
+
+    match (b(1, 0), funct3) {
+        (0, 0) => {
+            let offset = b(12, 11) << 4 | b(10, 7) << 6 | b(6, 6) << 2 | b(5, 5) << 3;
+            (offset != 0).then(|| format!("c.addi4spn {}, sp, {offset}", x(rd_p)))
+        }
+        (0, 1) => Some(format!("c.fld {}, {double_offset}({})", f(rd_p), x(rs1_p))),
+        (0, 2) => Some(format!("c.lw {}, {word_offset}({})", x(rd_p), x(rs1_p))),
+        (0, 3) if rv64 => Some(format!("c.ld {}, {double_offset}({})", x(rd_p), x(rs1_p))),
+        (0, 3) => Some(format!("c.flw {}, {word_offset}({})", f(rd_p), x(rs1_p))),
+        (0, 5) => Some(format!("c.fsd {}, {double_offset}({})", f(rd_p), x(rs1_p))),
+        (0, 6) => Some(format!("c.sw {}, {word_offset}({})", x(rd_p), x(rs1_p))),
+        (0, 7) if rv64 => Some(format!("c.sd {}, {double_offset}({})", x(rd_p), x(rs1_p))),
+        (0, 7) => Some(format!("c.fsw {}, {word_offset}({})", f(rd_p), x(rs1_p))),
+        (1, 0) if rd == 0 => Some("c.nop".to_string()),
+        (1, 0) => Some(format!("c.addi {}, {imm}", x(rd))),
+        (1, 1) if rv64 => (rd != 0).then(|| format!("c.addiw {}, {imm}", x(rd))),
+        (1, 1) => Some(format!("c.jal {j_imm}")),
+        (1, 2) => Some(format!("c.li {}, {imm}", x(rd))),
+        (1, 3) if rd == 2 => {
+            let imm = sign_extend(
+                b(12, 12) << 9 | b(6, 6) << 4 | b(5, 5) << 6 | b(4, 3) << 7 | b(2, 2) << 5,
+                10,
+            );
+            (imm != 0).then(|| format!("c.addi16sp sp, {imm}"))
+        }
+        (1, 3) => (imm != 0).then(|| format!("c.lui {}, {:#x}", x(rd), imm as u32 & 0xfffff)),
+        (1, 4) => match b(11, 10) {
+            0 if rv64 || shamt < 32 => Some(format!("c.srli {}, {shamt}", x(rs1_p))),
+            1 if rv64 || shamt < 32 => Some(format!("c.srai {}, {shamt}", x(rs1_p))),
+            2 => Some(format!("c.andi {}, {imm}", x(rs1_p))),
+            3 => {
+                let name = match (b(12, 12), b(6, 5)) {
+                    (0, 0) => "c.sub",
+                    (0, 1) => "c.xor",
+                    (0, 2) => "c.or",
+                    (0, 3) => "c.and",
+                    (1, 0) if rv64 => "c.subw",
+                    (1, 1) if rv64 => "c.addw",
+                    _ => return None,
+                };
+                Some(format!("{name} {}, {}", x(rs1_p), x(rd_p)))
+            }
+            _ => None,
+        },
+        (1, 5) => Some(format!("c.j {j_imm}")),
+        (1, 6) => Some(format!("c.beqz {}, {b_imm}", x(rs1_p))),
+        (1, 7) => Some(format!("c.bnez {}, {b_imm}", x(rs1_p))),
+        (2, 0) if rv64 || shamt < 32 => Some(format!("c.slli {}, {shamt}", x(rd))),
+        (2, 1) => Some(format!("c.fldsp {}, {double_sp_offset}(sp)", f(rd))),
+        (2, 2) if rd != 0 => Some(format!("c.lwsp {}, {word_sp_offset}(sp)", x(rd))),
+        (2, 3) if rv64 && rd != 0 => Some(format!("c.ldsp {}, {double_sp_offset}(sp)", x(rd))),
+        (2, 3) if !rv64 => Some(format!("c.flwsp {}, {word_sp_offset}(sp)", f(rd))),
+        (2, 4) => match (b(12, 12), rd, rs2) {
+            (0, 0, 0) => None,
+            (0, _, 0) => Some(format!("c.jr {}", x(rd))),
+            (0, _, _) => Some(format!("c.mv {}, {}", x(rd), x(rs2))),
+            (_, 0, 0) => Some("c.ebreak".to_string()),
+            (_, _, 0) => Some(format!("c.jalr {}", x(rd))),
+            _ => Some(format!("c.add {}, {}", x(rd), x(rs2))),
+        },
+        (2, 5) => Some(format!("c.fsdsp {}, {double_store_sp_offset}(sp)", f(rs2))),
+        (2, 6) => Some(format!("c.swsp {}, {word_store_sp_offset}(sp)", x(rs2))),
+        (2, 7) if rv64 => Some(format!("c.sdsp {}, {double_store_sp_offset}(sp)", x(rs2))),
+        (2, 7) => Some(format!("c.fswsp {}, {word_store_sp_offset}(sp)", f(rs2))),
+        _ => None,
+    }
+}
+
+impl BasicTranslator for RiscvTranslator {
+    fn name(&self) -> String {
+        match self.xlen {
+            Xlen::Rv32 => "RISC-V: RV32".to_string(),
+            Xlen::Rv64 => "RISC-V: RV64".to_string(),
+        }
+    }
+
+    fn basic_translate(&self, num_bits: u64, value: &SignalValue) -> (String, ValueKind) {
+        let insn = match value {
+            SignalValue::Binary(bytes) => BigUint::from_bytes_be(bytes),
+            SignalValue::String(s) => match map_vector_signal(s) {
+                NumberParseResult::Numerical(v) => v,
+                NumberParseResult::Unparsable(v, kind) => return (v, kind),
+            },
+        };
+        let insn = insn.iter_u32_digits().next().unwrap_or(0);
+
+        // A 16-bit signal can only hold compressed instructions
+        let decoded = if num_bits == 16 {
+            decode_compressed(insn, self.xlen)
+        } else {
+            decode(insn, self.xlen)
+        };
+        match decoded {
+            Some(insn) => (insn, ValueKind::Normal),
+            None => ("UNKNOWN INSN".to_string(), ValueKind::Warn),
+        }
+    }
+
+    fn translates(&self, var: &Var) -> Result<TranslationPreference> {
+        match var.length() {
+            SignalLength::Fixed(bits) if bits.get() == 16 || bits.get() == 32 => {
+                Ok(TranslationPreference::Yes)
+            }
+            _ => Ok(TranslationPreference::No),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rv32(insn: u32) -> Option<String> {
+        decode(insn, Xlen::Rv32)
+    }
+
+    fn rv64(insn: u32) -> Option<String> {
+        decode(insn, Xlen::Rv64)
+    }
+
+    #[test]
+    fn base_instructions_use_abi_names() {
+        assert_eq!(rv32(0x01010513).unwrap(), "addi a0, sp, 16");
+        assert_eq!(rv32(0xffdff0ef).unwrap(), "jal ra, -4");
+        assert_eq!(rv32(0x00008067).unwrap(), "jalr zero, 0(ra)");
+        assert_eq!(rv32(0xfeb50ee3).unwrap(), "beq a0, a1, -4");
+        assert_eq!(rv32(0x000122b7).unwrap(), "lui t0, 0x12");
+    }
+
+    #[test]
+    fn rv64_only_instructions() {
+        assert_eq!(rv64(0x00813083).unwrap(), "ld ra, 8(sp)");
+        assert_eq!(rv64(0x0015051b).unwrap(), "addiw a0, a0, 1");
+        assert_eq!(rv64(0x02051513).unwrap(), "slli a0, a0, 32");
+        assert_eq!(rv32(0x00813083), None);
+        assert_eq!(rv32(0x0015051b), None);
+        assert_eq!(rv32(0x02051513), None);
+    }
+
+    #[test]
+    fn extension_instructions() {
+        assert_eq!(rv32(0x02b50533).unwrap(), "mul a0, a0, a1");
+        assert_eq!(rv32(0x06b6252f).unwrap(), "amoadd.w.aqrl a0, a1, (a2)");
+        assert_eq!(rv64(0x02b57553).unwrap(), "fadd.d fa0, fa0, fa1");
+        assert_eq!(rv64(0x02b50553).unwrap(), "fadd.d fa0, fa0, fa1, rne");
+        assert_eq!(rv32(0x20b52533).unwrap(), "sh1add a0, a0, a1");
+        assert_eq!(rv32(0x60051513).unwrap(), "clz a0, a0");
+    }
+
+    #[test]
+    fn csrs_are_named() {
+        assert_eq!(rv32(0x30002573).unwrap(), "csrrs a0, mstatus, zero");
+        assert_eq!(rv32(0x3b002573).unwrap(), "csrrs a0, pmpaddr0, zero");
+        assert_eq!(rv32(0x7c002573).unwrap(), "csrrs a0, 0x7c0, zero");
+    }
+
+    #[test]
+    fn compressed_instructions() {
+        assert_eq!(rv32(0x1141).unwrap(), "c.addi sp, -16");
+        assert_eq!(rv32(0x8082).unwrap(), "c.jr ra");
+        assert_eq!(rv64(0xe406).unwrap(), "c.sdsp ra, 8(sp)");
+        assert_eq!(rv32(0xe406).unwrap(), "c.fswsp ft1, 8(sp)");
+        assert_eq!(rv32(0x0000), None);
+    }
+
+    #[test]
+    fn compressed_instructions_in_the_lower_half_are_decoded() {
+        assert_eq!(rv32(0xdead8082).unwrap(), "c.jr ra");
+    }
+}
@@ -2,10 +2,10 @@ use std::collections::HashMap;
 
 use camino::Utf8Path;
 use eframe::epaint::Color32;
-use waveform::{Hierarchy, SignalValue, Var};
-use num::ToPrimitive;
+use num::{BigUint, ToPrimitive};
 use serde::Deserialize;
 use spade::compiler_state::CompilerState;
+use waveform::{Hierarchy, SignalValue, Var};
 
 use color_eyre::{
     eyre::{anyhow, bail, Context, ContextCompat},
@@ -50,6 +50,43 @@ impl SpadeTranslator {
 
         Ok(Self { state, top })
     }
+
+    /// The type of `var` in the spade code
+    fn type_of(&self, hierarchy: &Hierarchy, var: &Var) -> Result<ConcreteType> {
+        let path = path_in_top(&var.full_name(hierarchy));
+        self.state.type_of_hierarchical_value(&self.top, &path)
+    }
+}
+
+/// The path of a signal inside the top unit. The top level scope is the testbench,
+/// so it is not part of the path
+fn path_in_top(full_name: &str) -> Vec<String> {
+    full_name
+        .split('.')
+        .skip(1)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// The bits of `value` as a string of `size` bits. Values which are shorter than the
+/// type are extended like VCD values, with zeros unless the first bit is x or z
+fn value_bits(value: &SignalValue, size: usize) -> Result<String> {
+    let raw = match value {
+        SignalValue::Binary(bytes) => format!("{:0size$b}", BigUint::from_bytes_be(bytes)),
+        SignalValue::String(v) => v.to_string(),
+    };
+    let extra_bits = if size > raw.len() {
+        let extra_value = match raw.chars().next() {
+            Some('0') | Some('1') => "0",
+            Some('x') => "x",
+            Some('z') => "z",
+            other => bail!("Found non-bit value in vcd ({other:?})"),
+        };
+        extra_value.repeat(size - raw.len())
+    } else {
+        String::new()
+    };
+    Ok(format!("{extra_bits}{raw}"))
 }
 
 impl Translator for SpadeTranslator {
@@ -57,60 +94,49 @@ impl Translator for SpadeTranslator {
         "spade".to_string()
     }
 
-    fn translate(&self, var: &Var, value: &SignalValue, hierarchy: &Hierarchy) -> Result<TranslationResult> {
-        let ty = self
-            .state
-            .type_of_hierarchical_value(&self.top, var.full_name(hierarchy))?;
+    fn translate(
+        &self,
+        hierarchy: &Hierarchy,
+        var: &Var,
+        value: &SignalValue,
+    ) -> Result<TranslationResult> {
+        let ty = self.type_of(hierarchy, var)?;
 
-        let val_vcd_raw = match value {
-            SignalValue::BigUint(v) => format!("{v:b}"),
-            SignalValue::String(v) => v.clone(),
-        };
         let mir_ty = ty.to_mir_type();
         let ty_size = mir_ty
             .size()
             .to_usize()
             .context("Type size does not fit in usize")?;
-        let extra_bits = if ty_size > val_vcd_raw.len() {
-            let extra_count = ty_size - val_vcd_raw.len();
-            let extra_value = match val_vcd_raw.chars().next() {
-                Some('0') => "0",
-                Some('1') => "0",
-                Some('x') => "x",
-                Some('z') => "z",
-                other => bail!("Found non-bit value in vcd ({other:?})"),
-            };
-            extra_value.repeat(extra_count)
-        } else {
-            String::new()
-        };
-        let val_vcd = format!("{extra_bits}{val_vcd_raw}",);
+        let val_vcd = value_bits(value, ty_size)?;
         translate_concrete(&val_vcd, &ty, &mut false)
     }
 
     fn signal_info(&self, hierarchy: &Hierarchy, var: &Var) -> Result<SignalInfo> {
-        todo!()
-        // let ty = self
-        //     .state
-        //     .type_of_hierarchical_value(&self.top, &signal.sig.full_path()[1..])?;
-        //
-        // info_from_concrete(&ty)
+        let ty = self.type_of(hierarchy, var)?;
+
+        info_from_concrete(&ty)
     }
 
     fn translates(&self, hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference> {
-        todo!()
-        // let ty = self
-        //     .state
-        //     .type_of_hierarchical_value(&self.top, &signal.sig.full_path()[1..])?;
-        //
-        // match ty {
-        //     ConcreteType::Single {
-        //         base: PrimitiveType::Clock,
-        //         params: _,
-        //     } => Ok(TranslationPreference::Prefer),
-        //     ConcreteType::Single { base: _, params: _ } => Ok(TranslationPreference::No),
-        //     _ => Ok(TranslationPreference::Prefer),
-        // }
+        let ty = self.type_of(hierarchy, var)?;
+
+        Ok(preference_for_concrete(&ty))
+    }
+}
+
+/// Compound types and clocks are best shown by the spade translator, other single
+/// values are better handled by the basic translators
+fn preference_for_concrete(ty: &ConcreteType) -> TranslationPreference {
+    match ty {
+        ConcreteType::Single {
+            base: PrimitiveType::Clock,
+            params: _,
+        } => TranslationPreference::Prefer,
+        ConcreteType::Single { base: _, params: _ } | ConcreteType::Integer(_) => {
+            TranslationPreference::No
+        }
+        ConcreteType::Wire(inner) => preference_for_concrete(inner),
+        _ => TranslationPreference::Prefer,
     }
 }
 
@@ -284,7 +310,7 @@ fn translate_concrete(
                 let tag = usize::from_str_radix(tag_section, 2)
                     .with_context(|| format!("Unexpected characters in enum tag {tag_section}"))?;
 
-                if tag > options.len() {
+                if tag >= options.len() {
                     *problematic = true;
                     TranslationResult {
                         val: ValueRepr::String(format!("?TAG(0b{tag_section})")),
@@ -440,3 +466,310 @@ fn info_from_concrete(ty: &ConcreteType) -> Result<SignalInfo> {
     };
     Ok(result)
 }
+
+#[cfg(test)]
+mod test {
+    use spade_common::num_ext::InfallibleToBigInt;
+    use waveform::Waveform;
+
+    use super::*;
+
+    /// Compiler state of examples/spade, written by `swim build` in that directory
+    const STATE_FILE: &str = "examples/spade/build/state.ron";
+    const TOP: &str = "example::main::counter";
+
+    fn bool_ty() -> ConcreteType {
+        ConcreteType::Single {
+            base: PrimitiveType::Bool,
+            params: vec![],
+        }
+    }
+
+    fn clock_ty() -> ConcreteType {
+        ConcreteType::Single {
+            base: PrimitiveType::Clock,
+            params: vec![],
+        }
+    }
+
+    fn uint_ty(size: u32) -> ConcreteType {
+        ConcreteType::Single {
+            base: PrimitiveType::Uint,
+            params: vec![ConcreteType::Integer(size.to_bigint())],
+        }
+    }
+
+    fn name_id(id: u64, path: &[&str]) -> NameID {
+        NameID(id, Path::from_strs(path))
+    }
+
+    fn option_ty(inner: ConcreteType) -> ConcreteType {
+        ConcreteType::Enum {
+            options: vec![
+                (name_id(1, &["Option", "None"]), vec![]),
+                (
+                    name_id(2, &["Option", "Some"]),
+                    vec![(Identifier("val".to_string()), inner)],
+                ),
+            ],
+        }
+    }
+
+    fn subfield<'a>(result: &'a TranslationResult, name: &str) -> &'a TranslationResult {
+        &result
+            .subfields
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("No subfield {name}"))
+            .1
+    }
+
+    fn bits(result: &TranslationResult) -> String {
+        match &result.val {
+            ValueRepr::Bits(_, bits) => bits.clone(),
+            ValueRepr::Bit(bit) => bit.to_string(),
+            _ => panic!("Expected a bit value"),
+        }
+    }
+
+    #[test]
+    fn struct_fields_are_expanded_in_order() {
+        let ty = ConcreteType::Struct {
+            name: name_id(0, &["S"]),
+            members: vec![
+                (Identifier("a".to_string()), uint_ty(4)),
+                (Identifier("b".to_string()), bool_ty()),
+            ],
+        };
+
+        let result = translate_concrete("10110", &ty, &mut false).unwrap();
+        assert!(matches!(result.val, ValueRepr::Tuple));
+        assert_eq!(bits(subfield(&result, "a")), "1011");
+        assert_eq!(bits(subfield(&result, "b")), "0");
+    }
+
+    #[test]
+    fn array_elements_are_expanded() {
+        let ty = ConcreteType::Array {
+            inner: Box::new(uint_ty(2)),
+            size: 3.to_bigint(),
+        };
+
+        let result = translate_concrete("011011", &ty, &mut false).unwrap();
+        assert!(matches!(result.val, ValueRepr::Array));
+        assert_eq!(bits(subfield(&result, "0")), "01");
+        assert_eq!(bits(subfield(&result, "1")), "10");
+        assert_eq!(bits(subfield(&result, "2")), "11");
+    }
+
+    #[test]
+    fn enum_shows_the_active_option() {
+        let ty = option_ty(uint_ty(4));
+
+        let result = translate_concrete("10101", &ty, &mut false).unwrap();
+        assert!(
+            matches!(&result.val, ValueRepr::Enum { idx: 1, name } if name == "Some"),
+            "Expected Some"
+        );
+        assert!(matches!(
+            subfield(&result, "None").val,
+            ValueRepr::NotPresent
+        ));
+        let some = subfield(&result, "Some");
+        assert!(matches!(some.val, ValueRepr::Struct));
+        assert_eq!(bits(subfield(some, "val")), "0101");
+    }
+
+    #[test]
+    fn enum_with_undefined_tag_is_marked() {
+        let ty = option_ty(uint_ty(4));
+
+        let mut problematic = false;
+        let result = translate_concrete("x0101", &ty, &mut problematic).unwrap();
+        assert!(problematic);
+        assert!(result.color == ValueKind::Undef);
+        assert!(matches!(
+            subfield(subfield(&result, "Some"), "val").val,
+            ValueRepr::NotPresent
+        ));
+    }
+
+    #[test]
+    fn signal_info_matches_type_structure() {
+        let ty = ConcreteType::Tuple(vec![clock_ty(), bool_ty(), option_ty(uint_ty(4))]);
+
+        let info = info_from_concrete(&ty).unwrap();
+        assert!(matches!(
+            info.get_subinfo(&["0".to_string()]),
+            SignalInfo::Clock
+        ));
+        assert!(matches!(
+            info.get_subinfo(&["1".to_string()]),
+            SignalInfo::Bool
+        ));
+        assert!(matches!(
+            info.get_subinfo(&["2".to_string(), "Some".to_string(), "val".to_string()]),
+            SignalInfo::Bits
+        ));
+    }
+
+    #[test]
+    fn testbench_scope_is_stripped() {
+        assert_eq!(path_in_top("tb.x"), vec!["x".to_string()]);
+        assert_eq!(
+            path_in_top("tb.sub_0.x"),
+            vec!["sub_0".to_string(), "x".to_string()]
+        );
+        assert!(path_in_top("tb").is_empty());
+    }
+
+    #[test]
+    fn binary_values_are_decoded() {
+        assert_eq!(
+            value_bits(&SignalValue::Binary(vec![0b101]), 5).unwrap(),
+            "00101"
+        );
+        assert_eq!(
+            value_bits(&SignalValue::Binary(vec![0x01, 0x80]), 12).unwrap(),
+            "000110000000"
+        );
+        assert_eq!(
+            value_bits(&SignalValue::String("x1".to_string()), 4).unwrap(),
+            "xxx1"
+        );
+        assert_eq!(
+            value_bits(&SignalValue::String("10".to_string()), 4).unwrap(),
+            "0010"
+        );
+        assert_eq!(
+            value_bits(&SignalValue::String("0110".to_string()), 4).unwrap(),
+            "0110"
+        );
+        assert!(value_bits(&SignalValue::String("".to_string()), 4).is_err());
+
+        // Decoded values are split into fields like string values
+        let ty = option_ty(uint_ty(4));
+        let value = value_bits(&SignalValue::Binary(vec![0b10101]), 5).unwrap();
+        let result = translate_concrete(&value, &ty, &mut false).unwrap();
+        assert_eq!(bits(subfield(subfield(&result, "Some"), "val")), "0101");
+    }
+
+    #[test]
+    fn clocks_and_compound_types_are_preferred() {
+        assert!(preference_for_concrete(&clock_ty()) == TranslationPreference::Prefer);
+        assert!(preference_for_concrete(&option_ty(bool_ty())) == TranslationPreference::Prefer);
+        assert!(preference_for_concrete(&uint_ty(8)) == TranslationPreference::No);
+        assert!(preference_for_concrete(&bool_ty()) == TranslationPreference::No);
+    }
+
+    fn load_example() -> (SpadeTranslator, Waveform) {
+        let translator = SpadeTranslator::new(TOP, Utf8Path::new(STATE_FILE))
+            .unwrap_or_else(|e| panic!("{e:#?}"));
+        let waves = waveform::vcd::read("examples/spade/counter.vcd").unwrap();
+        (translator, waves)
+    }
+
+    fn var<'a>(waves: &'a Waveform, name: &str) -> &'a Var {
+        let hierarchy = waves.hierarchy();
+        hierarchy
+            .iter_vars()
+            .find(|var| var.full_name(hierarchy) == name)
+            .unwrap_or_else(|| panic!("No signal {name}"))
+    }
+
+    #[test]
+    fn missing_top_units_are_reported() {
+        let Err(e) = SpadeTranslator::new("example::main::missing", Utf8Path::new(STATE_FILE))
+        else {
+            panic!("Found a unit which does not exist")
+        };
+        assert!(format!("{e}").contains("Did not find a unit example::main::missing"));
+    }
+
+    #[test]
+    fn signals_are_looked_up_in_the_top_unit() {
+        let (translator, waves) = load_example();
+        let hierarchy = waves.hierarchy();
+
+        let preference = |name| translator.translates(hierarchy, var(&waves, name)).unwrap();
+        assert!(preference("counter.clk") == TranslationPreference::Prefer);
+        assert!(preference("counter.pair") == TranslationPreference::Prefer);
+        assert!(preference("counter.mode") == TranslationPreference::Prefer);
+        assert!(preference("counter.count") == TranslationPreference::No);
+    }
+
+    #[test]
+    fn signal_info_follows_the_spade_types() {
+        let (translator, waves) = load_example();
+        let hierarchy = waves.hierarchy();
+
+        let pair = translator
+            .signal_info(hierarchy, var(&waves, "counter.pair"))
+            .unwrap();
+        assert!(matches!(
+            pair.get_subinfo(&["a".to_string()]),
+            SignalInfo::Bits
+        ));
+        assert!(matches!(
+            pair.get_subinfo(&["b".to_string()]),
+            SignalInfo::Bool
+        ));
+
+        let mode = translator
+            .signal_info(hierarchy, var(&waves, "counter.mode"))
+            .unwrap();
+        assert!(matches!(
+            mode.get_subinfo(&["Counting".to_string(), "count".to_string()]),
+            SignalInfo::Bits
+        ));
+        assert!(matches!(
+            translator
+                .signal_info(hierarchy, var(&waves, "counter.clk"))
+                .unwrap(),
+            SignalInfo::Clock
+        ));
+    }
+
+    #[test]
+    fn values_are_translated_with_the_spade_types() {
+        let (translator, waves) = load_example();
+        let hierarchy = waves.hierarchy();
+
+        // The values at #50 in the VCD
+        let pair = translator
+            .translate(
+                hierarchy,
+                var(&waves, "counter.pair"),
+                &SignalValue::Binary(vec![0b00100]),
+            )
+            .unwrap();
+        assert_eq!(bits(subfield(&pair, "a")), "0010");
+        assert_eq!(bits(subfield(&pair, "b")), "0");
+
+        let mode = translator
+            .translate(
+                hierarchy,
+                var(&waves, "counter.mode"),
+                &SignalValue::String("10010".to_string()),
+            )
+            .unwrap();
+        assert!(
+            matches!(&mode.val, ValueRepr::Enum { idx: 1, name } if name == "Counting"),
+            "Expected Counting"
+        );
+        assert_eq!(bits(subfield(subfield(&mode, "Counting"), "count")), "0010");
+
+        // The value while in reset is the first option
+        let mode = translator
+            .translate(
+                hierarchy,
+                var(&waves, "counter.mode"),
+                &SignalValue::String("00000".to_string()),
+            )
+            .unwrap();
+        assert!(
+            matches!(&mode.val, ValueRepr::Enum { idx: 0, name } if name == "Idle"),
+            "Expected Idle"
+        );
+    }
+}
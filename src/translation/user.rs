@@ -0,0 +1,171 @@
+//! Loading of translators defined by the user in TOML files. Files with a `fields`
+//! table are [bitfield] layouts, other files are [mapping] tables. When built with the
+//! `python` feature, `.py` files are loaded as Python translators, see the `python`
+//! module. `.wasm` files are loaded as WebAssembly translators, see the `wasm` module.
+//! At startup, all translator files in the `translators` directories next to the config
+//! files are loaded.
+use camino::{Utf8Path, Utf8PathBuf};
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use eframe::egui::DroppedFile;
+use futures_util::FutureExt;
+use futures_util::TryFutureExt;
+use log::{info, warn};
+
+use super::bitfield::{check_field_translators, BitFieldTranslator};
+use super::mapping::MappingTranslator;
+use super::wasm::{WasmTranslator, WASM_MAGIC};
+use super::{BasicTranslator, Translator, TranslatorList};
+use crate::message::Message;
+use crate::State;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn translator_dirs() -> Vec<Utf8PathBuf> {
+    let mut dirs = vec![];
+    if let Some(proj_dirs) = directories::ProjectDirs::from("org", "surfer-project", "surfer") {
+        if let Ok(dir) = Utf8PathBuf::try_from(proj_dirs.config_dir().join("translators")) {
+            dirs.push(dir)
+        }
+    }
+    dirs.push(Utf8PathBuf::from("translators"));
+    dirs
+}
+
+#[cfg(target_arch = "wasm32")]
+fn translator_dirs() -> Vec<Utf8PathBuf> {
+    vec![]
+}
+
+/// Load the translator defined in `path` and add it to `translators`. Returns the
+/// name of the new translator
+pub fn load_user_translator(translators: &mut TranslatorList, path: &Utf8Path) -> Result<String> {
+    if path.extension() == Some("py") {
+        return load_python_translator(translators, path);
+    }
+    if path.extension() == Some("wasm") {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read translator from {path}"))?;
+        return load_wasm_translator(translators, &bytes, path.as_str());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read translator from {path}"))?;
+    let table: toml::Table =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {path}"))?;
+
+    let name = if table.contains_key("fields") {
+        let translator = BitFieldTranslator::from_str(&content)
+            .with_context(|| format!("Failed to parse translator {path}"))?;
+        check_field_translators(&translator, &translators.basic_translator_names())?;
+        let name = translator.name();
+        translators.add(Box::new(translator));
+        name
+    } else {
+        let translator = MappingTranslator::from_str(&content)
+            .with_context(|| format!("Failed to parse translator {path}"))?;
+        let name = translator.name();
+        translators.add_basic(Box::new(translator));
+        name
+    };
+    info!("Loaded translator {name} from {path}");
+    Ok(name)
+}
+
+#[cfg(feature = "python")]
+fn load_python_translator(translators: &mut TranslatorList, path: &Utf8Path) -> Result<String> {
+    let translator = super::python::PythonTranslator::new(path)?;
+    let name = translator.name();
+    translators.add(Box::new(translator));
+    info!("Loaded Python translator {name} from {path}");
+    Ok(name)
+}
+
+#[cfg(not(feature = "python"))]
+fn load_python_translator(_translators: &mut TranslatorList, path: &Utf8Path) -> Result<String> {
+    color_eyre::eyre::bail!("Can not load {path}, Surfer was built without Python support")
+}
+
+fn load_wasm_translator(
+    translators: &mut TranslatorList,
+    bytes: &[u8],
+    source: &str,
+) -> Result<String> {
+    let translator = WasmTranslator::new(bytes)
+        .with_context(|| format!("Failed to load WebAssembly translator {source}"))?;
+    let name = translator.name();
+    translators.add(Box::new(translator));
+    info!("Loaded WebAssembly translator {name} from {source}");
+    Ok(name)
+}
+
+/// Load a dropped file as a translator if it is a WebAssembly module. Returns `None` if
+/// the file is something else, presumably a waveform
+pub fn load_dropped_translator(
+    translators: &mut TranslatorList,
+    file: &DroppedFile,
+) -> Option<Result<String>> {
+    let source = file
+        .path
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| file.name.clone());
+    match (&file.bytes, &file.path) {
+        (Some(bytes), _) if bytes.starts_with(WASM_MAGIC) => {
+            Some(load_wasm_translator(translators, bytes, &source))
+        }
+        (None, Some(path)) if path.extension().map_or(false, |ext| ext == "wasm") => Some(
+            std::fs::read(path)
+                .with_context(|| format!("Failed to read translator from {source}"))
+                .and_then(|bytes| load_wasm_translator(translators, &bytes, &source)),
+        ),
+        _ => None,
+    }
+}
+
+impl State {
+    /// Download a WebAssembly translator from `url` and add it once it has loaded
+    pub fn load_translator_from_url(&mut self, url: String) {
+        let sender = self.msg_sender.clone();
+        let task = async move {
+            let bytes = reqwest::get(&url)
+                .map(|e| e.with_context(|| format!("Failed fetch download {url}")))
+                .and_then(|resp| {
+                    resp.bytes()
+                        .map(|e| e.with_context(|| format!("Failed to download {url}")))
+                })
+                .await;
+
+            let translator = bytes.and_then(|bytes| {
+                WasmTranslator::new(&bytes)
+                    .with_context(|| format!("Failed to load WebAssembly translator {url}"))
+            });
+            match translator {
+                Ok(t) => sender.send(Message::TranslatorLoaded(Box::new(t))),
+                Err(e) => sender.send(Message::Error(e)),
+            }
+            .unwrap();
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::spawn(task);
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(task);
+    }
+}
+
+/// Load all translators in the translator directories. Files which fail to load are
+/// reported and skipped
+pub fn load_user_translators(translators: &mut TranslatorList) {
+    let paths = translator_dirs()
+        .into_iter()
+        .filter_map(|dir| dir.read_dir_utf8().ok())
+        .flatten()
+        .filter_map(|entry| entry.map_err(|e| warn!("{e:#?}")).ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| matches!(path.extension(), Some("toml") | Some("py") | Some("wasm")));
+
+    for path in paths {
+        if let Err(e) = load_user_translator(translators, &path) {
+            warn!("{e:#?}")
+        }
+    }
+}
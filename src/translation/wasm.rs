@@ -0,0 +1,495 @@
+//! Translators compiled to WebAssembly. Plugins run sandboxed in an interpreter, which
+//! makes the same plugin usable in the native and the web build. A plugin module exports
+//!
+//! - `memory`, its linear memory
+//! - `alloc(len: i32) -> i32`, allocating `len` bytes for an argument which Surfer
+//!   writes before calling one of the functions below. The plugin owns the allocation
+//!   from then on
+//! - `name() -> i64`, returning the name of the translator
+//! - `translates(ptr: i32, len: i32) -> i32`, returning 0 if the signal can not be
+//!   translated, 1 if it can and 2 if the translator should be used by default
+//! - `signal_info(ptr: i32, len: i32) -> i64`
+//! - `translate(ptr: i32, len: i32) -> i64`
+//!
+//! Strings are UTF-8 and returned as `ptr << 32 | len`. The arguments are JSON objects
+//! `{"name": "top.cpu.insn", "width": 32}` describing the signal, and for `translate`
+//! also `"value"`, the value as a string of binary digits which can include other values
+//! such as `x` and `z`. The other return values are also JSON.
+//!
+//! `signal_info` returns one of `"bits"`, `"bool"`, `"clock"`, `"string"` and `"real"`,
+//! or a list of `[name, info]` pairs for the sub-fields. `translate` returns a string,
+//! `null` if the value is not present, or an object with one of the keys
+//!
+//! - `string`, the value as a string
+//! - `bits`, a string of binary digits which is shown in the format chosen for the field
+//! - `struct`, a list of `[name, value]` pairs of the fields
+//! - `tuple` and `array`, lists of the values of the fields
+//! - `enum`, an object with the `name` of the selected variant and `variants`, a list of
+//!   `[name, value]` pairs of all variants
+//!
+//! and optionally `kind`, one of `normal`, `undef`, `highimp`, `warn`, `dontcare` and
+//! `weak`.
+//!
+//! Every call runs with a limited amount of fuel. A call which runs out fails like any
+//! other plugin error, which blacklists the translator for the signal.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use color_eyre::eyre::{anyhow, bail, Context};
+use color_eyre::Result;
+use num::BigUint;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, TypedFunc, WasmParams, WasmResults,
+};
+use waveform::{Hierarchy, SignalLength, SignalValue, Var};
+
+use super::{
+    SignalInfo, TranslationPreference, TranslationResult, Translator, ValueKind, ValueRepr,
+};
+
+/// The first bytes of every WebAssembly module
+pub const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// The fuel a plugin gets for each call. A call which runs out, for example because the
+/// plugin loops forever, fails instead of hanging the UI
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+pub struct WasmTranslator {
+    name: String,
+    plugin: Mutex<Plugin>,
+}
+
+struct Plugin {
+    store: Store<()>,
+    exports: Exports,
+}
+
+struct Exports {
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    translates: TypedFunc<(i32, i32), i32>,
+    signal_info: TypedFunc<(i32, i32), i64>,
+    translate: TypedFunc<(i32, i32), i64>,
+}
+
+/// Top up the fuel of `store` to `FUEL_PER_CALL`
+fn refuel(store: &mut Store<()>) -> Result<()> {
+    let remaining = store.consume_fuel(0).map_err(|e| anyhow!("{e}"))?;
+    store
+        .add_fuel(FUEL_PER_CALL.saturating_sub(remaining))
+        .map_err(|e| anyhow!("{e}"))
+}
+
+/// Convert an error from calling into the plugin, reporting fuel exhaustion explicitly
+fn call_error(store: &mut Store<()>, e: wasmi::Error) -> color_eyre::Report {
+    if matches!(store.consume_fuel(0), Ok(0)) {
+        anyhow!("Plugin ran out of fuel")
+    } else {
+        anyhow!("{e}")
+    }
+}
+
+fn export<Params: WasmParams, Results: WasmResults>(
+    instance: &Instance,
+    store: &Store<()>,
+    name: &str,
+) -> Result<TypedFunc<Params, Results>> {
+    instance
+        .get_typed_func(store, name)
+        .map_err(|e| anyhow!("Plugin does not export {name} with the right type: {e}"))
+}
+
+impl Exports {
+    /// Call `func` with `arg` written to memory allocated by the plugin
+    fn call<R: WasmResults>(
+        &self,
+        store: &mut Store<()>,
+        func: &TypedFunc<(i32, i32), R>,
+        arg: &str,
+    ) -> Result<R> {
+        refuel(store)?;
+        let len = arg.len() as i32;
+        let ptr = self
+            .alloc
+            .call(&mut *store, len)
+            .map_err(|e| call_error(store, e))?;
+        self.memory
+            .write(&mut *store, ptr as usize, arg.as_bytes())
+            .map_err(|e| anyhow!("{e}"))?;
+        func.call(&mut *store, (ptr, len))
+            .map_err(|e| call_error(store, e))
+    }
+
+    /// Read a string returned as `ptr << 32 | len`
+    fn read_string(&self, store: &Store<()>, packed: i64) -> Result<String> {
+        let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        let mut bytes = vec![0; len];
+        self.memory
+            .read(store, ptr, &mut bytes)
+            .map_err(|e| anyhow!("{e}"))?;
+        String::from_utf8(bytes).context("Plugin returned a string which is not UTF-8")
+    }
+
+    fn call_json<T: DeserializeOwned, R>(
+        &self,
+        store: &mut Store<()>,
+        func: &TypedFunc<(i32, i32), i64>,
+        arg: &str,
+        convert: impl FnOnce(T) -> Result<R>,
+    ) -> Result<R> {
+        let packed = self.call(store, func, arg)?;
+        let json = self.read_string(store, packed)?;
+        let value = serde_json::from_str::<T>(&json)
+            .map_err(|e| anyhow!("Plugin returned invalid JSON: {e}"))?;
+        convert(value)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PluginInfo {
+    Kind(String),
+    Compound(Vec<(String, PluginInfo)>),
+}
+
+impl PluginInfo {
+    fn into_info(self) -> Result<SignalInfo> {
+        match self {
+            PluginInfo::Kind(kind) => match kind.as_str() {
+                "bits" => Ok(SignalInfo::Bits),
+                "bool" => Ok(SignalInfo::Bool),
+                "clock" => Ok(SignalInfo::Clock),
+                "string" => Ok(SignalInfo::String),
+                "real" => Ok(SignalInfo::Real),
+                other => bail!("Unknown signal info {other}"),
+            },
+            PluginInfo::Compound(fields) => Ok(SignalInfo::Compound {
+                subfields: fields
+                    .into_iter()
+                    .map(|(name, info)| Ok((name, info.into_info()?)))
+                    .collect::<Result<_>>()?,
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum PluginKind {
+    #[default]
+    Normal,
+    Undef,
+    HighImp,
+    Warn,
+    DontCare,
+    Weak,
+}
+
+impl From<PluginKind> for ValueKind {
+    fn from(kind: PluginKind) -> Self {
+        match kind {
+            PluginKind::Normal => ValueKind::Normal,
+            PluginKind::Undef => ValueKind::Undef,
+            PluginKind::HighImp => ValueKind::HighImp,
+            PluginKind::Warn => ValueKind::Warn,
+            PluginKind::DontCare => ValueKind::DontCare,
+            PluginKind::Weak => ValueKind::Weak,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PluginRepr {
+    String(String),
+    Bits(String),
+    Struct(Vec<(String, PluginValue)>),
+    Tuple(Vec<PluginValue>),
+    Array(Vec<PluginValue>),
+    Enum {
+        name: String,
+        variants: Vec<(String, PluginValue)>,
+    },
+}
+
+#[derive(Deserialize)]
+struct TaggedValue {
+    #[serde(flatten)]
+    repr: PluginRepr,
+    #[serde(default)]
+    kind: PluginKind,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PluginValue {
+    NotPresent(()),
+    String(String),
+    Tagged(TaggedValue),
+}
+
+impl PluginValue {
+    fn into_result(self) -> Result<TranslationResult> {
+        let indexed = |values: Vec<PluginValue>| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| (i.to_string(), value))
+                .collect::<Vec<_>>()
+        };
+        let (val, subfields, kind) = match self {
+            PluginValue::NotPresent(()) => (ValueRepr::NotPresent, vec![], PluginKind::Normal),
+            PluginValue::String(s) => (ValueRepr::String(s), vec![], PluginKind::Normal),
+            PluginValue::Tagged(TaggedValue { repr, kind }) => {
+                let (val, subfields) = match repr {
+                    PluginRepr::String(s) => (ValueRepr::String(s), vec![]),
+                    PluginRepr::Bits(bits) => (ValueRepr::Bits(bits.len() as u64, bits), vec![]),
+                    PluginRepr::Struct(fields) => (ValueRepr::Struct, fields),
+                    PluginRepr::Tuple(values) => (ValueRepr::Tuple, indexed(values)),
+                    PluginRepr::Array(values) => (ValueRepr::Array, indexed(values)),
+                    PluginRepr::Enum { name, variants } => {
+                        let idx = variants
+                            .iter()
+                            .position(|(variant, _)| *variant == name)
+                            .ok_or_else(|| anyhow!("{name} is not an enum variant"))?;
+                        (ValueRepr::Enum { idx, name }, variants)
+                    }
+                };
+                (val, subfields, kind)
+            }
+        };
+
+        Ok(TranslationResult {
+            val,
+            subfields: subfields
+                .into_iter()
+                .map(|(name, value)| Ok((name, value.into_result()?)))
+                .collect::<Result<_>>()?,
+            color: kind.into(),
+            durations: HashMap::new(),
+        })
+    }
+}
+
+impl Plugin {
+    fn translates(&mut self, signal: &str) -> Result<TranslationPreference> {
+        let Plugin { store, exports } = self;
+        match exports.call(store, &exports.translates, signal)? {
+            0 => Ok(TranslationPreference::No),
+            1 => Ok(TranslationPreference::Yes),
+            2 => Ok(TranslationPreference::Prefer),
+            other => bail!("Unknown translation preference {other}"),
+        }
+    }
+
+    fn signal_info(&mut self, signal: &str) -> Result<SignalInfo> {
+        let Plugin { store, exports } = self;
+        exports.call_json(store, &exports.signal_info, signal, PluginInfo::into_info)
+    }
+
+    fn translate(&mut self, signal: &str) -> Result<TranslationResult> {
+        let Plugin { store, exports } = self;
+        exports.call_json(store, &exports.translate, signal, PluginValue::into_result)
+    }
+}
+
+impl WasmTranslator {
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes).map_err(|e| anyhow!("Invalid plugin: {e}"))?;
+        let mut store = Store::new(&engine, ());
+        refuel(&mut store)?;
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| anyhow!("Failed to instantiate plugin: {e}"))?;
+
+        let exports = Exports {
+            memory: instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| anyhow!("Plugin does not export its memory"))?,
+            alloc: export(&instance, &store, "alloc")?,
+            translates: export(&instance, &store, "translates")?,
+            signal_info: export(&instance, &store, "signal_info")?,
+            translate: export(&instance, &store, "translate")?,
+        };
+        let name_func = export::<(), i64>(&instance, &store, "name")?;
+        refuel(&mut store)?;
+        let packed = name_func
+            .call(&mut store, ())
+            .map_err(|e| call_error(&mut store, e))?;
+        let name = exports.read_string(&store, packed)?;
+
+        Ok(Self {
+            name,
+            plugin: Mutex::new(Plugin { store, exports }),
+        })
+    }
+
+    fn plugin(&self) -> Result<std::sync::MutexGuard<'_, Plugin>> {
+        self.plugin
+            .lock()
+            .map_err(|_| anyhow!("{} failed earlier", self.name))
+    }
+}
+
+fn signal_json(hierarchy: &Hierarchy, var: &Var) -> serde_json::Value {
+    let width = match var.length() {
+        SignalLength::Variable => 0,
+        SignalLength::Fixed(len) => len.get() as u64,
+    };
+    serde_json::json!({"name": var.full_name(hierarchy), "width": width})
+}
+
+impl Translator for WasmTranslator {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn translate(
+        &self,
+        hierarchy: &Hierarchy,
+        var: &Var,
+        value: &SignalValue,
+    ) -> Result<TranslationResult> {
+        let mut signal = signal_json(hierarchy, var);
+        signal["value"] = match value {
+            SignalValue::Binary(bytes) => format!(
+                "{:0width$b}",
+                BigUint::from_bytes_be(bytes),
+                width = signal["width"].as_u64().unwrap_or(0) as usize
+            ),
+            SignalValue::String(s) => s.to_string(),
+        }
+        .into();
+        self.plugin()?
+            .translate(&signal.to_string())
+            .with_context(|| format!("{} failed to translate", self.name))
+    }
+
+    fn signal_info(&self, hierarchy: &Hierarchy, var: &Var) -> Result<SignalInfo> {
+        self.plugin()?
+            .signal_info(&signal_json(hierarchy, var).to_string())
+            .with_context(|| format!("{} failed to get signal info", self.name))
+    }
+
+    fn translates(&self, hierarchy: &Hierarchy, var: &Var) -> Result<TranslationPreference> {
+        self.plugin()?
+            .translates(&signal_json(hierarchy, var).to_string())
+            .with_context(|| format!("{} failed to check signal", self.name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INFO: &str = r#"[["valid","bool"],["data","bits"]]"#;
+    const VALUE: &str = r#"{"struct":[["valid",{"bits":"1"}],["data","idle"]],"kind":"warn"}"#;
+
+    /// A plugin which returns constant values, and prefers signals passed as JSON objects
+    fn test_plugin() -> WasmTranslator {
+        let data = |offset: u64, s: &str| {
+            format!(
+                r#"(data (i32.const {offset}) "{}")"#,
+                s.replace('"', r#"\""#)
+            )
+        };
+        let packed = |offset: u64, s: &str| offset << 32 | s.len() as u64;
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                {name_data}
+                {info_data}
+                {value_data}
+                (func (export "alloc") (param $len i32) (result i32)
+                    (global.get $next)
+                    (global.set $next (i32.add (global.get $next) (local.get $len))))
+                (func (export "name") (result i64) (i64.const {name}))
+                (func (export "translates") (param $ptr i32) (param $len i32) (result i32)
+                    (select (i32.const 2) (i32.const 0)
+                        (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 123))))
+                (func (export "signal_info") (param i32 i32) (result i64) (i64.const {info}))
+                (func (export "translate") (param i32 i32) (result i64) (i64.const {value})))"#,
+            name_data = data(0, "Test plugin"),
+            info_data = data(16, INFO),
+            value_data = data(128, VALUE),
+            name = packed(0, "Test plugin"),
+            info = packed(16, INFO),
+            value = packed(128, VALUE),
+        );
+        WasmTranslator::new(&wat::parse_str(wat).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn plugins_are_called_through_the_abi() {
+        let translator = test_plugin();
+        assert_eq!(translator.name(), "Test plugin");
+
+        let mut plugin = translator.plugin().unwrap();
+        let signal = r#"{"name":"top.data","width":9}"#;
+        assert!(plugin.translates(signal).unwrap() == TranslationPreference::Prefer);
+        assert!(plugin.translates("[]").unwrap() == TranslationPreference::No);
+
+        let Ok(SignalInfo::Compound { subfields }) = plugin.signal_info(signal) else {
+            panic!("Expected compound signal info")
+        };
+        assert_eq!(subfields[0].0, "valid");
+
+        let result = plugin.translate(signal).unwrap();
+        assert!(matches!(result.val, ValueRepr::Struct));
+        assert_eq!(result.color, ValueKind::Warn);
+        assert!(matches!(&result.subfields[1].1.val, ValueRepr::String(s) if s == "idle"));
+    }
+
+    #[test]
+    fn looping_plugins_run_out_of_fuel() {
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "Loop")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "name") (result i64) (i64.const 4))
+            (func (export "translates") (param i32 i32) (result i32) (i32.const 1))
+            (func (export "signal_info") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                (i64.const 0))
+            (func (export "translate") (param i32 i32) (result i64) (i64.const 0)))"#;
+        let translator = WasmTranslator::new(&wat::parse_str(wat).unwrap()).unwrap();
+        assert_eq!(translator.name(), "Loop");
+
+        let mut plugin = translator.plugin().unwrap();
+        let signal = r#"{"name":"top.data","width":9}"#;
+        let error = plugin.signal_info(signal).unwrap_err();
+        assert_eq!(error.to_string(), "Plugin ran out of fuel");
+        // The plugin is refuelled for the next call
+        assert!(plugin.translates(signal).unwrap() == TranslationPreference::Yes);
+    }
+
+    #[test]
+    fn values_are_parsed() {
+        let parse = |json| {
+            serde_json::from_str::<PluginValue>(json)
+                .unwrap()
+                .into_result()
+        };
+
+        assert!(matches!(parse("null").unwrap().val, ValueRepr::NotPresent));
+        let tuple = parse(r#"{"tuple": ["a", {"bits": "01"}]}"#).unwrap();
+        assert_eq!(tuple.subfields[1].0, "1");
+        assert!(matches!(&tuple.subfields[1].1.val, ValueRepr::Bits(2, _)));
+
+        let option =
+            parse(r#"{"enum": {"name": "Some", "variants": [["None", null], ["Some", "5"]]}}"#);
+        assert!(matches!(
+            option.unwrap().val,
+            ValueRepr::Enum { idx: 1, .. }
+        ));
+        assert!(parse(r#"{"enum": {"name": "Other", "variants": []}}"#).is_err());
+        assert!(serde_json::from_str::<PluginValue>(r#"{"kind": "warn"}"#).is_err());
+    }
+}
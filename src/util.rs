@@ -57,3 +57,14 @@ pub fn alpha_idx_to_uint_idx(idx: String) -> Option<usize> {
         .collect::<String>();
     usize::from_str_radix(&mapped, 16).ok()
 }
+
+/// The new index of the item at `idx` after the items at `a` and `b` are swapped
+pub fn swapped_index(idx: usize, a: usize, b: usize) -> usize {
+    if idx == a {
+        b
+    } else if idx == b {
+        a
+    } else {
+        idx
+    }
+}
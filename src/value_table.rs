@@ -0,0 +1,433 @@
+use std::collections::{BTreeSet, HashMap};
+
+use camino::Utf8PathBuf;
+use color_eyre::eyre::{anyhow, bail, Context};
+use color_eyre::Result;
+use itertools::Itertools;
+use log::{info, warn};
+use num::{BigUint, ToPrimitive};
+
+use crate::displayed_item::DisplayedItem;
+use crate::translation::TranslatorList;
+use crate::wave_container::{FieldRef, VarName};
+use crate::{State, WaveData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+impl TableFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match Utf8PathBuf::from(path).extension()?.to_lowercase().as_str() {
+            "csv" => Some(TableFormat::Csv),
+            "json" => Some(TableFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The times at which the displayed signals are sampled
+#[derive(Debug, Clone)]
+pub enum Sampling {
+    /// Every time any of the displayed signals changes
+    Changes,
+    /// Every rising edge of the specified clock signal
+    RisingEdge(VarName),
+}
+
+/// The time range to export
+#[derive(Debug, Clone)]
+pub enum TableRange {
+    All,
+    /// Between the two numbered cursors
+    Cursors(u8, u8),
+    /// Between two absolute timestamps
+    Time(BigUint, BigUint),
+}
+
+#[derive(Debug, Clone)]
+pub struct ValueTableSettings {
+    pub path: String,
+    pub format: TableFormat,
+    pub sampling: Sampling,
+    pub range: TableRange,
+}
+
+/// A sampled table of the values of all (sub-)fields of the displayed signals
+struct ValueTable {
+    columns: Vec<String>,
+    /// The sample time and the value of each column, `None` if the column has no value
+    rows: Vec<(BigUint, Vec<Option<String>>)>,
+}
+
+impl WaveData {
+    pub(crate) fn table_range(&self, range: &TableRange) -> Result<(BigUint, BigUint)> {
+        let end_of_waves = self.num_timestamps.to_biguint().unwrap_or_default();
+        let (start, end) = match range {
+            TableRange::All => (BigUint::default(), end_of_waves),
+            TableRange::Cursors(a, b) => {
+                let time = |idx: &u8| {
+                    self.cursors
+                        .get(idx)
+                        .and_then(|t| t.to_biguint())
+                        .ok_or_else(|| anyhow!("Cursor {idx} is not set"))
+                };
+                (time(a)?, time(b)?)
+            }
+            TableRange::Time(a, b) => (a.clone(), b.clone()),
+        };
+        // Allow the cursors to be specified in either order
+        if start <= end {
+            Ok((start, end))
+        } else {
+            Ok((end, start))
+        }
+    }
+
+    /// All times in `start..=end` at which `signal` changes, including `start`
+    pub(crate) fn change_times(
+        &self,
+        signal: &VarName,
+        start: &BigUint,
+        end: &BigUint,
+    ) -> Vec<BigUint> {
+        let mut times = vec![start.clone()];
+        while let Some(next) = self.next_change_time(signal, times.last().unwrap()) {
+            if &next > end {
+                break;
+            }
+            times.push(next);
+        }
+        times
+    }
+
+    fn sample_times(&self, sampling: &Sampling, start: &BigUint, end: &BigUint) -> Vec<BigUint> {
+        match sampling {
+            Sampling::Changes => self
+                .displayed_items
+                .iter()
+                .filter_map(|item| match item {
+                    DisplayedItem::Signal(signal) => Some(&signal.signal_ref),
+                    _ => None,
+                })
+                .flat_map(|signal| self.change_times(signal, start, end))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            Sampling::RisingEdge(clock) => {
+                // `change_times` includes `start` even if the clock does not change there
+                let changes_at_start = start > &BigUint::default()
+                    && self.next_change_time(clock, &(start - 1u32)).as_ref() == Some(start);
+                self.change_times(clock, start, end)
+                    .into_iter()
+                    .filter(|time| {
+                        (time != start || changes_at_start)
+                            && self
+                                .raw_value_at(clock, time)
+                                .map_or(false, |value| value == "1")
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The names and translated values of all (sub-)fields of `signal` at `time`
+    fn translated_fields_at(
+        &self,
+        signal: &VarName,
+        time: &BigUint,
+        translators: &TranslatorList,
+    ) -> Vec<(String, Option<String>)> {
+        let field_name = |path: &[String]| {
+            [signal.full_path_string()]
+                .into_iter()
+                .chain(path.iter().cloned())
+                .join(".")
+        };
+        let root = FieldRef::without_fields(signal.clone());
+
+        let Some((meta, value)) = self.inner.signal_meta(signal).ok().and_then(|meta| {
+            let (_, value) = self.inner.query_signal(signal, time).ok().flatten()?;
+            Some((meta, value))
+        }) else {
+            return vec![(field_name(&[]), None)];
+        };
+        let translated = match self
+            .signal_translator(&root, translators)
+            .translate(&meta, &value)
+        {
+            Ok(translated) => translated,
+            Err(e) => {
+                warn!("{e:#?}");
+                return vec![(field_name(&[]), None)];
+            }
+        };
+
+        translated
+            .flatten(root, &self.signal_format, translators)
+            .as_fields()
+            .into_iter()
+            .map(|(path, value)| (field_name(&path), value.map(|(value, _)| value)))
+            .collect()
+    }
+
+    fn value_table(
+        &self,
+        settings: &ValueTableSettings,
+        translators: &TranslatorList,
+    ) -> Result<ValueTable> {
+        if let Sampling::RisingEdge(clock) = &settings.sampling {
+            if self.inner.signal_meta(clock).is_err() {
+                bail!("Clock signal {} does not exist", clock.full_path_string());
+            }
+        }
+        let (start, end) = self.table_range(&settings.range)?;
+
+        let signals = self
+            .displayed_items
+            .iter()
+            .filter_map(|item| match item {
+                DisplayedItem::Signal(signal) => Some(&signal.signal_ref),
+                _ => None,
+            })
+            .collect_vec();
+
+        // The set of sub-fields can change over time, for example for enums, so
+        // columns are added as they are encountered
+        let mut columns: Vec<String> = vec![];
+        let mut column_indices: HashMap<String, usize> = HashMap::new();
+        let samples = self
+            .sample_times(&settings.sampling, &start, &end)
+            .into_iter()
+            .map(|time| {
+                let fields = signals
+                    .iter()
+                    .flat_map(|signal| self.translated_fields_at(signal, &time, translators))
+                    .map(|(name, value)| {
+                        let idx = *column_indices.entry(name).or_insert_with_key(|name| {
+                            columns.push(name.clone());
+                            columns.len() - 1
+                        });
+                        (idx, value)
+                    })
+                    .collect_vec();
+                (time, fields)
+            })
+            .collect_vec();
+
+        let rows = samples
+            .into_iter()
+            .map(|(time, fields)| {
+                let mut values = vec![None; columns.len()];
+                for (idx, value) in fields {
+                    values[idx] = value;
+                }
+                (time, values)
+            })
+            .collect();
+
+        Ok(ValueTable { columns, rows })
+    }
+}
+
+impl ValueTable {
+    fn to_csv(&self) -> String {
+        fn escape(field: &str) -> String {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        }
+
+        let header = ["time".to_string()]
+            .iter()
+            .chain(&self.columns)
+            .map(|c| escape(c))
+            .join(",");
+        let rows = self.rows.iter().map(|(time, values)| {
+            [time.to_string()]
+                .into_iter()
+                .chain(
+                    values
+                        .iter()
+                        .map(|v| v.as_deref().map(escape).unwrap_or_default()),
+                )
+                .join(",")
+        });
+        [header].into_iter().chain(rows).join("\n") + "\n"
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let rows = self
+            .rows
+            .iter()
+            .map(|(time, values)| {
+                let mut row = serde_json::Map::new();
+                row.insert(
+                    "time".to_string(),
+                    time.to_u64()
+                        .map(serde_json::Value::from)
+                        .unwrap_or_else(|| serde_json::Value::from(time.to_string())),
+                );
+                for (column, value) in self.columns.iter().zip(values) {
+                    row.insert(column.clone(), serde_json::Value::from(value.clone()));
+                }
+                serde_json::Value::Object(row)
+            })
+            .collect_vec();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "signals": self.columns,
+            "rows": rows,
+        }))
+        .context("Failed to serialize value table")
+    }
+}
+
+impl State {
+    pub fn export_value_table(&self, settings: &ValueTableSettings) -> Result<()> {
+        let Some(waves) = &self.waves else {
+            bail!("Can not export values without a loaded waveform");
+        };
+
+        let table = waves.value_table(settings, &self.translators)?;
+        let content = match settings.format {
+            TableFormat::Csv => table.to_csv(),
+            TableFormat::Json => table.to_json()?,
+        };
+        std::fs::write(&settings.path, content)
+            .with_context(|| format!("Failed to write {}", settings.path))?;
+
+        info!(
+            "Exported {} samples of {} fields to {}",
+            table.rows.len(),
+            table.columns.len(),
+            settings.path
+        );
+        Ok(())
+    }
+}
+
+/// Parse the arguments of the `export_values` command:
+/// `<file> [changes|<clock signal>] [<cursor> <cursor>]`
+pub fn parse_export_values_args(args: &str) -> Option<ValueTableSettings> {
+    let words = args.split_whitespace().collect_vec();
+    let (path, rest) = words.split_first()?;
+    let format = TableFormat::from_path(path)?;
+
+    let (sampling, rest) = match rest.split_first() {
+        Some((&"changes", rest)) => (Sampling::Changes, rest),
+        Some((word, rest)) if word.parse::<u8>().is_err() => (
+            Sampling::RisingEdge(VarName::from_hierarchy_string(word)),
+            rest,
+        ),
+        _ => (Sampling::Changes, rest),
+    };
+
+    let range = match rest {
+        [] => TableRange::All,
+        [a, b] => TableRange::Cursors(a.parse().ok()?, b.parse().ok()?),
+        _ => return None,
+    };
+
+    Some(ValueTableSettings {
+        path: path.to_string(),
+        format,
+        sampling,
+        range,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::Message;
+    use crate::tests::load_example;
+
+    fn table() -> ValueTable {
+        ValueTable {
+            columns: vec!["tb.a".to_string(), "tb.b,c".to_string()],
+            rows: vec![
+                (BigUint::from(0u32), vec![Some("1".to_string()), None]),
+                (
+                    BigUint::from(10u32),
+                    vec![Some("say \"hi\"".to_string()), Some("x\r\ny".to_string())],
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn csv_fields_are_escaped() {
+        assert_eq!(
+            table().to_csv(),
+            "time,tb.a,\"tb.b,c\"\n0,1,\n10,\"say \"\"hi\"\"\",\"x\r\ny\"\n"
+        );
+    }
+
+    #[test]
+    fn json_rows_contain_all_columns() {
+        let json: serde_json::Value = serde_json::from_str(&table().to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "signals": ["tb.a", "tb.b,c"],
+                "rows": [
+                    {"time": 0, "tb.a": "1", "tb.b,c": null},
+                    {"time": 10, "tb.a": "say \"hi\"", "tb.b,c": "x\r\ny"},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn export_values_args_are_parsed() {
+        let settings = parse_export_values_args("out.csv").unwrap();
+        assert_eq!(settings.format, TableFormat::Csv);
+        assert!(matches!(settings.sampling, Sampling::Changes));
+        assert!(matches!(settings.range, TableRange::All));
+
+        let settings = parse_export_values_args("out.json tb.clk 1 2").unwrap();
+        assert_eq!(settings.format, TableFormat::Json);
+        assert!(
+            matches!(&settings.sampling, Sampling::RisingEdge(clock) if clock.full_path_string() == "tb.clk")
+        );
+        assert!(matches!(settings.range, TableRange::Cursors(1, 2)));
+
+        let settings = parse_export_values_args("out.csv 2 1").unwrap();
+        assert!(matches!(settings.sampling, Sampling::Changes));
+        assert!(matches!(settings.range, TableRange::Cursors(2, 1)));
+
+        assert!(parse_export_values_args("out.txt").is_none());
+        assert!(parse_export_values_args("out.csv changes 1").is_none());
+    }
+
+    #[test]
+    fn rising_edges_are_sampled_from_start() {
+        let mut state = load_example("examples/counter.vcd");
+        state.update(Message::AddSignal(VarName::from_hierarchy_string(
+            "tb.dut.counter",
+        )));
+        let waves = state.waves.as_ref().unwrap();
+        let settings = |start: u32| ValueTableSettings {
+            path: String::new(),
+            format: TableFormat::Csv,
+            sampling: Sampling::RisingEdge(VarName::from_hierarchy_string("tb.clk")),
+            range: TableRange::Time(start.into(), 50u32.into()),
+        };
+        let times = |start| {
+            waves
+                .value_table(&settings(start), &state.translators)
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|(time, _)| time.to_u32().unwrap())
+                .collect_vec()
+        };
+        assert_eq!(times(10), vec![10, 30, 50]);
+        assert_eq!(times(11), vec![30, 50]);
+        assert_eq!(times(0), vec![10, 30, 50]);
+    }
+}
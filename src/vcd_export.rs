@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use color_eyre::eyre::{bail, Context};
+use color_eyre::Result;
+use itertools::Itertools;
+use log::{info, warn};
+use num::BigUint;
+
+use crate::displayed_item::DisplayedItem;
+use crate::value_table::TableRange;
+use crate::wave_container::{ScopeName, VarName};
+use crate::{State, WaveData};
+
+/// The signals to include in an exported VCD file
+#[derive(Debug, Clone)]
+pub enum VcdSignalSelection {
+    /// The signals in the list of displayed items
+    Displayed,
+    /// All signals in the scope and its sub-scopes
+    Scope(ScopeName),
+}
+
+#[derive(Debug, Clone)]
+pub struct VcdExportSettings {
+    pub path: String,
+    pub selection: VcdSignalSelection,
+    pub range: TableRange,
+}
+
+/// The scope of signals which are not in any scope in the original waveform, since
+/// some tools can not read variables outside of a scope
+const TOP_SCOPE: &str = "top";
+
+/// A node in the scope tree of the exported file
+#[derive(Default)]
+struct VcdScope {
+    signals: Vec<(String, usize)>,
+    children: BTreeMap<String, VcdScope>,
+}
+
+/// The short identifier of signal number `idx`, built from the printable ASCII
+/// characters allowed by the VCD format
+fn vcd_identifier(mut idx: usize) -> String {
+    const FIRST: u8 = b'!';
+    const COUNT: usize = (b'~' - b'!' + 1) as usize;
+
+    let mut result = String::new();
+    loop {
+        result.push((FIRST + (idx % COUNT) as u8) as char);
+        idx /= COUNT;
+        if idx == 0 {
+            break;
+        }
+        idx -= 1;
+    }
+    result
+}
+
+fn write_scope(out: &mut String, name: &str, scope: &VcdScope, widths: &[u32]) {
+    writeln!(out, "$scope module {name} $end").unwrap();
+    for (name, idx) in &scope.signals {
+        writeln!(
+            out,
+            "$var wire {} {} {name} $end",
+            widths[*idx],
+            vcd_identifier(*idx)
+        )
+        .unwrap();
+    }
+    for (name, child) in &scope.children {
+        write_scope(out, name, child, widths);
+    }
+    writeln!(out, "$upscope $end").unwrap();
+}
+
+impl WaveData {
+    fn signals_in_scope_tree(&self, scope: &ScopeName) -> Vec<VarName> {
+        let children = self
+            .inner
+            .child_modules(scope)
+            .context("Failed to get child modules")
+            .map_err(|e| warn!("{e:#?}"))
+            .unwrap_or_default();
+
+        self.inner
+            .signals_in_module(scope)
+            .into_iter()
+            .chain(
+                children
+                    .iter()
+                    .flat_map(|child| self.signals_in_scope_tree(child)),
+            )
+            .collect()
+    }
+
+    fn vcd_value(&self, signal: &VarName, width: u32, time: &BigUint) -> String {
+        let value = self
+            .raw_value_at(signal, time)
+            .unwrap_or_else(|| "x".repeat(width as usize));
+        if width == 1 {
+            value
+        } else {
+            format!("b{value} ")
+        }
+    }
+
+    fn vcd_content(&self, settings: &VcdExportSettings) -> Result<String> {
+        let signals = match &settings.selection {
+            VcdSignalSelection::Displayed => self
+                .displayed_items
+                .iter()
+                .filter_map(|item| match item {
+                    DisplayedItem::Signal(signal) => Some(signal.signal_ref.clone()),
+                    _ => None,
+                })
+                .collect_vec(),
+            VcdSignalSelection::Scope(scope) => {
+                if !self.inner.has_module(scope) {
+                    bail!("Scope {scope} does not exist");
+                }
+                self.signals_in_scope_tree(scope)
+            }
+        };
+
+        // Only signals with a known bit width can be represented as vectors
+        let (signals, widths): (Vec<_>, Vec<_>) = signals
+            .into_iter()
+            .unique()
+            .filter_map(
+                |signal| match self.inner.signal_meta(&signal).map(|meta| meta.num_bits) {
+                    Ok(Some(width)) => Some((signal, width)),
+                    Ok(None) => {
+                        warn!("Not exporting {signal:?} which is not a bit vector");
+                        None
+                    }
+                    Err(e) => {
+                        warn!("{e:#?}");
+                        None
+                    }
+                },
+            )
+            .unzip();
+        if signals.is_empty() {
+            bail!("No signals to export");
+        }
+
+        let (start, end) = self.table_range(&settings.range)?;
+
+        let mut root = VcdScope::default();
+        for (idx, signal) in signals.iter().enumerate() {
+            let scope = signal.path.strs().iter().fold(&mut root, |scope, name| {
+                scope.children.entry(name.clone()).or_default()
+            });
+            scope.signals.push((signal.name.clone(), idx));
+        }
+        if !root.signals.is_empty() {
+            let signals = std::mem::take(&mut root.signals);
+            root.children
+                .entry(TOP_SCOPE.to_string())
+                .or_default()
+                .signals
+                .extend(signals);
+        }
+
+        let mut changes: BTreeMap<BigUint, Vec<usize>> = BTreeMap::new();
+        for (idx, signal) in signals.iter().enumerate() {
+            for time in self.change_times(signal, &start, &end).into_iter().skip(1) {
+                changes.entry(time).or_default().push(idx);
+            }
+        }
+
+        let mut out = String::new();
+        let timescale = &self.inner.metadata().timescale;
+        writeln!(out, "$version surfer $end").unwrap();
+        writeln!(
+            out,
+            "$timescale {} {} $end",
+            timescale.factor, timescale.unit
+        )
+        .unwrap();
+        for (name, scope) in &root.children {
+            write_scope(&mut out, name, scope, &widths);
+        }
+        writeln!(out, "$enddefinitions $end").unwrap();
+
+        // Initial values at the start of the window
+        writeln!(out, "#{start}").unwrap();
+        writeln!(out, "$dumpvars").unwrap();
+        for (idx, signal) in signals.iter().enumerate() {
+            writeln!(
+                out,
+                "{}{}",
+                self.vcd_value(signal, widths[idx], &start),
+                vcd_identifier(idx)
+            )
+            .unwrap();
+        }
+        writeln!(out, "$end").unwrap();
+
+        let last_change = changes.keys().last().cloned();
+        for (time, changed) in changes {
+            writeln!(out, "#{time}").unwrap();
+            for idx in changed {
+                writeln!(
+                    out,
+                    "{}{}",
+                    self.vcd_value(&signals[idx], widths[idx], &time),
+                    vcd_identifier(idx)
+                )
+                .unwrap();
+            }
+        }
+        // Make sure the file covers the whole window even if nothing changes at the end
+        if start != end && last_change.as_ref() != Some(&end) {
+            writeln!(out, "#{end}").unwrap();
+        }
+
+        Ok(out)
+    }
+}
+
+impl State {
+    pub fn export_vcd(&self, settings: &VcdExportSettings) -> Result<()> {
+        let Some(waves) = &self.waves else {
+            bail!("Can not export a VCD file without a loaded waveform");
+        };
+
+        let content = waves.vcd_content(settings)?;
+        std::fs::write(&settings.path, content)
+            .with_context(|| format!("Failed to write {}", settings.path))?;
+        info!("Exported VCD file to {}", settings.path);
+        Ok(())
+    }
+}
+
+/// Parse the arguments of the `export_vcd` command:
+/// `<file> [displayed|<scope>] [<cursor> <cursor>]`
+pub fn parse_export_vcd_args(args: &str) -> Option<VcdExportSettings> {
+    let words = args.split_whitespace().collect_vec();
+    let (path, rest) = words.split_first()?;
+
+    let (selection, rest) = match rest.split_first() {
+        Some((&"displayed", rest)) => (VcdSignalSelection::Displayed, rest),
+        Some((word, rest)) if word.parse::<u8>().is_err() => (
+            VcdSignalSelection::Scope(ScopeName::from_hierarchy_string(word)),
+            rest,
+        ),
+        _ => (VcdSignalSelection::Displayed, rest),
+    };
+
+    let range = match rest {
+        [] => TableRange::All,
+        [a, b] => TableRange::Cursors(a.parse().ok()?, b.parse().ok()?),
+        _ => return None,
+    };
+
+    Some(VcdExportSettings {
+        path: path.to_string(),
+        selection,
+        range,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::Message;
+    use crate::tests::load_vcd_string;
+
+    const VCD: &str = "$timescale 1ns $end
+$var wire 1 ! en $end
+$scope module tb $end
+$var wire 1 \" clk $end
+$var wire 4 # data [3:0] $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+0\"
+b0000 #
+#10
+1\"
+#15
+1!
+#20
+0\"
+b0101 #
+#30
+1\"
+#40
+0\"
+b1111 #
+#50
+1\"
+";
+
+    #[test]
+    fn identifiers_are_unique() {
+        let identifiers = (0..10000).map(vcd_identifier).collect_vec();
+        assert_eq!(identifiers.iter().unique().count(), identifiers.len());
+        assert_eq!(vcd_identifier(0), "!");
+        assert_eq!(vcd_identifier(93), "~");
+        assert_eq!(vcd_identifier(94), "!!");
+    }
+
+    #[test]
+    fn exported_values_are_read_back() {
+        let mut state = load_vcd_string("vcd_export_original", VCD);
+        for signal in ["en", "tb.clk", "tb.data"] {
+            state.update(Message::AddSignal(VarName::from_hierarchy_string(signal)));
+        }
+        let waves = state.waves.as_ref().unwrap();
+        let content = waves
+            .vcd_content(&VcdExportSettings {
+                path: String::new(),
+                selection: VcdSignalSelection::Displayed,
+                range: TableRange::Time(15u32.into(), 40u32.into()),
+            })
+            .unwrap();
+        // Signals outside of any scope are moved into a scope
+        assert!(content.contains("$scope module top $end\n$var wire 1 ! en $end"));
+
+        let exported = load_vcd_string("vcd_export_exported", &content);
+        let exported_waves = exported.waves.as_ref().unwrap();
+        assert_eq!(exported_waves.num_timestamps, 40u32.into());
+        for (original, exported) in [
+            ("en", "top.en"),
+            ("tb.clk", "tb.clk"),
+            ("tb.data", "tb.data"),
+        ] {
+            let original = VarName::from_hierarchy_string(original);
+            let exported = VarName::from_hierarchy_string(exported);
+            assert_eq!(exported_waves.raw_value_at(&exported, &14u32.into()), None);
+            for time in 15u32..=40 {
+                assert_eq!(
+                    exported_waves.raw_value_at(&exported, &time.into()),
+                    waves.raw_value_at(&original, &time.into()),
+                    "{exported:?} at {time}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn export_vcd_args_are_parsed() {
+        let settings = parse_export_vcd_args("out.vcd").unwrap();
+        assert!(matches!(settings.selection, VcdSignalSelection::Displayed));
+        assert!(matches!(settings.range, TableRange::All));
+
+        let settings = parse_export_vcd_args("out.vcd tb.dut 0 1").unwrap();
+        assert!(
+            matches!(&settings.selection, VcdSignalSelection::Scope(scope) if scope.to_string() == "tb.dut")
+        );
+        assert!(matches!(settings.range, TableRange::Cursors(0, 1)));
+
+        assert!(parse_export_vcd_args("").is_none());
+        assert!(parse_export_vcd_args("out.vcd displayed 1").is_none());
+    }
+}
@@ -17,7 +17,9 @@ use crate::util::uint_idx_to_alpha_idx;
 use crate::wave_container::{FieldRef, ScopeName};
 use crate::wave_source::draw_progress_panel;
 use crate::{
-    command_prompt::show_command_prompt, translation::SignalInfo, Message, MoveDir, State, WaveData,
+    command_prompt::show_command_prompt,
+    translation::{bit_fields, SignalInfo},
+    Message, MoveDir, State, WaveData,
 };
 
 pub struct DrawingContext<'a> {
@@ -34,6 +36,18 @@ pub struct DrawConfig {
     pub max_transition_width: i32,
 }
 
+impl DrawConfig {
+    /// The configuration used to draw a canvas of height `canvas_height`, shared by the
+    /// UI and the image export
+    pub fn new(canvas_height: f32) -> Self {
+        Self {
+            canvas_height,
+            line_height: 16.,
+            max_transition_width: 6,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SignalDrawingInfo {
     pub field_ref: FieldRef,
@@ -54,10 +68,17 @@ pub struct CursorDrawingInfo {
     pub idx: u8,
 }
 
+#[derive(Debug)]
+pub struct DecoderDrawingInfo {
+    pub signal_list_idx: usize,
+    pub offset: f32,
+}
+
 pub enum ItemDrawingInfo {
     Signal(SignalDrawingInfo),
     Divider(DividerDrawingInfo),
     Cursor(CursorDrawingInfo),
+    Decoder(DecoderDrawingInfo),
 }
 
 impl ItemDrawingInfo {
@@ -66,6 +87,7 @@ impl ItemDrawingInfo {
             ItemDrawingInfo::Signal(drawing_info) => drawing_info.offset,
             ItemDrawingInfo::Divider(drawing_info) => drawing_info.offset,
             ItemDrawingInfo::Cursor(drawing_info) => drawing_info.offset,
+            ItemDrawingInfo::Decoder(drawing_info) => drawing_info.offset,
         }
     }
     pub fn signal_list_idx(&self) -> usize {
@@ -73,6 +95,7 @@ impl ItemDrawingInfo {
             ItemDrawingInfo::Signal(drawing_info) => drawing_info.signal_list_idx,
             ItemDrawingInfo::Divider(drawing_info) => drawing_info.signal_list_idx,
             ItemDrawingInfo::Cursor(drawing_info) => drawing_info.signal_list_idx,
+            ItemDrawingInfo::Decoder(drawing_info) => drawing_info.signal_list_idx,
         }
     }
 }
@@ -218,6 +241,10 @@ impl State {
             draw_progress_panel(ctx, vcd_progress_data);
         }
 
+        if let Some(findings) = &self.analysis_findings {
+            self.draw_analysis_panel(ctx, &mut msgs, findings);
+        }
+
         if let Some(vcd) = &self.waves {
             if !vcd.displayed_items.is_empty() {
                 let item_offsets = egui::SidePanel::left("signal list")
@@ -283,6 +310,10 @@ impl State {
                 if let Some(idx) = self.rename_target {
                     self.draw_rename_window(ctx, &mut msgs, idx);
                 }
+
+                if let Some(idx) = self.frame_table_target {
+                    self.draw_frame_table_window(ctx, &mut msgs, idx);
+                }
             }
         };
 
@@ -317,7 +348,7 @@ impl State {
         }
 
         if self.show_keys {
-            draw_control_help_window(ctx, max_width, max_height, &mut msgs);
+            draw_control_help_window(ctx, &self.config.keys, max_width, max_height, &mut msgs);
         }
 
         if self.show_gestures {
@@ -353,6 +384,10 @@ impl State {
             }
         }
 
+        if self.show_export_image {
+            self.draw_export_image_window(ctx, &mut msgs);
+        }
+
         ctx.input(|i| {
             i.raw.dropped_files.iter().for_each(|file| {
                 info!("Got dropped file");
@@ -361,7 +396,7 @@ impl State {
         });
 
         // If some dialogs are open, skip decoding keypresses
-        if !self.show_url_entry && self.rename_target.is_none() {
+        if !self.show_url_entry && !self.show_export_image && self.rename_target.is_none() {
             self.handle_pressed_keys(ctx, &mut msgs);
         }
 
@@ -501,6 +536,9 @@ impl State {
                     DisplayedItem::Cursor(_) => {
                         self.draw_plain_var(msgs, vidx, &displayed_item, &mut item_offsets, ui);
                     }
+                    DisplayedItem::Decoder(_) => {
+                        self.draw_plain_var(msgs, vidx, &displayed_item, &mut item_offsets, ui);
+                    }
                 },
             );
         }
@@ -670,6 +708,12 @@ impl State {
                     idx: cursor.idx,
                 }))
             }
+            DisplayedItem::Decoder(_) => {
+                item_offsets.push(ItemDrawingInfo::Decoder(DecoderDrawingInfo {
+                    signal_list_idx: vidx,
+                    offset: label.inner.rect.top(),
+                }))
+            }
             &DisplayedItem::Signal(_) => {}
         }
     }
@@ -699,11 +743,7 @@ impl State {
         let (response, mut painter) = ui.allocate_painter(ui.available_size(), Sense::click());
         let container_rect = Rect::from_min_size(Pos2::ZERO, response.rect.size());
         let to_screen = RectTransform::from_to(container_rect, response.rect);
-        let cfg = DrawConfig {
-            canvas_height: response.rect.size().y,
-            line_height: 16.,
-            max_transition_width: 6,
-        };
+        let cfg = DrawConfig::new(response.rect.size().y);
         let frame_width = response.rect.width();
 
         let ctx = DrawingContext {
@@ -755,16 +795,39 @@ impl State {
 
                             let signal = &drawing_info.field_ref.root;
                             let meta = waves.inner.signal_meta(&signal);
+                            let time = num::BigInt::to_biguint(&cursor).unwrap();
+                            let displayed_signal =
+                                match waves.displayed_items.get(drawing_info.signal_list_idx) {
+                                    Some(DisplayedItem::Signal(displayed_signal)) => {
+                                        Some(displayed_signal)
+                                    }
+                                    _ => None,
+                                };
+                            let qualified = match displayed_signal
+                                .and_then(|signal| signal.qualifier.as_ref())
+                            {
+                                Some(qualifier) => waves.qualifier_at(qualifier, &time).0,
+                                None => true,
+                            };
+                            let bits_expanded =
+                                displayed_signal.map_or(false, |signal| signal.bits_expanded);
                             let translation_result = waves
                                 .inner
-                                .query_signal(&signal, &num::BigInt::to_biguint(&cursor).unwrap())
+                                .query_signal(&signal, &time)
                                 .ok()
                                 .flatten()
                                 .map(|(_time, value)| {
-                                    meta.and_then(|meta| translator.translate(&meta, &value))
+                                    meta.and_then(|meta| {
+                                        let bits = if bits_expanded {
+                                            bit_fields(meta.num_bits.unwrap_or(0) as u64, &value)
+                                        } else {
+                                            vec![]
+                                        };
+                                        Ok((translator.translate(&meta, &value)?, bits))
+                                    })
                                 });
 
-                            if let Some(Ok(s)) = translation_result {
+                            if let Some(Ok((s, bits))) = translation_result {
                                 let subfields = s
                                     .flatten(
                                         FieldRef::without_fields(
@@ -773,11 +836,16 @@ impl State {
                                         &waves.signal_format,
                                         &self.translators,
                                     )
-                                    .as_fields();
+                                    .as_fields()
+                                    .into_iter()
+                                    .chain(bits)
+                                    .collect::<Vec<_>>();
 
+                                // Values are not present while the qualifier is low
                                 let subfield = subfields
                                     .iter()
-                                    .find(|(k, _)| k == &drawing_info.field_ref.field);
+                                    .find(|(k, _)| k == &drawing_info.field_ref.field)
+                                    .filter(|_| qualified);
 
                                 if let Some((_, Some((v, _)))) = subfield {
                                     ui.label(v).context_menu(|ui| {
@@ -794,6 +862,23 @@ impl State {
                             }
                         }
                         ItemDrawingInfo::Divider(_) => {}
+                        ItemDrawingInfo::Decoder(drawing_info) => {
+                            let Some(DisplayedItem::Decoder(decoder)) =
+                                waves.displayed_items.get(drawing_info.signal_list_idx)
+                            else {
+                                continue;
+                            };
+                            let frame = cursor.to_biguint().and_then(|cursor| {
+                                decoder
+                                    .frames
+                                    .iter()
+                                    .find(|frame| frame.start <= cursor && cursor < frame.end)
+                            });
+                            let text = frame.map_or("-", |frame| frame.text.as_str());
+                            ui.label(text).context_menu(|ui| {
+                                self.item_context_menu(None, msgs, ui, vidx);
+                            });
+                        }
                         ItemDrawingInfo::Cursor(extra_cursor) => {
                             let delta = time_string(
                                 &(cursor
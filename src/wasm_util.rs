@@ -1,3 +1,5 @@
+#[cfg(target_arch = "wasm32")]
+use color_eyre::{eyre::anyhow, Result};
 use log::info;
 
 // Wasm doesn't seem to support std::thread, so this spawns a thread where we can
@@ -32,3 +34,32 @@ pub fn vcd_from_url() -> Option<String> {
         .and_then(|l| web_sys::UrlSearchParams::new_with_str(&l).ok())
         .and_then(|p| p.get("load_url"))
 }
+
+/// Offer `contents` to the user as a file download named `name`
+#[cfg(target_arch = "wasm32")]
+pub fn download_file(name: &str, contents: &[u8], mime: &str) -> Result<()> {
+    use wasm_bindgen::JsCast;
+
+    let js_error = |e: wasm_bindgen::JsValue| anyhow!("Failed to download {name}: {e:?}");
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &parts,
+        web_sys::BlobPropertyBag::new().type_(mime),
+    )
+    .map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+    let anchor = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| anyhow!("Failed to download {name}: no document"))?
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|e| js_error(e.into()))?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)
+}
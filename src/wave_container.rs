@@ -18,6 +18,11 @@ impl ScopeName {
         result
     }
 
+    /// The names of the scopes on the path to this scope
+    pub fn strs(&self) -> &[String] {
+        &self.0
+    }
+
     pub(crate) fn name(&self) -> String {
         self.0.last().cloned().unwrap_or_else(|| String::new())
     }